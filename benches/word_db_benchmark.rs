@@ -0,0 +1,82 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use latin_db::latin_utilities::{NormalizedLatinString, StandardLatinConverter};
+use latin_db::word_db::{WordDatabase, WordDb, WordId};
+use std::sync::Arc;
+use std::thread;
+
+static WORDS: usize = 100_000;
+static THREADS: usize = 4;
+
+fn make_words() -> Vec<NormalizedLatinString> {
+    let conver = StandardLatinConverter::default();
+    (0..WORDS)
+        .map(|i| conver.convert(format!("uerbum{}", i)))
+        .collect()
+}
+
+fn make_db(words: &[NormalizedLatinString]) -> (WordDb, Vec<WordId>) {
+    let db = WordDb::default();
+    let ids = words.iter().map(|w| db.intern_word(w.clone())).collect();
+    (db, ids)
+}
+
+fn intern_benchmark(c: &mut Criterion) {
+    let words = make_words();
+
+    c.bench_function("intern new words", |b| {
+        b.iter(|| black_box(make_db(&words)))
+    });
+
+    let (db, _) = make_db(&words);
+    c.bench_function("intern known words", |b| {
+        b.iter(|| {
+            for w in &words {
+                black_box(db.intern_word(w.clone()));
+            }
+        })
+    });
+}
+
+fn lookup_benchmark(c: &mut Criterion) {
+    let words = make_words();
+    let (db, ids) = make_db(&words);
+
+    c.bench_function("lookup words", |b| {
+        b.iter(|| {
+            for &id in &ids {
+                black_box(db.lookup_word_str(id));
+            }
+        })
+    });
+
+    c.bench_function("lookup ids", |b| {
+        b.iter(|| {
+            for w in &words {
+                black_box(db.find(w.inner()));
+            }
+        })
+    });
+
+    // The same lookups, from a few threads at once
+    let ids = Arc::new(ids);
+    c.bench_function("lookup words in threads", |b| {
+        b.iter(|| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    let (db, ids) = (db.clone(), Arc::clone(&ids));
+                    thread::spawn(move || {
+                        for &id in ids.iter() {
+                            black_box(db.lookup_word_str(id));
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, intern_benchmark, lookup_benchmark);
+criterion_main!(benches);
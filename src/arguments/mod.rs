@@ -1,19 +1,107 @@
 use crate::query_driver::{Configuration, LemmMode};
-use clap::{load_yaml, App};
+use clap::{load_yaml, App, Arg, ArgMatches};
+use std::ffi::OsString;
+
+pub mod output;
 
 pub fn load_configuration() -> Configuration {
+    load_configuration_with(&[], |_| ()).0
+}
+
+/// Load the configuration, together with some arguments that are specific to a binary
+pub fn load_configuration_with<T>(
+    extra: &[Arg<'static, 'static>],
+    extract: impl FnOnce(&ArgMatches) -> T,
+) -> (Configuration, T) {
+    configuration_from(std::env::args_os(), extra, extract).unwrap_or_else(|e| e.exit())
+}
+
+/// Parse the configuration out of a command line, i.e. the one a binary was called with
+pub fn configuration_from<T, I>(
+    args: I,
+    extra: &[Arg<'static, 'static>],
+    extract: impl FnOnce(&ArgMatches) -> T,
+) -> clap::Result<(Configuration, T)>
+where
+    I: IntoIterator,
+    I::Item: Into<OsString> + Clone,
+{
     let yaml = load_yaml!("cli.yml");
-    let app = App::from_yaml(yaml).get_matches();
+    let app = App::from_yaml(yaml)
+        .args(extra)
+        .get_matches_from_safe(args)?;
 
-    Configuration::new(
+    let config = Configuration::new(
         app.value_of("data_path").unwrap(),
         app.value_of("lemmatizer").unwrap(),
         app.value_of("authors_path"),
         if app.value_of("useLemlat").is_some() {
             LemmMode::LemlatFormat
+        } else if app.is_present("useConllu") {
+            LemmMode::ConlluFormat
         } else {
             LemmMode::CSVFormat
         },
     )
-    .unwrap()
+    .unwrap();
+
+    let config = match app.value_of("manifest") {
+        Some(path) => config.with_manifest(path),
+        None => config,
+    };
+
+    let config = match app.value_of("normalization") {
+        Some(profile) => {
+            config.with_normalization(profile.parse().expect("Invalid normalization profile"))
+        }
+        None => config,
+    };
+
+    let config = match app.value_of("snapshot") {
+        Some(path) => config.with_snapshot(path),
+        None => config,
+    };
+
+    let config = match app.value_of("overrides") {
+        Some(path) => config.with_overrides(path),
+        None => config,
+    };
+
+    let config = match app.value_of("jobs") {
+        Some(threads) => config.with_warm_up(threads.parse().map_err(|_| {
+            clap::Error::value_validation_auto(format!("Invalid number of jobs {:?}", threads))
+        })?),
+        None => config,
+    };
+
+    Ok((config, extract(&app)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_arguments() {
+        let args = vec![
+            "latin_db",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--overrides",
+            "o.json",
+        ];
+        let (_, overrides) =
+            configuration_from(args, &[], |app| app.value_of("overrides").map(String::from))
+                .unwrap();
+        assert_eq!(overrides.as_deref(), Some("o.json"));
+
+        let args = vec!["latin_db", "-d", "src"];
+        assert!(configuration_from(args, &[], |_| ()).is_err());
+
+        let args = vec!["latin_db", "-d", "src", "-l", "Cargo.toml", "-j", "all"];
+        let err = configuration_from(args, &[], |_| ()).err().unwrap();
+        assert_eq!(err.kind, clap::ErrorKind::ValueValidation);
+    }
 }
@@ -0,0 +1,40 @@
+//! The files the binaries write their results to
+
+use clap::{Arg, ArgMatches};
+
+/// The argument giving the file to write to
+pub fn output_arg() -> Arg<'static, 'static> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("The file to write to")
+}
+
+/// The file to write to, the given one or else the default
+pub fn output_path(app: &ArgMatches, default: &str) -> String {
+    app.value_of("output").unwrap_or(default).to_string()
+}
+
+/// Quote a field of a csv file, doubling the quotes inside it
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::App;
+
+    #[test]
+    fn test_output() {
+        let app = App::new("test").arg(output_arg());
+        let matches = app.clone().get_matches_from(vec!["test", "-o", "out.csv"]);
+        assert_eq!(output_path(&matches, "res.csv"), "out.csv");
+        let matches = app.get_matches_from(vec!["test"]);
+        assert_eq!(output_path(&matches, "res.csv"), "res.csv");
+
+        assert_eq!(quote("arma \"uirumque\""), "\"arma \"\"uirumque\"\"\"");
+    }
+}
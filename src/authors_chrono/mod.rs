@@ -1,23 +1,48 @@
+use crate::query_system::ids::AuthorId;
+
 use chrono::prelude::*;
 use derivative::Derivative;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
 
 pub mod parsers;
 
+/// How much a date can be trusted, from the least to the most
+#[derive(
+    Debug, Default, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum Certainty {
+    /// Only a bound is known (i.e. `ante 30d`)
+    Terminus,
+    /// An approximation (i.e. `c. 43a`, or `fl. 1a`)
+    Approximate,
+    #[default]
+    Exact,
+}
+
 /// A struct representing the span between two dates
-#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TimeSpan {
     start: NaiveDate,
     end: NaiveDate,
+    certainty: Certainty,
 }
 
 impl TimeSpan {
     /// Instantiate the TimeSpan
     pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
         assert!(start <= end);
-        Self { start, end }
+        Self {
+            start,
+            end,
+            certainty: Certainty::Exact,
+        }
+    }
+
+    /// Set how much the dates can be trusted
+    pub fn with_certainty(self, certainty: Certainty) -> Self {
+        Self { certainty, ..self }
     }
 
     /// Time span from century
@@ -45,9 +70,24 @@ impl TimeSpan {
         &self.end
     }
 
+    /// Get how much the dates can be trusted
+    pub fn certainty(&self) -> Certainty {
+        self.certainty
+    }
+
     /// Get a tuple with the centuries spanned
     pub fn get_century(&self) -> (i32, i32) {
-        (self.start().year() / 100, self.end().year() / 100)
+        (century(self.start().year()), century(self.end().year()))
+    }
+}
+
+// The century of a year (negative before Christ)
+// Note, the years that close a century (i.e. 100, the way a century is written down) belong to it
+fn century(year: i32) -> i32 {
+    if year % 100 == 0 {
+        year / 100
+    } else {
+        year / 100 + year.signum()
     }
 }
 
@@ -70,7 +110,7 @@ impl PartialOrd for TimeSpan {
 }
 
 /// Our representation of an Author
-#[derive(Derivative, Serialize)]
+#[derive(Derivative, Serialize, Deserialize)]
 #[derivative(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Author {
     name: String,
@@ -108,8 +148,96 @@ impl Author {
 
     /// Was this author present in this time, if we know?
     pub fn in_timespan(&self, time_span: &TimeSpan) -> bool {
+        self.in_timespan_with(time_span, Certainty::Terminus)
+    }
+
+    /// Was this author present in this time, as far as dates at least this certain say?
+    pub fn in_timespan_with(&self, time_span: &TimeSpan, certainty: Certainty) -> bool {
         match &self.time_span {
-            Some(t) => time_span.contains(t),
+            Some(t) => t.certainty() >= certainty && time_span.contains(t),
+            None => false,
+        }
+    }
+}
+
+/// A work of an author, which might be split across several sources (i.e. the books of the Aeneid)
+/// Note, a work is identified by its author and its title
+#[derive(Derivative)]
+#[derivative(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Work {
+    author: AuthorId,
+    title: String,
+    #[derivative(PartialEq = "ignore")]
+    #[derivative(Hash = "ignore")]
+    time_span: Option<TimeSpan>,
+    #[derivative(PartialEq = "ignore")]
+    #[derivative(Hash = "ignore")]
+    genre: Option<String>,
+}
+
+impl Work {
+    /// Instantiate a new work, with no date nor genre
+    pub fn new(author: AuthorId, title: impl ToString) -> Self {
+        Self {
+            author,
+            title: title.to_string(),
+            time_span: None,
+            genre: None,
+        }
+    }
+
+    /// Set when the work was written
+    pub fn with_tspan(self, time_span: TimeSpan) -> Self {
+        Self {
+            time_span: Some(time_span),
+            ..self
+        }
+    }
+
+    pub fn with_genre(self, genre: impl ToString) -> Self {
+        Self {
+            genre: Some(genre.to_string()),
+            ..self
+        }
+    }
+
+    pub fn author(&self) -> AuthorId {
+        self.author
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Get when the work was written, if we know
+    pub fn tspan(&self) -> Option<&TimeSpan> {
+        self.time_span.as_ref()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    /// Get when the work was written, or else when its author was active
+    pub fn dating<'a>(&'a self, author: &'a Author) -> Option<&'a TimeSpan> {
+        self.tspan().or_else(|| author.tspan())
+    }
+
+    /// Was this work written in this time, if we know?
+    /// Note, a work with no date of its own takes the one of its author
+    pub fn in_timespan(&self, author: &Author, time_span: &TimeSpan) -> bool {
+        self.in_timespan_with(author, time_span, Certainty::Terminus)
+    }
+
+    /// Was this work written in this time, as far as dates at least this certain say?
+    pub fn in_timespan_with(
+        &self,
+        author: &Author,
+        time_span: &TimeSpan,
+        certainty: Certainty,
+    ) -> bool {
+        match self.dating(author) {
+            Some(t) => t.certainty() >= certainty && time_span.contains(t),
             None => false,
         }
     }
@@ -119,13 +247,36 @@ impl Author {
 pub fn split_by_century<'a>(
     iter: impl IntoIterator<Item = &'a Author>,
 ) -> BTreeMap<i32, Vec<&'a Author>> {
+    split_by_century_with(iter, Certainty::Terminus)
+}
+
+/// The same as `split_by_century`, leaving out the authors whose dates are less certain
+pub fn split_by_century_with<'a>(
+    iter: impl IntoIterator<Item = &'a Author>,
+    certainty: Certainty,
+) -> BTreeMap<i32, Vec<&'a Author>> {
+    let dated = iter.into_iter().map(|author| {
+        let span = author.tspan().filter(|s| s.certainty() >= certainty);
+        (author, span)
+    });
+    split_spans_by_century(dated)
+}
+
+/// Given an iterator over anything dated (i.e. the works, by their own date or their author's),
+/// construct a mapping that buckets them by date
+pub fn split_spans_by_century<'a, T>(
+    iter: impl IntoIterator<Item = (T, Option<&'a TimeSpan>)>,
+) -> BTreeMap<i32, Vec<T>>
+where
+    T: Clone,
+{
     let mut res = BTreeMap::new();
-    for author in iter.into_iter() {
+    for (item, span) in iter.into_iter() {
         // Skip null
-        if author.tspan().is_none() {
-            continue;
-        }
-        let span = author.tspan().unwrap();
+        let span = match span {
+            Some(span) => span,
+            None => continue,
+        };
         let (s, e) = span.get_century();
         let mut possible_centuries = Vec::new();
         for i in s..=e {
@@ -137,7 +288,7 @@ pub fn split_by_century<'a>(
         }
 
         for cent in possible_centuries {
-            res.entry(cent).or_insert_with(Vec::new).push(author);
+            res.entry(cent).or_insert_with(Vec::new).push(item.clone());
         }
     }
 
@@ -208,4 +359,60 @@ mod tests {
         assert_eq!(first_cen.len(), 1);
         assert_eq!(first_cen[0].name(), "first");
     }
+
+    #[test]
+    fn years_century() {
+        let year = |y| NaiveDate::from_ymd(y, 1, 1);
+        assert_eq!(TimeSpan::new(year(-43), year(17)).get_century(), (-1, 1));
+        assert_eq!(TimeSpan::new(year(101), year(1999)).get_century(), (2, 20));
+    }
+
+    #[test]
+    fn certainty() {
+        let first = TimeSpan::new(make_century(-1), make_century(1));
+        let exact = author_single_cent("exact", 1);
+        let approximate = Author::new_with_tspan(
+            "approximate",
+            TimeSpan::new(make_century(1), make_century(1)).with_certainty(Certainty::Approximate),
+        );
+
+        assert!(approximate.in_timespan(&first));
+        assert!(!approximate.in_timespan_with(&first, Certainty::Exact));
+        assert!(exact.in_timespan_with(&first, Certainty::Exact));
+
+        let authors = [exact, approximate];
+        assert_eq!(split_by_century(authors.iter())[&1].len(), 2);
+        assert_eq!(
+            split_by_century_with(authors.iter(), Certainty::Exact)[&1].len(),
+            1
+        );
+    }
+
+    #[test]
+    fn work_dating() {
+        let author = author_single_cent("Ovidius", 1);
+        let undated = Work::new(AuthorId::from_integer(0), "Amores");
+        let dated = Work::new(AuthorId::from_integer(0), "Tristia")
+            .with_tspan(TimeSpan::new(make_century(2), make_century(2)));
+
+        let first = TimeSpan::new(make_century(1), make_century(1));
+        assert!(undated.in_timespan(&author, &first));
+        assert!(!dated.in_timespan(&author, &first));
+
+        // Note, the certainty is the one of the date the work is known by
+        let approximate = Work::new(AuthorId::from_integer(0), "Fasti").with_tspan(
+            TimeSpan::new(make_century(1), make_century(1)).with_certainty(Certainty::Approximate),
+        );
+        assert!(approximate.in_timespan(&author, &first));
+        assert!(!approximate.in_timespan_with(&author, &first, Certainty::Exact));
+        assert!(undated.in_timespan_with(&author, &first, Certainty::Exact));
+
+        let res = split_spans_by_century(
+            vec![&undated, &dated]
+                .into_iter()
+                .map(|w| (w.title(), w.dating(&author))),
+        );
+        assert_eq!(res[&1], vec!["Amores"]);
+        assert_eq!(res[&2], vec!["Tristia"]);
+    }
 }
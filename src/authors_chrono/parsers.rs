@@ -1,15 +1,33 @@
-use super::{Author, TimeSpan};
+//! The authors file, where each line dates an author (or a single work of theirs), i.e.
+//! ```text
+//! Publius Ovidius Naso #(y43a, y17d)
+//! Publius Ovidius Naso / Tristia #(c. y9d, y17d)
+//! Titus Livius #(1a, 1d)
+//! Anonymous #(post 2d, ante 4d)
+//! Epigraphs #?
+//! ```
+//! A date is either a century (`1a`, `10d`, as the points that close them) or an exact year
+//! written with a `y` (`y43a`, `y17d`), each optionally preceded by `c.` or `fl.` (an approximation),
+//! or by `ante` and `post` (a terminus). A span is one or two dates, and its certainty is the one
+//! of its least certain date.
 
-use chrono::NaiveDate;
-use regex::Regex;
-use std::collections::BTreeSet;
+use super::{Author, Certainty, TimeSpan};
+
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
+use std::fmt;
 use std::io::{self, prelude::*, BufReader};
 
+// How far from a terminus the span goes, in years, when there is nothing else to bound it
+const TERMINUS_YEARS: i32 = 100;
+
 /// A parser for our homemade and well loved format
+/// Note, a single work can be dated as well, on a line of the form `author_name / work_title #(...)`
 #[derive(Debug, Default)]
 pub struct WeirdParser {
     authors: BTreeSet<Author>,
+    works: BTreeMap<(String, String), TimeSpan>,
 }
 
 /// The line where the parsing failed
@@ -23,26 +41,162 @@ pub enum ParsingError {
     InvalidNumberOfChunks(usize, LineNo),
     /// Either 0, or 3+ dates in the line
     InvalidNumberOfDates(usize, LineNo),
+    /// A date that cannot be read
+    InvalidDate(String, LineNo),
+    /// A span that ends before it starts
+    ReversedSpan(String, LineNo),
+    /// Reading the file failed
+    Io(io::Error),
 }
 
-impl std::fmt::Display for ParsingError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Note, the lines are numbered from one, as in an editor
+        match self {
+            ParsingError::InvalidNumberOfChunks(n, LineNo(l)) => {
+                write!(f, "Line {}: expected one '#', found {}", l + 1, n - 1)
+            }
+            ParsingError::InvalidNumberOfDates(n, LineNo(l)) => {
+                write!(f, "Line {}: expected one or two dates, found {}", l + 1, n)
+            }
+            ParsingError::InvalidDate(date, LineNo(l)) => {
+                write!(f, "Line {}: invalid date {:?}", l + 1, date)
+            }
+            ParsingError::ReversedSpan(span, LineNo(l)) => {
+                write!(
+                    f,
+                    "Line {}: the span {:?} ends before it starts",
+                    l + 1,
+                    span
+                )
+            }
+            ParsingError::Io(err) => write!(f, "{}", err),
+        }
     }
 }
 
 impl Error for ParsingError {}
 
-fn parse_segment(s: &str) -> NaiveDate {
-    let re = Regex::new("(\\d)(a|d)").unwrap();
-    let captures = re.captures(s).unwrap();
-    // Note the first is the whole str
-    let century = captures.get(1).unwrap().as_str().parse::<i32>().unwrap();
-    let avanti_o_dietro = captures.get(2).unwrap().as_str();
-    match avanti_o_dietro {
-        "a" => NaiveDate::from_ymd(century * -100, 1, 1),
-        "d" => NaiveDate::from_ymd(century * 100, 1, 1),
-        _ => unreachable!(),
+impl From<io::Error> for ParsingError {
+    fn from(err: io::Error) -> Self {
+        ParsingError::Io(err)
+    }
+}
+
+// A date of the span, as the years it lies between
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    earliest: i32,
+    latest: i32,
+    certainty: Certainty,
+}
+
+// Read a year or a century, i.e. `y43a` or `10d`
+fn parse_year(s: &str) -> Option<i32> {
+    let (is_year, s) = match s.strip_prefix('y') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let sign = match s.chars().last()? {
+        'a' => -1,
+        'd' => 1,
+        _ => return None,
+    };
+
+    let n = s[..s.len() - 1].parse::<i32>().ok().filter(|&n| n > 0)?;
+    let years = if is_year { n } else { n.checked_mul(100)? };
+    Some(sign * years)
+}
+
+fn parse_point(s: &str) -> Option<Point> {
+    let s = s.trim();
+    let qualifiers = [
+        ("c.", Certainty::Approximate),
+        ("ca.", Certainty::Approximate),
+        ("fl.", Certainty::Approximate),
+        ("ante", Certainty::Terminus),
+        ("post", Certainty::Terminus),
+    ];
+
+    let (qualifier, certainty, date) = qualifiers
+        .iter()
+        .find_map(|&(q, c)| s.strip_prefix(q).map(|rest| (q, c, rest.trim())))
+        .unwrap_or(("", Certainty::Exact, s));
+
+    let year = parse_year(date)?;
+    let (earliest, latest) = match qualifier {
+        "ante" => (year.checked_sub(TERMINUS_YEARS)?, year),
+        "post" => (year, year.checked_add(TERMINUS_YEARS)?),
+        _ => (year, year),
+    };
+
+    Some(Point {
+        earliest,
+        latest,
+        certainty,
+    })
+}
+
+// Read the dates of a line (i.e. `(y43a, y17d)`), none if they are unknown (`?`)
+fn parse_span(s: &str, num: LineNo) -> Result<Option<TimeSpan>, ParsingError> {
+    // No hist info
+    if s.contains('?') {
+        return Ok(None);
+    }
+
+    let inner = s.trim().trim_end_matches(')').trim_start_matches('(');
+    let segments: Vec<_> = inner.split(',').collect();
+    if segments.is_empty() || segments.len() > 2 {
+        return Err(ParsingError::InvalidNumberOfDates(segments.len(), num));
+    }
+
+    let mut points = Vec::new();
+    for segment in &segments {
+        match parse_point(segment) {
+            Some(point) => points.push(point),
+            None => return Err(ParsingError::InvalidDate(segment.trim().to_string(), num)),
+        }
+    }
+
+    // Note, a single date is a span of its own (so that `ante` and `post` keep their bounds)
+    let start = points[0].earliest;
+    let end = points[points.len() - 1].latest;
+    if start > end {
+        return Err(ParsingError::ReversedSpan(inner.trim().to_string(), num));
+    }
+
+    let year = |y| NaiveDate::from_ymd_opt(y, 1, 1);
+    let (start, end) = match (year(start), year(end)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Err(ParsingError::InvalidDate(inner.trim().to_string(), num)),
+    };
+
+    let certainty = points.iter().map(|p| p.certainty).min().unwrap();
+    Ok(Some(TimeSpan::new(start, end).with_certainty(certainty)))
+}
+
+/// Write the dates of a span the way they are read, i.e. `(1a, y17d)`
+pub fn format_span(span: &TimeSpan) -> String {
+    let date = |year: i32| {
+        let era = if year < 0 { 'a' } else { 'd' };
+        if year != 0 && year % 100 == 0 {
+            format!("{}{}", year.abs() / 100, era)
+        } else {
+            format!("y{}{}", year.abs(), era)
+        }
+    };
+
+    let (start, end) = (span.start().year(), span.end().year());
+    let (before, after) = match span.certainty() {
+        Certainty::Exact => ("", ""),
+        Certainty::Approximate => ("c. ", "c. "),
+        Certainty::Terminus => ("post ", "ante "),
+    };
+
+    if start == end && span.certainty() != Certainty::Terminus {
+        format!("({}{})", before, date(start))
+    } else {
+        format!("({}{}, {}{})", before, date(start), after, date(end))
     }
 }
 
@@ -51,7 +205,7 @@ impl WeirdParser {
     pub fn read_all(&mut self, read: impl io::Read) -> Result<(), ParsingError> {
         let bufreader = BufReader::new(read);
         for (i, line) in bufreader.lines().enumerate() {
-            self.read_line(&line.unwrap(), LineNo(i))?;
+            self.read_line(&line?, LineNo(i))?;
         }
         Ok(())
     }
@@ -61,9 +215,14 @@ impl WeirdParser {
         self.authors
     }
 
+    /// Get the dates of the single works, by author and title
+    pub fn work_spans(&self) -> &BTreeMap<(String, String), TimeSpan> {
+        &self.works
+    }
+
     fn read_line(&mut self, line: &str, num: LineNo) -> Result<(), ParsingError> {
         // We skip these lines
-        if line.contains('~') {
+        if line.contains('~') || line.trim().is_empty() {
             return Ok(());
         }
 
@@ -72,35 +231,26 @@ impl WeirdParser {
             return Err(ParsingError::InvalidNumberOfChunks(chunks.len(), num));
         }
 
-        let author_name = chunks[0].trim();
-        let span = chunks[1];
-
-        // No hist info
-        if span.contains('?') {
-            self.authors.insert(Author::new(author_name));
-            return Ok(());
-        }
-
-        let inner = span.trim_end_matches(')').trim_start_matches('(');
+        let mut names = chunks[0].splitn(2, '/');
+        let author_name = names.next().unwrap().trim();
+        let work_title = names.next().map(str::trim);
+        let span = parse_span(chunks[1], num)?;
 
-        let segments: Vec<_> = inner.split(',').collect();
-
-        if segments.is_empty() || segments.len() > 2 {
-            return Err(ParsingError::InvalidNumberOfDates(segments.len(), num));
+        match (work_title, span) {
+            (Some(title), Some(span)) => {
+                let key = (author_name.to_string(), title.to_string());
+                self.works.insert(key, span);
+            }
+            (Some(_), None) => {}
+            (None, Some(span)) => {
+                self.authors
+                    .insert(Author::new_with_tspan(author_name, span));
+            }
+            (None, None) => {
+                self.authors.insert(Author::new(author_name));
+            }
         }
 
-        let start = parse_segment(segments[0]);
-        let end = if segments.len() == 2 {
-            parse_segment(segments[1])
-        } else {
-            start
-        };
-
-        self.authors.insert(Author::new_with_tspan(
-            author_name,
-            TimeSpan::new(start, end),
-        ));
-
         Ok(())
     }
 }
@@ -109,10 +259,88 @@ impl WeirdParser {
 mod tests {
     use super::*;
 
+    fn years(span: &TimeSpan) -> (i32, i32, Certainty) {
+        (span.start().year(), span.end().year(), span.certainty())
+    }
+
+    fn parse(s: &str) -> (i32, i32, Certainty) {
+        years(&parse_span(s, LineNo(0)).unwrap().unwrap())
+    }
+
     #[test]
     fn test_empty() {
         let parser = WeirdParser::default();
         let res = parser.build();
         assert_eq!(res.len(), 0);
     }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse("(1a, 1d)"), (-100, 100, Certainty::Exact));
+        assert_eq!(parse("(10d)"), (1000, 1000, Certainty::Exact));
+        assert_eq!(parse("(y43a, y17d)"), (-43, 17, Certainty::Exact));
+        assert_eq!(parse("(c. y43a, y17d)"), (-43, 17, Certainty::Approximate));
+        assert_eq!(parse("(fl. 1a)"), (-100, -100, Certainty::Approximate));
+        assert_eq!(parse("(ante y30d)"), (-70, 30, Certainty::Terminus));
+        assert_eq!(parse("(post 2d, ante 4d)"), (200, 400, Certainty::Terminus));
+        assert!(parse_span(" ?", LineNo(0)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_format() {
+        for s in &[
+            "(1a, 1d)",
+            "(y17d)",
+            "(c. y43a, c. 1d)",
+            "(post 2d, ante y350d)",
+        ] {
+            let span = parse_span(s, LineNo(0)).unwrap().unwrap();
+            assert_eq!(format_span(&span), *s);
+            let again = parse_span(&format_span(&span), LineNo(0)).unwrap();
+            assert_eq!(again, Some(span));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut parser = WeirdParser::default();
+        let err = parser
+            .read_all("Vergilius #(1a)\nOvidius #(y43x)\n".as_bytes())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: invalid date \"y43x\"");
+
+        let mut parser = WeirdParser::default();
+        let err = parser
+            .read_all("Vergilius #(1d, 1a)\n".as_bytes())
+            .unwrap_err();
+        assert!(matches!(err, ParsingError::ReversedSpan(_, LineNo(0))));
+
+        let mut parser = WeirdParser::default();
+        let err = parser.read_all("Vergilius 1a\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, ParsingError::InvalidNumberOfChunks(1, _)));
+    }
+
+    #[test]
+    fn test_works() {
+        let mut parser = WeirdParser::default();
+        parser
+            .read_all(
+                "Publius Ovidius Naso #(1a, 1d)\nPublius Ovidius Naso / Tristia #(1d)\n".as_bytes(),
+            )
+            .unwrap();
+
+        let key = ("Publius Ovidius Naso".to_string(), "Tristia".to_string());
+        assert_eq!(
+            parser.work_spans()[&key],
+            TimeSpan::new(
+                NaiveDate::from_ymd(100, 1, 1),
+                NaiveDate::from_ymd(100, 1, 1)
+            )
+        );
+
+        let authors = parser.build();
+        assert_eq!(authors.len(), 1);
+        let span = authors.iter().next().unwrap().tspan().unwrap();
+        assert_eq!(*span.start(), NaiveDate::from_ymd(-100, 1, 1));
+    }
 }
@@ -0,0 +1,228 @@
+use latin_db::arguments::load_configuration_with;
+use latin_db::arguments::output::{output_arg, output_path, quote};
+use latin_db::latin_utilities::NormalizedLatinString;
+use latin_db::query_driver::{self, driver_init};
+use latin_db::query_system::concordance::{self, ConcordanceLine, ContextSort, Window};
+use latin_db::query_system::lit_subset::LitSubset;
+use latin_db::query_system::traits::*;
+use latin_db::query_system::types::Lemma;
+
+use clap::Arg;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+// The width, in chars, of each side of the context in the text format
+const CONTEXT_WIDTH: usize = 50;
+
+struct Options {
+    lemma: String,
+    window: Window,
+    sort: ContextSort,
+    csv: bool,
+    authors: Option<HashSet<String>>,
+    output: String,
+}
+
+fn options() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("lemma")
+            .long("lemma")
+            .takes_value(true)
+            .value_name("LEMMA")
+            .help("The lemma to build the concordance of")
+            .required(true),
+        Arg::with_name("window")
+            .long("window")
+            .takes_value(true)
+            .value_name("N")
+            .help("The amount of context on each side (default 5)"),
+        Arg::with_name("lines")
+            .long("lines")
+            .help("Measure the window in lines rather than in words"),
+        Arg::with_name("sort")
+            .long("sort")
+            .takes_value(true)
+            .possible_values(&["textual", "left", "right"])
+            .help("How to order the occurrences"),
+        Arg::with_name("csv")
+            .long("csv")
+            .help("Write a csv file rather than a text one"),
+        Arg::with_name("only")
+            .long("only")
+            .takes_value(true)
+            .multiple(true)
+            .value_name("AUTHOR")
+            .help("Restrict the concordance to some authors"),
+        output_arg(),
+    ]
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("RUST_LOG", "info");
+    std::env::set_var("RUST_BACKTRACE", "1");
+    color_backtrace::install();
+    env_logger::init();
+    let (config, opts) = load_configuration_with(&options(), |app| -> Result<_, String> {
+        let window = app
+            .value_of("window")
+            .unwrap_or("5")
+            .parse::<usize>()
+            .map_err(|_| "Invalid window size".to_string())?;
+
+        Ok(Options {
+            lemma: app.value_of("lemma").unwrap().to_string(),
+            window: if app.is_present("lines") {
+                Window::Lines(window)
+            } else {
+                Window::Tokens(window)
+            },
+            sort: match app.value_of("sort") {
+                Some("left") => ContextSort::Left,
+                Some("right") => ContextSort::Right,
+                _ => ContextSort::Textual,
+            },
+            csv: app.is_present("csv"),
+            output: output_path(
+                app,
+                if app.is_present("csv") {
+                    "concordance.csv"
+                } else {
+                    "concordance.txt"
+                },
+            ),
+            authors: app
+                .values_of("only")
+                .map(|names| names.map(String::from).collect()),
+        })
+    });
+    let opts = opts?;
+    let db = driver_init(config)?;
+
+    let authors: Vec<_> = db
+        .authors()
+        .iter()
+        .filter(|(author, _)| match &opts.authors {
+            Some(names) => names.contains(author.name()),
+            None => true,
+        })
+        .map(|(_, id)| *id)
+        .collect();
+    let lit = LitSubset::from_authors(authors.iter(), &db.snapshot());
+
+    let lemma = db
+        .lookup_interned_word(NormalizedLatinString::from(opts.lemma.as_str()))
+        .map(Lemma)
+        .ok_or_else(|| format!("Unknown lemma {}", opts.lemma))?;
+
+    let occurrences = db.lemma_occurrences_subset(lemma, lit);
+    let lines = concordance::concordance(&db, occurrences.iter().cloned(), opts.window, opts.sort);
+
+    let mut out = File::create(&opts.output)?;
+
+    if opts.csv {
+        writeln!(out, "author,source,line,citation,left,keyword,right")?;
+    }
+
+    for line in &lines {
+        if opts.csv {
+            write_csv(&db, &mut out, line)?;
+        } else {
+            write_text(&db, &mut out, line)?;
+        }
+    }
+
+    Ok(())
+}
+
+// The name of the author and of the file where an occurrence is
+fn origin(db: &query_driver::MainDatabase, line: &ConcordanceLine) -> (String, String) {
+    let fd = db.lookup_intern_form_data(line.occurrence());
+    let author = db.lookup_intern_author(fd.author(db)).name().to_string();
+    let source = db
+        .sources()
+        .get_by_right(&fd.source())
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    (author, source)
+}
+
+fn write_csv(
+    db: &query_driver::MainDatabase,
+    w: &mut impl Write,
+    line: &ConcordanceLine,
+) -> io::Result<()> {
+    let fd = db.lookup_intern_form_data(line.occurrence());
+    let (author, source) = origin(db, line);
+
+    writeln!(
+        w,
+        "{},{},{},{},{},{},{}",
+        quote(&author),
+        quote(&source),
+        fd.line_no() + 1,
+        quote(&fd.citation(db).unwrap_or_default()),
+        quote(&line.left()),
+        quote(line.keyword()),
+        quote(&line.right())
+    )
+}
+
+// Classic KWIC layout, with the keywords aligned in a column
+fn write_text(
+    db: &query_driver::MainDatabase,
+    w: &mut impl Write,
+    line: &ConcordanceLine,
+) -> io::Result<()> {
+    let fd = db.lookup_intern_form_data(line.occurrence());
+    let (author, source) = origin(db, line);
+    let reference = fd
+        .citation(db)
+        .unwrap_or_else(|| format!("{}:{}", source, fd.line_no() + 1));
+
+    // Keep the part of the context that is closest to the keyword
+    let left = line.left();
+    let left_len = left.chars().count();
+    let left: String = left
+        .chars()
+        .skip(left_len.saturating_sub(CONTEXT_WIDTH))
+        .collect();
+    let right: String = line.right().chars().take(CONTEXT_WIDTH).collect();
+
+    writeln!(
+        w,
+        "{:>width$}  {}  {:<width$}  ({}, {})",
+        left,
+        line.keyword(),
+        right,
+        author,
+        reference,
+        width = CONTEXT_WIDTH
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_db::arguments::configuration_from;
+
+    #[test]
+    fn test_arguments() {
+        let args = vec![
+            "concordance",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--overrides",
+            "o.json",
+            "--lemma",
+            "arma",
+            "-o",
+            "out.txt",
+        ];
+        configuration_from(args, &options(), |_| ()).unwrap();
+    }
+}
@@ -1,86 +1,156 @@
-use latin_db::arguments::load_configuration;
+use latin_db::arguments::load_configuration_with;
+use latin_db::authors_chrono::split_spans_by_century;
 use latin_db::query_driver::driver_init;
 use latin_db::query_system::ids::*;
 use latin_db::query_system::lit_subset::LitSubset;
 use latin_db::query_system::traits::*;
 use latin_db::query_system::types::{Form, Lemma};
+use latin_db::query_system::weights::{CountMode, Weighting};
 
-use std::collections::{BTreeMap, HashMap, HashSet};
+use clap::Arg;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{self, prelude::*};
 
 const AUTHOR_SCALE_FACTOR: usize = 1_000;
+const WORK_SCALE_FACTOR: usize = 1_000;
 const HISTORIC_SCALE_FACTOR: usize = 1_000;
+const MAX_CITATIONS: usize = 10;
+// How many words around an ambiguous occurrence are used to split it
+const CONTEXT_WINDOW: usize = 3;
+
+fn options() -> Vec<Arg<'static, 'static>> {
+    vec![Arg::with_name("works")
+        .long("works")
+        .help("Add the count and the frequency in each work, after the ones of the authors")]
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_LOG", "info");
     std::env::set_var("RUST_BACKTRACE", "1");
     color_backtrace::install();
     env_logger::init();
-    let db = driver_init(load_configuration())?;
+    let (config, by_works) = load_configuration_with(&options(), |app| app.is_present("works"));
+    let db = driver_init(config.warm_up_by_default())?;
     let lit = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
-    let alpha = Dictionary::new(&db, lit.clone());
+    let weighting = Weighting::new(lit.clone()).with_context(CONTEXT_WINDOW);
+    let alpha = Dictionary::new(&db, lit.clone(), weighting);
 
-    let author_count = db.authors_count(lit);
+    let author_count: HashMap<_, _> = db
+        .authors_count(lit.clone(), CountMode::Every)
+        .iter()
+        .map(|(&author, count)| (author, count.value() as usize))
+        .collect();
     let author_names: BTreeMap<_, _> = author_count
         .keys()
         .map(|a| (db.lookup_intern_author(*a).name(), *a))
         .collect();
 
+    let work_count = db.works_count(lit);
+    // Note, the works are ordered by author, then by title
+    let work_names: BTreeMap<_, _> = if by_works {
+        work_count
+            .keys()
+            .map(|w| {
+                let work = db.lookup_intern_work(*w);
+                let author = db.lookup_intern_author(work.author()).name();
+                ((author, work.title()), *w)
+            })
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+
     let file = &mut File::create("export.csv")?;
-    write!(file, "lemma,count,certain,ambigous,")?;
+    write!(file, "lemma,count,certain,ambigous,expected,citations,")?;
     for name in author_names.keys() {
         write!(file, "{},", name)?;
         write!(file, "{} Freq,", name)?;
     }
 
+    for (author, title) in work_names.keys() {
+        write!(file, "{} - {},", author, title)?;
+        write!(file, "{} - {} Freq,", author, title)?;
+    }
+
     for cent in -6..=6 {
         write!(file, "{} cent,{} cent rel,", cent, cent)?;
     }
 
     writeln!(file)?;
 
-    alpha.write(&db, file, &author_count, &author_names)?;
+    alpha.write(
+        &db,
+        file,
+        (&author_count, &author_names),
+        (&work_count, &work_names),
+    )?;
     Ok(())
 }
 
+// The total count of each column, and the columns by name
+type Columns<'a, Id, Name> = (&'a HashMap<Id, usize>, &'a BTreeMap<Name, Id>);
+
 #[derive(Debug, Clone)]
 struct Entry {
     lemma: Lemma,
     count: usize,
     ambig_count: usize,
+    expected_count: f64,
     forms: Vec<(Form, Vec<FormDataId>)>,
-    authors: HashSet<AuthorId>,
 }
 
 fn id_to_str(db: &impl MainDatabase, id: WordId) -> String {
-    db.lookup_word(id).inner().to_string()
+    db.lookup_word_str(id).to_string()
 }
 
 impl Entry {
+    // The first references where the lemma appears, as a quoted csv field
+    fn citations(&self, db: &impl MainDatabase) -> String {
+        let mut occurrences: Vec<_> = self
+            .forms
+            .iter()
+            .flat_map(|(_, fds)| fds)
+            .map(|fd| db.lookup_intern_form_data(*fd))
+            .collect();
+        occurrences.sort_by_key(|fd| (fd.source(), fd.line_no()));
+
+        let mut citations: Vec<String> = Vec::new();
+        for citation in occurrences.iter().flat_map(|fd| fd.citation(db)) {
+            if citations.len() > MAX_CITATIONS {
+                break;
+            }
+            if !citations.contains(&citation) {
+                citations.push(citation);
+            }
+        }
+
+        if citations.len() > MAX_CITATIONS {
+            citations.truncate(MAX_CITATIONS);
+            citations.push("...".to_string());
+        }
+
+        format!("\"{}\"", citations.join("; ").replace('"', "\"\""))
+    }
+
     fn write(
         &self,
         w: &mut impl Write,
         db: &impl MainDatabase,
-        global_authors_count: &HashMap<AuthorId, usize>,
-        authors_names: &BTreeMap<&str, AuthorId>,
+        (global_authors_count, authors_names): Columns<AuthorId, &str>,
+        (global_works_count, works_names): Columns<WorkId, (&str, &str)>,
     ) -> io::Result<()> {
         write!(
             w,
-            "{},{},{},{},",
+            "{},{},{},{},{:.2},{},",
             id_to_str(db, self.lemma.0).to_uppercase(),
             self.count,
             self.count - self.ambig_count,
-            self.ambig_count
+            self.ambig_count,
+            self.expected_count,
+            self.citations(db)
         )?;
 
-        let mut authors: Vec<_> = self
-            .authors
-            .iter()
-            .map(|e| (e, db.lookup_intern_author(*e)))
-            .collect();
-        authors.sort_by(|(_, a), (_, b)| a.name().cmp(b.name()));
-
         // How many times was it used by an author
         let mut authors_count = HashMap::new();
         for &fd in self.forms.iter().map(|(_, fds)| fds).flatten() {
@@ -99,16 +169,32 @@ impl Entry {
             let freq = ((count * AUTHOR_SCALE_FACTOR) as f64) / relative_count as f64;
             write!(w, "{},{:.2},", count, freq)?;
         }
-        // Split the authors by century
-        let buckets =
-            latin_db::authors_chrono::split_by_century(authors.iter().map(|(_, a)| a).cloned());
+
+        // How many times was it used in a work
+        let mut works_count = HashMap::new();
+        for &fd in self.forms.iter().flat_map(|(_, fds)| fds) {
+            *works_count
+                .entry(db.lookup_intern_form_data(fd).work(db))
+                .or_insert(0usize) += 1;
+        }
+
+        // The same, for each of the works (if any)
+        for id in works_names.values() {
+            let count = *works_count.get(id).unwrap_or(&0);
+            let relative_count = *global_works_count.get(id).unwrap_or(&1);
+            let freq = ((count * WORK_SCALE_FACTOR) as f64) / relative_count as f64;
+            write!(w, "{},{:.2},", count, freq)?;
+        }
+
+        // Split the works by century, each by its own date or else by the one of its author
+        let buckets = split_spans_by_century(works_count.iter().map(|(&id, &count)| {
+            let work = db.lookup_intern_work(id);
+            (count, work.dating(db.lookup_intern_author(work.author())))
+        }));
         let mut centuries: BTreeMap<_, _> = (-6..=6_i32).map(|i| (i, (0, 0.0))).collect();
-        for (cent, authors_b) in buckets.into_iter() {
+        for (cent, counts) in buckets.into_iter() {
             // How many we had for each century
-            let aggregated = authors_b
-                .iter()
-                .flat_map(|&a| authors_count.get(&a))
-                .sum::<usize>();
+            let aggregated = counts.iter().sum::<usize>();
             let relative_freq = (aggregated * HISTORIC_SCALE_FACTOR) as f64 / self.count as f64;
 
             centuries.insert(cent, (aggregated, relative_freq));
@@ -130,28 +216,26 @@ struct Dictionary {
 }
 
 impl Dictionary {
-    fn new(db: &impl MainDatabase, sub: LitSubset) -> Self {
-        let tree = db.subset_tree(sub);
+    fn new(db: &impl MainDatabase, sub: LitSubset, weighting: Weighting) -> Self {
+        let tree = db.subset_tree(sub.clone());
+        let expected = db.weighted_lemma_frequencies(sub, weighting);
         let mut ls = Vec::with_capacity(tree.len());
         for (&lemma, forms) in tree.iter() {
             let count = forms.values().map(|v| v.len()).sum();
+            // Note, the occurrences whose lemma was chosen by hand are certain
             let ambig_count = forms
                 .iter()
                 .filter(|(&k, _)| db.lemmatizer().is_ambig(k.0))
-                .map(|(_, v)| v.len())
-                .sum();
+                .flat_map(|(_, v)| v)
+                .filter(|&&fd| db.lookup_intern_form_data(fd).chosen_lemma(db).is_none())
+                .count();
 
             ls.push(Entry {
                 lemma,
                 count,
                 ambig_count,
+                expected_count: expected.get(&lemma).map_or(0.0, |w| w.value()),
                 forms: forms.iter().map(|(a, b)| (*a, b.clone())).collect(),
-                // TODO, this is a bit inefficient, as many double lookups
-                authors: forms
-                    .values()
-                    .flatten()
-                    .map(|f| db.lookup_intern_form_data(*f).author(db))
-                    .collect(),
             })
         }
 
@@ -183,12 +267,33 @@ impl Dictionary {
         &self,
         db: &impl MainDatabase,
         w: &mut impl Write,
-        author_count: &HashMap<AuthorId, usize>,
-        author_names: &BTreeMap<&str, AuthorId>,
+        authors: Columns<AuthorId, &str>,
+        works: Columns<WorkId, (&str, &str)>,
     ) -> io::Result<()> {
         for entry in &self.ls {
-            entry.write(w, db, author_count, author_names)?;
+            entry.write(w, db, authors, works)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_db::arguments::configuration_from;
+
+    #[test]
+    fn test_arguments() {
+        let args = vec![
+            "csv_export",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--overrides",
+            "o.json",
+            "--works",
+        ];
+        configuration_from(args, &options(), |_| ()).unwrap();
+    }
+}
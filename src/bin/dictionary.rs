@@ -1,11 +1,14 @@
+use latin_db::arguments::load_configuration_with;
+use latin_db::authors_chrono::{split_spans_by_century, Author};
 use latin_db::query_driver::driver_init;
 use latin_db::query_system::ids::*;
 use latin_db::query_system::lit_subset::LitSubset;
 use latin_db::query_system::traits::*;
 use latin_db::query_system::types::{Form, Lemma};
-use latin_db::{arguments::load_configuration, authors_chrono::Author};
+use latin_db::query_system::weights::CountMode;
 
-use std::collections::{HashMap, HashSet};
+use clap::Arg;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, prelude::*};
 
@@ -50,6 +53,7 @@ enum AuthorMode {
 struct AuthorConfig {
     include_header: bool,
     include_authors: (bool, usize),
+    include_works: (bool, usize),
     include_centuries: (bool, CenturySettings, usize),
     spotlight: Option<AuthorId>,
 }
@@ -71,14 +75,22 @@ struct Configuration {
 }
 
 const AUTHOR_SCALE_FACTOR: usize = 1_000;
+const WORK_SCALE_FACTOR: usize = 1_000;
 const HISTORIC_SCALE_FACTOR: usize = 1_000;
 
+fn options() -> Vec<Arg<'static, 'static>> {
+    vec![Arg::with_name("works")
+        .long("works")
+        .help("List the works each lemma is attested in, with their count and frequency")]
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_LOG", "info");
     std::env::set_var("RUST_BACKTRACE", "1");
     color_backtrace::install();
     env_logger::init();
-    let db = driver_init(load_configuration())?;
+    let (config, by_works) = load_configuration_with(&options(), |app| app.is_present("works"));
+    let db = driver_init(config.warm_up_by_default())?;
     let lit = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
     let epigraph_id = *db.authors().get_by_left(&Author::new("Epigraphs")).unwrap();
     let alpha = Dictionary::new(
@@ -90,6 +102,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             author_mode: AuthorMode::Full(AuthorConfig {
                 include_header: true,
                 include_authors: (false, AUTHOR_SCALE_FACTOR),
+                include_works: (by_works, WORK_SCALE_FACTOR),
                 include_centuries: (
                     true,
                     CenturySettings::IncludeAuthors(AUTHOR_SCALE_FACTOR),
@@ -138,15 +151,51 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
     );
 
-    let author_count = db.authors_count(lit);
+    let author_count: HashMap<_, _> = db
+        .authors_count(lit.clone(), CountMode::Every)
+        .iter()
+        .map(|(&author, count)| (author, count.value() as usize))
+        .collect();
+    let work_count = db.works_count(lit);
+    let counts = (&author_count, &*work_count);
+
+    alpha.write(&db, &mut File::create("alpha.txt")?, counts)?;
+    alpha_only_ambig.write(&db, &mut File::create("alpha_ambig.txt")?, counts)?;
+    freq_with_forms.write(&db, &mut File::create("freq_forms.txt")?, counts)?;
+    freq_without_forms.write(&db, &mut File::create("freq_no_forms.txt")?, counts)?;
+    Ok(())
+}
+
+// The occurrences in some works, summed for each of their authors (ordered by name)
+fn by_author(
+    db: &impl MainDatabase,
+    works: impl IntoIterator<Item = (AuthorId, usize)>,
+) -> BTreeMap<&Author, (AuthorId, usize)> {
+    let mut res = BTreeMap::new();
+    for (id, count) in works {
+        res.entry(db.lookup_intern_author(id)).or_insert((id, 0)).1 += count;
+    }
+    res
+}
 
-    alpha.write(&db, &mut File::create("alpha.txt")?, &author_count)?;
-    alpha_only_ambig.write(&db, &mut File::create("alpha_ambig.txt")?, &author_count)?;
-    freq_with_forms.write(&db, &mut File::create("freq_forms.txt")?, &author_count)?;
-    freq_without_forms.write(&db, &mut File::create("freq_no_forms.txt")?, &author_count)?;
+// Write the occurrences of each author, with their frequency in the whole of the author
+fn write_authors(
+    w: &mut impl Write,
+    authors: &BTreeMap<&Author, (AuthorId, usize)>,
+    global_authors_count: &HashMap<AuthorId, usize>,
+    scale: usize,
+) -> io::Result<()> {
+    for (author, (id, count)) in authors {
+        let global_count = *global_authors_count.get(id).unwrap();
+        let relative_freq = (count * scale) as f64 / global_count as f64;
+        write!(w, "{} {} ({:.2}) ", author.name(), count, relative_freq)?;
+    }
     Ok(())
 }
 
+// The total count of each author, and of each work
+type Counts<'a> = (&'a HashMap<AuthorId, usize>, &'a HashMap<WorkId, usize>);
+
 #[derive(Debug, Clone)]
 struct Entry {
     lemma: Lemma,
@@ -158,7 +207,7 @@ struct Entry {
 }
 
 fn id_to_str(db: &impl MainDatabase, id: WordId) -> String {
-    db.lookup_word(id).inner().to_string()
+    db.lookup_word_str(id).to_string()
 }
 
 impl Entry {
@@ -167,7 +216,7 @@ impl Entry {
         w: &mut impl Write,
         db: &impl MainDatabase,
         config: Configuration,
-        global_authors_count: &HashMap<AuthorId, usize>,
+        (global_authors_count, global_works_count): Counts,
     ) -> io::Result<()> {
         match config.lemma_mode {
             LemmaMode::Full => {
@@ -255,6 +304,14 @@ impl Entry {
                 .or_insert(0usize) += 1;
         }
 
+        // How many times was it used in a work
+        let mut works_count = HashMap::new();
+        for &fd in self.forms.iter().flat_map(|(_, fds)| fds) {
+            *works_count
+                .entry(db.lookup_intern_form_data(fd).work(db))
+                .or_insert(0usize) += 1;
+        }
+
         match config.author_mode {
             AuthorMode::Nothing => (),
             AuthorMode::Full(config) => {
@@ -292,17 +349,42 @@ impl Entry {
                     writeln!(w)?;
                 }
 
+                if config.include_works.0 {
+                    let scale = config.include_works.1;
+
+                    // Note, the works are ordered by author, then by title
+                    let mut works: Vec<_> = works_count
+                        .iter()
+                        .map(|(id, count)| (db.lookup_intern_work(*id), id, count))
+                        .map(|(work, id, count)| {
+                            let author = db.lookup_intern_author(work.author()).name();
+                            ((author, work.title()), id, count)
+                        })
+                        .collect();
+                    works.sort();
+
+                    write!(w, "\t\t")?;
+                    for ((author, title), id, count) in works {
+                        let relative_freq =
+                            (count * scale) as f64 / *global_works_count.get(id).unwrap() as f64;
+                        write!(w, "{}, {} {} ({:.2}) ", author, title, count, relative_freq)?;
+                    }
+                    writeln!(w)?;
+                }
+
                 if config.include_centuries.0 {
                     let scale = config.include_centuries.2;
 
-                    let buckets = latin_db::authors_chrono::split_by_century(
-                        authors.iter().map(|(_, a)| a).cloned(),
-                    );
-                    for (cent, mut authors_b) in buckets.into_iter() {
-                        let aggregated = authors_b
-                            .iter()
-                            .flat_map(|&a| authors_count.get(&a))
-                            .sum::<usize>();
+                    // Split the works by century, each by its own date or else by the one of its author
+                    let buckets =
+                        split_spans_by_century(works_count.iter().map(|(&id, &count)| {
+                            let work = db.lookup_intern_work(id);
+                            let author = db.lookup_intern_author(work.author());
+                            ((work.author(), count), work.dating(author))
+                        }));
+                    for (cent, works_b) in buckets.into_iter() {
+                        let authors_b = by_author(db, works_b);
+                        let aggregated = authors_b.values().map(|(_, c)| c).sum::<usize>();
                         let relative_freq = (aggregated * scale) as f64 / self.count as f64;
 
                         write!(
@@ -317,41 +399,27 @@ impl Entry {
 
                         match config.include_centuries.1 {
                             CenturySettings::IncludeAuthors(scale) => {
-                                authors_b.sort_by(|a, b| a.name().cmp(b.name()));
-                                for author in authors_b {
-                                    let count = authors_count.get(author).unwrap();
-                                    let id = authors
-                                        .iter()
-                                        .find(|(_, a)| a == &author)
-                                        .map(|(id, _)| id)
-                                        .unwrap();
-                                    let global_count = *global_authors_count.get(id).unwrap();
-                                    let relative_freq =
-                                        (count * scale) as f64 / global_count as f64;
-                                    write!(
-                                        w,
-                                        "{} {} ({:.2}) ",
-                                        author.name(),
-                                        count,
-                                        relative_freq
-                                    )?;
-                                }
+                                write_authors(w, &authors_b, global_authors_count, scale)?
                             }
                             CenturySettings::Nothing => (),
                         }
                         writeln!(w, "!")?;
                     }
 
-                    // Handle authors with no century
-                    let mut authors_without_cent: Vec<_> = authors
-                        .iter()
-                        .filter(|(_, a)| a.tspan().is_none())
-                        .map(|(_, a)| a)
-                        .collect();
+                    // Handle works with no century
+                    let authors_without_cent = by_author(
+                        db,
+                        works_count
+                            .iter()
+                            .map(|(&id, &count)| (db.lookup_intern_work(id), count))
+                            .filter(|(work, _)| {
+                                work.dating(db.lookup_intern_author(work.author()))
+                                    .is_none()
+                            })
+                            .map(|(work, count)| (work.author(), count)),
+                    );
 
                     if !authors_without_cent.is_empty() {
-                        authors_without_cent.sort_by(|a, b| a.name().cmp(b.name()));
-
                         write!(
                             w,
                             "\t\t\t•Unknown century: {} source{}, ",
@@ -364,26 +432,12 @@ impl Entry {
                         )?;
 
                         match config.include_centuries.1 {
-                            CenturySettings::IncludeAuthors(scale) => {
-                                for author in authors_without_cent {
-                                    let count = authors_count.get(author).unwrap();
-                                    let id = authors
-                                        .iter()
-                                        .find(|(_, a)| a == author)
-                                        .map(|(id, _)| id)
-                                        .unwrap();
-                                    let global_count = *global_authors_count.get(id).unwrap();
-                                    let relative_freq =
-                                        (count * scale) as f64 / global_count as f64;
-                                    write!(
-                                        w,
-                                        "{} {} ({:.2}) ",
-                                        author.name(),
-                                        count,
-                                        relative_freq
-                                    )?;
-                                }
-                            }
+                            CenturySettings::IncludeAuthors(scale) => write_authors(
+                                w,
+                                &authors_without_cent,
+                                global_authors_count,
+                                scale,
+                            )?,
                             CenturySettings::Nothing => (),
                         }
                         writeln!(w, "!")?;
@@ -408,11 +462,13 @@ impl Dictionary {
         let mut ls = Vec::with_capacity(tree.len());
         for (&lemma, forms) in tree.iter() {
             let count = forms.values().map(|v| v.len()).sum();
+            // Note, the occurrences whose lemma was chosen by hand are certain
             let ambig_count = forms
                 .iter()
                 .filter(|(&k, _)| db.lemmatizer().is_ambig(k.0))
-                .map(|(_, v)| v.len())
-                .sum();
+                .flat_map(|(_, v)| v)
+                .filter(|&&fd| db.lookup_intern_form_data(fd).chosen_lemma(db).is_none())
+                .count();
 
             ls.push(Entry {
                 lemma,
@@ -491,15 +547,31 @@ impl Dictionary {
         }
     }
 
-    fn write(
-        &self,
-        db: &impl MainDatabase,
-        w: &mut impl Write,
-        author_count: &HashMap<AuthorId, usize>,
-    ) -> io::Result<()> {
+    fn write(&self, db: &impl MainDatabase, w: &mut impl Write, counts: Counts) -> io::Result<()> {
         for entry in &self.ls {
-            entry.write(w, db, self.config, author_count)?;
+            entry.write(w, db, self.config, counts)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_db::arguments::configuration_from;
+
+    #[test]
+    fn test_arguments() {
+        let args = vec![
+            "dictionary",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--overrides",
+            "o.json",
+            "--works",
+        ];
+        configuration_from(args, &options(), |_| ()).unwrap();
+    }
+}
@@ -11,14 +11,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_BACKTRACE", "1");
     color_backtrace::install();
     env_logger::init();
-    let db = driver_init(load_configuration())?;
+    let db = driver_init(load_configuration().warm_up_by_default())?;
     let lit = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
     let subset_tree = db.subset_tree(lit);
 
     let forms = subset_tree
         .values()
         .flat_map(|map| map.keys())
-        .map(|form| db.lookup_word(form.0).inner().to_string());
+        .map(|form| db.lookup_word_str(form.0).to_string());
 
     let mut out = File::create("forms.txt")?;
 
@@ -13,7 +13,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_BACKTRACE", "1");
     color_backtrace::install();
     env_logger::init();
-    let db = driver_init(load_configuration())?;
+    let db = driver_init(load_configuration().warm_up_by_default())?;
     let lit = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
 
     let source_tree = db.subset_tree(lit);
@@ -47,17 +47,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn lemma_id_to_string(db: &impl IntermediateDatabase, lemma: Lemma) -> String {
-    db.lookup_word(lemma.0).inner().to_string()
+    db.lookup_word_str(lemma.0).to_string()
 }
 
 fn form_id_to_string(db: &impl IntermediateDatabase, form: Form) -> String {
-    db.lookup_word(form.0).inner().to_string()
+    db.lookup_word_str(form.0).to_string()
 }
 
 fn form_data_normalize(db: &latin_db::query_driver::MainDatabase, fd: FormDataId) -> FormData {
     let fd = db.lookup_intern_form_data(fd);
     FormData {
         line_no: fd.line_no(),
+        index: fd.index(),
+        offset: fd.offset(),
+        citation: fd.citation(db),
         author: db.lookup_intern_author(fd.author(db)).name().to_string(),
         source: db.sources().get_by_right(&fd.source()).unwrap().clone(),
     }
@@ -67,5 +70,8 @@ fn form_data_normalize(db: &latin_db::query_driver::MainDatabase, fd: FormDataId
 struct FormData {
     author: String,
     line_no: usize,
+    index: usize,
+    offset: usize,
+    citation: Option<String>,
     source: PathBuf,
 }
@@ -0,0 +1,210 @@
+use latin_db::arguments::load_configuration_with;
+use latin_db::arguments::output::{output_arg, output_path, quote};
+use latin_db::authors_chrono::Author;
+use latin_db::query_driver::{self, driver_init};
+use latin_db::query_system::lit_subset::LitSubset;
+use latin_db::query_system::parallels::{find_parallels, LineRef, ParallelsConfig, Unit};
+use latin_db::query_system::traits::*;
+
+use clap::{Arg, ArgMatches};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::prelude::*;
+
+struct Options {
+    source: HashSet<String>,
+    target: HashSet<String>,
+    config: ParallelsConfig,
+    output: String,
+}
+
+fn options() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("source")
+            .long("source")
+            .takes_value(true)
+            .multiple(true)
+            .value_name("AUTHOR")
+            .help("The authors to look for parallels in")
+            .required(true),
+        Arg::with_name("target")
+            .long("target")
+            .takes_value(true)
+            .multiple(true)
+            .value_name("AUTHOR")
+            .help("The authors the parallels are with")
+            .required(true),
+        Arg::with_name("min_shared")
+            .long("min-shared")
+            .takes_value(true)
+            .value_name("N")
+            .help("How many lemmas two lines need to share (default 2)"),
+        Arg::with_name("stop_words")
+            .long("stop-words")
+            .takes_value(true)
+            .value_name("N")
+            .help("How many of the most frequent lemmas to ignore (default 10)"),
+        Arg::with_name("words")
+            .long("words")
+            .takes_value(true)
+            .value_name("N")
+            .help("Look for the shared lemmas in windows of N words rather than in lines"),
+        output_arg(),
+    ]
+}
+
+fn parse_options(app: &ArgMatches) -> Result<Options, String> {
+    let names = |arg| {
+        app.values_of(arg)
+            .map(|names| names.map(String::from).collect())
+            .unwrap_or_default()
+    };
+    let number = |arg, default: usize| {
+        app.value_of(arg)
+            .map(|n| n.parse::<usize>().map_err(|_| format!("Invalid {}", arg)))
+            .unwrap_or(Ok(default))
+    };
+
+    let defaults = ParallelsConfig::default();
+    Ok(Options {
+        source: names("source"),
+        target: names("target"),
+        config: ParallelsConfig {
+            min_shared: number("min_shared", defaults.min_shared)?,
+            stop_words: number("stop_words", defaults.stop_words)?,
+            unit: match app.value_of("words") {
+                Some(_) => match number("words", 0)? {
+                    0 => return Err("Invalid words".to_string()),
+                    n => Unit::Words(n),
+                },
+                None => defaults.unit,
+            },
+        },
+        output: output_path(app, "parallels.csv"),
+    })
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("RUST_LOG", "info");
+    std::env::set_var("RUST_BACKTRACE", "1");
+    color_backtrace::install();
+    env_logger::init();
+    let (config, opts) = load_configuration_with(&options(), parse_options);
+    let opts = opts?;
+    let db = driver_init(config)?;
+
+    // Note, a misspelled name would otherwise leave the subset empty
+    let subset = |names: &HashSet<String>| {
+        let authors = names
+            .iter()
+            .map(|name| {
+                db.authors()
+                    .get_by_left(&Author::new(name))
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown author {}", name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok::<_, String>(LitSubset::from_authors(authors.iter(), &db.snapshot()))
+    };
+
+    let source = subset(&opts.source)?;
+    let target = subset(&opts.target)?;
+    let corpus = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
+
+    let res = find_parallels(&db, &source, &target, &corpus, &opts.config);
+
+    let mut out = File::create(&opts.output)?;
+    writeln!(
+        out,
+        "score,shared,source author,source citation,source line,target author,target citation,target line"
+    )?;
+
+    for parallel in res {
+        let shared: Vec<_> = parallel
+            .shared()
+            .iter()
+            .map(|l| db.lookup_word_str(l.0).to_string())
+            .collect();
+
+        writeln!(
+            out,
+            "{:.3},{},{},{}",
+            parallel.score(),
+            quote(&shared.join(" ")),
+            describe(&db, parallel.source()),
+            describe(&db, parallel.target())
+        )?;
+    }
+
+    Ok(())
+}
+
+// The author, citation and text of a line, as csv fields
+fn describe(db: &query_driver::MainDatabase, line: LineRef) -> String {
+    let author = db.lookup_intern_author(db.associated_author(line.source()));
+    let citation = line.citation(db).unwrap_or_else(|| {
+        let file = db
+            .sources()
+            .get_by_right(&line.source())
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{}:{}", file, line.line_no() + 1)
+    });
+    let text = (line.line_no()..=line.last_line_no())
+        .filter_map(|line_no| db.get_line(line.source(), line_no))
+        .map(|l| l.trim().to_string())
+        .collect::<Vec<_>>()
+        .join(" / ");
+
+    format!(
+        "{},{},{}",
+        quote(author.name()),
+        quote(&citation),
+        quote(&text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_db::arguments::configuration_from;
+
+    #[test]
+    fn test_arguments() {
+        let args = vec![
+            "parallels",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--overrides",
+            "o.json",
+            "--source",
+            "Vergilius",
+            "--target",
+            "Lucanus",
+            "--words",
+            "5",
+            "-o",
+            "out.csv",
+        ];
+        configuration_from(args, &options(), |_| ()).unwrap();
+
+        let args = vec![
+            "parallels",
+            "-d",
+            "src",
+            "-l",
+            "Cargo.toml",
+            "--source",
+            "Vergilius",
+            "--target",
+            "Lucanus",
+            "--words",
+            "0",
+        ];
+        let (_, opts) = configuration_from(args, &options(), parse_options).unwrap();
+        assert_eq!(opts.err(), Some("Invalid words".to_string()));
+    }
+}
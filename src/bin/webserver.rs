@@ -1,12 +1,14 @@
-use salsa::ParallelDatabase;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
 use warp::{http::Response, Filter};
 
 use latin_db::arguments::load_configuration;
 use latin_db::graphql_queries::context::Context;
 use latin_db::graphql_queries::schema;
 use latin_db::query_driver::driver_init;
+use latin_db::query_driver::editor::CorpusEditor;
+use latin_db::query_driver::watcher::CorpusWatcher;
 
 fn main() {
     // If I fail, I want to see it :)
@@ -16,7 +18,24 @@ fn main() {
     env_logger::init();
 
     // Initialize the db
-    let db = Arc::new(Mutex::new(driver_init(load_configuration()).unwrap()));
+    let config = load_configuration();
+    let mut db = driver_init(config.clone()).unwrap();
+    let watcher = CorpusWatcher::new(&mut db, &config);
+    let db = Arc::new(Mutex::new(db));
+
+    // Spawn a daemon applying the changes to the corpus, which the next snapshots will see
+    match watcher {
+        Ok(watcher) => {
+            let watched_copy = db.clone();
+            thread::spawn(move || {
+                while let Some(event) = watcher.next_event() {
+                    let mut db = watched_copy.lock().unwrap();
+                    watcher.apply(&mut db, event);
+                }
+            });
+        }
+        Err(err) => log::warn!("The corpus will not be reloaded: {}", err),
+    }
 
     /*
     let garbage_copy = db.clone();
@@ -43,8 +62,9 @@ fn main() {
     // log that we are running!
     log::info!("Listening on 0.0.0.0.8088");
 
-    // This is snapshot of the db
-    let state = warp::any().map(move || Context::new(db.clone().lock().unwrap().snapshot()));
+    // This is snapshot of the db, which the mutations update
+    let editor = Arc::new(CorpusEditor::new(config));
+    let state = warp::any().map(move || Context::new_editable(db.clone(), editor.clone()));
 
     // Set up cors. TODO, this is most likely insecure
     let cors = warp::cors()
@@ -0,0 +1,109 @@
+//! Canonical references for the lines of a source (i.e. `Aen. 1.33`, `Cic. Cat. 1.2`)
+//! A reference applies from the line where it is set up to the next one.
+//! If a reference ends with a number followed by `+` (i.e. `Aen. 1.1+`), the number
+//! is incremented for each of the following lines, as it is common for verse
+
+use std::collections::BTreeMap;
+
+pub mod parsers;
+
+/// A reference, which can be either fixed or counting lines
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Reference {
+    prefix: String,
+    counter: Option<usize>,
+}
+
+impl Reference {
+    /// A reference that is the same for all the lines it applies to
+    pub fn new(citation: impl ToString) -> Self {
+        Self {
+            prefix: citation.to_string(),
+            counter: None,
+        }
+    }
+
+    /// A reference that is incremented for each line, starting from `start`
+    pub fn new_counting(prefix: impl ToString, start: usize) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            counter: Some(start),
+        }
+    }
+
+    /// Get the citation for the line `distance` lines after the start of the reference
+    pub fn cite(&self, distance: usize) -> String {
+        match self.counter {
+            Some(start) => format!("{}{}", self.prefix, start + distance),
+            None => self.prefix.clone(),
+        }
+    }
+}
+
+/// A mapping between the lines of a source and their references
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CitationScheme {
+    // Note, keyed by the (zero based) line where the reference starts
+    references: BTreeMap<usize, Reference>,
+}
+
+impl CitationScheme {
+    /// Instantiate a scheme with no references
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the reference starting at a (zero based) line
+    pub fn insert(&mut self, line_no: usize, reference: Reference) {
+        self.references.insert(line_no, reference);
+    }
+
+    /// Is any reference set?
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+
+    /// Get the citation of a (zero based) line, if any reference precedes it
+    pub fn cite(&self, line_no: usize) -> Option<String> {
+        self.references
+            .range(..=line_no)
+            .next_back()
+            .map(|(start, reference)| reference.cite(line_no - start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let scheme = CitationScheme::new();
+        assert!(scheme.is_empty());
+        assert_eq!(scheme.cite(0), None);
+    }
+
+    #[test]
+    fn fixed() {
+        let mut scheme = CitationScheme::new();
+        scheme.insert(2, Reference::new("Cic. Cat. 1.1"));
+        scheme.insert(5, Reference::new("Cic. Cat. 1.2"));
+
+        assert_eq!(scheme.cite(1), None);
+        assert_eq!(scheme.cite(2).unwrap(), "Cic. Cat. 1.1");
+        assert_eq!(scheme.cite(4).unwrap(), "Cic. Cat. 1.1");
+        assert_eq!(scheme.cite(5).unwrap(), "Cic. Cat. 1.2");
+        assert_eq!(scheme.cite(100).unwrap(), "Cic. Cat. 1.2");
+    }
+
+    #[test]
+    fn counting() {
+        let mut scheme = CitationScheme::new();
+        scheme.insert(0, Reference::new_counting("Aen. 1.", 1));
+        scheme.insert(756, Reference::new_counting("Aen. 2.", 1));
+
+        assert_eq!(scheme.cite(0).unwrap(), "Aen. 1.1");
+        assert_eq!(scheme.cite(32).unwrap(), "Aen. 1.33");
+        assert_eq!(scheme.cite(756).unwrap(), "Aen. 2.1");
+    }
+}
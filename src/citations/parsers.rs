@@ -0,0 +1,244 @@
+//! The two ways references can be specified for a source:
+//! - A sidecar file, where each line contains the (one based) line of the source and its reference,
+//!   separated by whitespace (i.e. `1 Aen. 1.1+`). Empty lines and lines starting with `#` are skipped.
+//! - Inline markers in the text, delimited by `§` (i.e. `§Cic. Cat. 1.2§ quo usque tandem`).
+//!   A marker applies from the line it appears in.
+
+use super::{CitationScheme, Reference};
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io::{self, prelude::*, BufReader};
+
+/// The char used to delimit inline markers
+pub const MARKER: char = '§';
+
+/// A parser for sidecar reference files
+#[derive(Debug, Default)]
+pub struct SidecarParser {
+    scheme: CitationScheme,
+}
+
+/// The line where the parsing failed
+#[derive(Debug)]
+pub struct LineNo(usize);
+
+/// The various way the parsing can fail
+#[derive(Debug)]
+pub enum ParsingError {
+    /// The line of the source is not a (one based) number
+    InvalidLineNumber(String, LineNo),
+    /// A line of the source with no reference
+    MissingReference(LineNo),
+    /// A counting reference (ending in `+`) which does not end with a number
+    InvalidCounter(String, LineNo),
+    /// An inline marker which is never closed
+    UnclosedMarker(LineNo),
+    /// The sidecar could not be read
+    IOError(io::Error),
+}
+
+impl std::fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Note, the lines are numbered from one, as in an editor
+        match self {
+            ParsingError::InvalidLineNumber(line_no, LineNo(l)) => {
+                write!(f, "Line {}: invalid line number {:?}", l + 1, line_no)
+            }
+            ParsingError::MissingReference(LineNo(l)) => {
+                write!(f, "Line {}: missing reference", l + 1)
+            }
+            ParsingError::InvalidCounter(reference, LineNo(l)) => {
+                write!(f, "Line {}: invalid counter in {:?}", l + 1, reference)
+            }
+            ParsingError::UnclosedMarker(LineNo(l)) => {
+                write!(f, "Line {}: unclosed reference marker", l + 1)
+            }
+            ParsingError::IOError(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ParsingError {}
+
+impl From<io::Error> for ParsingError {
+    fn from(err: io::Error) -> Self {
+        ParsingError::IOError(err)
+    }
+}
+
+fn parse_reference(s: &str, num: LineNo) -> Result<Reference, ParsingError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(ParsingError::MissingReference(num));
+    }
+
+    let counting = match s.strip_suffix('+') {
+        Some(counting) => counting,
+        None => return Ok(Reference::new(s)),
+    };
+
+    let prefix = counting.trim_end_matches(|c: char| c.is_ascii_digit());
+    counting[prefix.len()..]
+        .parse::<usize>()
+        .map(|start| Reference::new_counting(prefix, start))
+        .map_err(|_| ParsingError::InvalidCounter(s.to_string(), num))
+}
+
+impl SidecarParser {
+    /// Read the source to completion
+    pub fn read_all(&mut self, read: impl io::Read) -> Result<(), ParsingError> {
+        let bufreader = BufReader::new(read);
+        for (i, line) in bufreader.lines().enumerate() {
+            self.read_line(&line?, LineNo(i))?;
+        }
+        Ok(())
+    }
+
+    /// Get the resulting representation
+    pub fn build(self) -> CitationScheme {
+        self.scheme
+    }
+
+    fn read_line(&mut self, line: &str, num: LineNo) -> Result<(), ParsingError> {
+        let line = line.trim();
+
+        // We skip these lines
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (line_no, reference) = line
+            .find(char::is_whitespace)
+            .map(|i| line.split_at(i))
+            .unwrap_or((line, ""));
+
+        let line_no = match line_no.parse::<usize>() {
+            Ok(i) if i > 0 => i - 1,
+            _ => return Err(ParsingError::InvalidLineNumber(line_no.to_string(), num)),
+        };
+
+        let reference = parse_reference(reference, num)?;
+        self.scheme.insert(line_no, reference);
+
+        Ok(())
+    }
+}
+
+/// Build the citation scheme from the inline markers in a text
+pub fn from_markers(text: &str) -> Result<CitationScheme, ParsingError> {
+    let mut scheme = CitationScheme::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut parts = line.split(MARKER).skip(1);
+        if let Some(marker) = parts.next() {
+            // Note, we need the closing marker too
+            if parts.next().is_none() {
+                return Err(ParsingError::UnclosedMarker(LineNo(i)));
+            }
+
+            scheme.insert(i, parse_reference(marker, LineNo(i))?);
+        }
+    }
+
+    Ok(scheme)
+}
+
+/// Replace the inline markers with whitespace, so that they are not parsed as words.
+/// Note, this preserves the lines and the char offsets in them
+pub fn blank_markers(text: &str) -> Cow<'_, str> {
+    if !text.contains(MARKER) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut res = String::with_capacity(text.len());
+    let mut in_marker = false;
+    for c in text.chars() {
+        if c == MARKER {
+            in_marker = !in_marker;
+            res.push(' ');
+        } else if c == '\n' {
+            // Markers never span more than a line
+            in_marker = false;
+            res.push(c);
+        } else if in_marker {
+            res.push(' ');
+        } else {
+            res.push(c);
+        }
+    }
+
+    Cow::Owned(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sidecar(s: &str) -> Result<CitationScheme, ParsingError> {
+        let mut parser = SidecarParser::default();
+        parser.read_all(s.as_bytes())?;
+        Ok(parser.build())
+    }
+
+    #[test]
+    fn test_empty() {
+        let parser = SidecarParser::default();
+        assert!(parser.build().is_empty());
+        assert!(sidecar("# A comment\n\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let scheme = sidecar("1\tAen. 1.1+\n757 Aen. 2.1+\n# Prose\n2000\tSchol. 12").unwrap();
+        assert_eq!(scheme.cite(32).unwrap(), "Aen. 1.33");
+        assert_eq!(scheme.cite(757).unwrap(), "Aen. 2.2");
+        assert_eq!(scheme.cite(2005).unwrap(), "Schol. 12");
+    }
+
+    #[test]
+    fn test_sidecar_errors() {
+        assert!(matches!(
+            sidecar("0\tAen. 1.1"),
+            Err(ParsingError::InvalidLineNumber(_, LineNo(0)))
+        ));
+        assert!(matches!(
+            sidecar("1 Aen. 1.1\nAen. 1.2"),
+            Err(ParsingError::InvalidLineNumber(_, LineNo(1)))
+        ));
+        assert!(matches!(
+            sidecar("1"),
+            Err(ParsingError::MissingReference(LineNo(0)))
+        ));
+        assert!(matches!(
+            sidecar("1 Aen. I+"),
+            Err(ParsingError::InvalidCounter(_, LineNo(0)))
+        ));
+        assert_eq!(
+            sidecar("1 Aen. 1.1\n2").unwrap_err().to_string(),
+            "Line 2: missing reference"
+        );
+    }
+
+    #[test]
+    fn test_markers() {
+        let text = "§Cic. Cat. 1.1§ Quo usque tandem\nabutere, Catilina\n§Cic. Cat. 1.2§ O tempora";
+        let scheme = from_markers(text).unwrap();
+        assert_eq!(scheme.cite(1).unwrap(), "Cic. Cat. 1.1");
+        assert_eq!(scheme.cite(2).unwrap(), "Cic. Cat. 1.2");
+
+        assert!(matches!(
+            from_markers("quo usque\n§Cic. Cat. 1.1 tandem"),
+            Err(ParsingError::UnclosedMarker(LineNo(1)))
+        ));
+    }
+
+    #[test]
+    fn test_blank_markers() {
+        assert!(matches!(blank_markers("quo usque"), Cow::Borrowed(_)));
+        assert_eq!(
+            blank_markers("§1.1§ quo\nusque §1.2§tandem"),
+            "      quo\nusque      tandem"
+        );
+        assert_eq!(blank_markers("quo §1.1\nusque"), "quo     \nusque");
+    }
+}
@@ -1,60 +1,154 @@
+use super::latin_lemmatizer::parsers::conllu_format;
 use super::query_system::ids::SourceId;
+use super::tei;
 
 use bimap::BiMap;
-//use notify::{EventFn, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use log::warn;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The extension of the sidecar files holding the references of a source
+pub const CITATIONS_EXTENSION: &str = "cit";
+
+/// How long the changes to a file have to settle before they are reported
+pub const WATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Get the sidecar file with the references for a source (i.e. `aeneid.txt` -> `aeneid.cit`)
+pub fn citations_path(path: &Path) -> PathBuf {
+    path.with_extension(CITATIONS_EXTENSION)
+}
+
+/// How the content of a source is read
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum SourceFormat {
+    /// Plain text, whose words are resolved through the lemmatizer
+    Text,
+    /// A CoNLL-U treebank, whose words come with their lemmas
+    Treebank,
+    /// A TEI XML edition, whose structure gives the references
+    Tei,
+}
+
+/// Get the format of a source from its extension (i.e. `annales.conllu` is a treebank)
+pub fn source_format(path: &Path) -> SourceFormat {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(conllu_format::EXTENSION) => SourceFormat::Treebank,
+        Some(tei::EXTENSION) => SourceFormat::Tei,
+        _ => SourceFormat::Text,
+    }
+}
 
 pub trait FileSystem {
     type Source;
 
     fn load(&self, id: SourceId) -> String;
+    fn format(&self, id: SourceId) -> SourceFormat;
+    fn load_citations(&self, id: SourceId) -> Option<String>;
     fn watch(&self, id: SourceId);
 
     fn intern_source(&mut self, path: Self::Source) -> SourceId;
 }
 
-// TODO: This might just be AsRef
-pub trait GetFileSystem {
-    type Fs: FileSystem;
-
-    fn filesystem(&self) -> &Self::Fs;
-    fn filesystem_mut(&mut self) -> &mut Self::Fs;
+// The watcher is shared by all the snapshots of a file system
+#[derive(Clone)]
+struct DirectoryWatcher {
+    watcher: Arc<Mutex<RecommendedWatcher>>,
+    root: PathBuf,
 }
 
-impl<T> FileSystem for T
-where
-    T: GetFileSystem,
-{
-    type Source = <T::Fs as FileSystem>::Source;
-
-    fn intern_source(&mut self, path: Self::Source) -> SourceId {
-        self.filesystem_mut().intern_source(path)
-    }
-
-    fn watch(&self, id: SourceId) {
-        self.filesystem().watch(id)
-    }
-
-    fn load(&self, id: SourceId) -> String {
-        self.filesystem().load(id)
+impl fmt::Debug for DirectoryWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DirectoryWatcher({:?})", self.root)
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct InternerFileSystem {
     sources: BiMap<PathBuf, SourceId>,
+    // The references of the sources that are not in the usual sidecar file
+    citations: HashMap<SourceId, PathBuf>,
+    watcher: Option<DirectoryWatcher>,
+    // The id of the next source, so that the ids of removed sources are never given again
+    next_id: u32,
 }
 
 impl InternerFileSystem {
     pub fn new() -> Self {
         InternerFileSystem {
             sources: BiMap::new(),
+            citations: HashMap::new(),
+            watcher: None,
+            next_id: 0,
+        }
+    }
+
+    /// Instantiate a file system with already interned sources
+    pub fn from_sources(sources: BiMap<PathBuf, SourceId>) -> Self {
+        let next_id = sources
+            .right_values()
+            .map(|id| id.as_integer() + 1)
+            .max()
+            .unwrap_or(0);
+        InternerFileSystem {
+            sources,
+            citations: HashMap::new(),
+            watcher: None,
+            next_id,
         }
     }
 
     pub fn sources(&self) -> &BiMap<PathBuf, SourceId> {
         &self.sources
     }
+
+    /// Read the references of a source from a file other than its sidecar
+    pub fn set_citations_path(&mut self, id: SourceId, path: impl Into<PathBuf>) {
+        self.citations.insert(id, path.into());
+    }
+
+    /// Get the file with the references of a source
+    pub fn citations_path_of(&self, id: SourceId) -> PathBuf {
+        match self.citations.get(&id) {
+            Some(path) => path.clone(),
+            None => citations_path(self.sources.get_by_right(&id).unwrap()),
+        }
+    }
+
+    /// Start watching a directory and all of its content, the changes are sent to the receiver
+    /// Note, sources outside of the directory are watched once they are loaded
+    pub fn watch_directory(
+        &mut self,
+        root: impl Into<PathBuf>,
+    ) -> notify::Result<Receiver<DebouncedEvent>> {
+        let root = root.into();
+        let (tx, rx) = channel();
+        let mut watcher = notify::watcher(tx, WATCH_DELAY)?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        self.watcher = Some(DirectoryWatcher {
+            watcher: Arc::new(Mutex::new(watcher)),
+            root,
+        });
+
+        Ok(rx)
+    }
+
+    /// Record that a source is now somewhere else (i.e. because it was moved)
+    pub fn relocate_source(&mut self, id: SourceId, path: PathBuf) {
+        self.sources.remove_by_right(&id);
+        self.sources.insert(path, id);
+    }
+
+    /// Forget a source (i.e. because it was deleted), returning where it was
+    pub fn remove_source(&mut self, id: SourceId) -> Option<PathBuf> {
+        self.citations.remove(&id);
+        self.sources.remove_by_right(&id).map(|(path, _)| path)
+    }
 }
 
 impl FileSystem for InternerFileSystem {
@@ -65,36 +159,89 @@ impl FileSystem for InternerFileSystem {
             return *self.sources.get_by_left(&path).unwrap();
         }
 
-        // Guarantee that we never overwrite a value
-        let mut new_id = self.sources.len() as u32;
-        while self.sources.contains_right(&SourceId::from_integer(new_id)) {
-            new_id += 1;
-        }
-
-        let new_id = SourceId::from_integer(new_id);
+        // Note, the queries about a removed source are never asked again of a new one
+        let new_id = SourceId::from_integer(self.next_id);
+        self.next_id += 1;
         self.sources.insert(path, new_id);
         new_id
     }
 
     fn watch(&self, id: SourceId) {
-        let _path = self.sources.get_by_right(&id).unwrap();
-        /*
-        // TODO: Error recovery
-        self.watcher
-            .watch(path, RecursiveMode::NonRecursive)
-            .unwrap();
-        */
+        let path = self.sources.get_by_right(&id).unwrap();
+        let dir = match &self.watcher {
+            Some(dir) => dir,
+            None => return,
+        };
+
+        // The sources in the directory are already watched
+        if !path.starts_with(&dir.root) {
+            if let Err(err) = dir
+                .watcher
+                .lock()
+                .unwrap()
+                .watch(path, RecursiveMode::NonRecursive)
+            {
+                warn!("Could not watch {:?}: {}", path, err);
+            }
+        }
     }
 
     fn load(&self, id: SourceId) -> String {
+        // Note, a file can be gone before the watcher hears of it, it is then read as empty
+        let path = match self.sources.get_by_right(&id) {
+            Some(path) => path,
+            None => {
+                warn!("Unknown source {:?}", id);
+                return String::new();
+            }
+        };
+
         // TODO: Check conversions
-        std::fs::read_to_string(self.sources.get_by_right(&id).unwrap()).unwrap()
+        std::fs::read_to_string(path).unwrap_or_else(|err| {
+            warn!("Could not read {:?}: {}", path, err);
+            String::new()
+        })
+    }
+
+    fn format(&self, id: SourceId) -> SourceFormat {
+        self.sources
+            .get_by_right(&id)
+            .map(|path| source_format(path))
+            .unwrap_or(SourceFormat::Text)
+    }
+
+    fn load_citations(&self, id: SourceId) -> Option<String> {
+        // Note, the sidecar is optional
+        if !self.sources.contains_right(&id) {
+            return None;
+        }
+        std::fs::read_to_string(self.citations_path_of(id)).ok()
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct MockFileSystem {
     strings: BiMap<String, SourceId>,
+    // Note, the sources that are not here are plain text
+    formats: HashMap<SourceId, SourceFormat>,
+}
+
+impl MockFileSystem {
+    /// Intern a source in the CoNLL-U format
+    pub fn intern_treebank(&mut self, content: String) -> SourceId {
+        self.intern_with_format(content, SourceFormat::Treebank)
+    }
+
+    /// Intern a source in TEI XML
+    pub fn intern_tei(&mut self, content: String) -> SourceId {
+        self.intern_with_format(content, SourceFormat::Tei)
+    }
+
+    fn intern_with_format(&mut self, content: String, format: SourceFormat) -> SourceId {
+        let id = self.intern_source(content);
+        self.formats.insert(id, format);
+        id
+    }
 }
 
 impl FileSystem for MockFileSystem {
@@ -112,4 +259,12 @@ impl FileSystem for MockFileSystem {
     fn load(&self, id: SourceId) -> String {
         self.strings.get_by_right(&id).unwrap().clone()
     }
+
+    fn format(&self, id: SourceId) -> SourceFormat {
+        self.formats.get(&id).cloned().unwrap_or(SourceFormat::Text)
+    }
+
+    fn load_citations(&self, _: SourceId) -> Option<String> {
+        None
+    }
 }
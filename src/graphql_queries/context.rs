@@ -1,18 +1,69 @@
+use crate::query_driver::editor::CorpusEditor;
 use crate::query_driver::MainDatabase;
-use salsa::Snapshot;
-use std::sync::Mutex;
-use std::sync::MutexGuard;
+use salsa::{ParallelDatabase, Snapshot};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
 
-#[derive(Debug)]
-pub struct Context(Mutex<Snapshot<MainDatabase>>);
+/// A snapshot of the database, and (if the database can be changed) a way to change it
+pub struct Context {
+    snapshot: Mutex<Option<Snapshot<MainDatabase>>>,
+    editing: Option<(Arc<Mutex<MainDatabase>>, Arc<CorpusEditor>)>,
+}
+
+/// Access to the snapshot of a context
+pub struct ContextGuard<'a>(MutexGuard<'a, Option<Snapshot<MainDatabase>>>);
+
+impl<'a> Deref for ContextGuard<'a> {
+    type Target = Snapshot<MainDatabase>;
+
+    fn deref(&self) -> &Self::Target {
+        // Note, the snapshot is only missing while the database is being changed
+        self.0.as_ref().unwrap()
+    }
+}
 
 impl Context {
     pub fn new(db: Snapshot<MainDatabase>) -> Self {
-        Context(Mutex::new(db))
+        Context {
+            snapshot: Mutex::new(Some(db)),
+            editing: None,
+        }
+    }
+
+    /// A context where the database can be changed through the editor
+    pub fn new_editable(db: Arc<Mutex<MainDatabase>>, editor: Arc<CorpusEditor>) -> Self {
+        let snapshot = db.lock().unwrap().snapshot();
+        Context {
+            snapshot: Mutex::new(Some(snapshot)),
+            editing: Some((db, editor)),
+        }
     }
 
-    pub fn get(&self) -> MutexGuard<Snapshot<MainDatabase>> {
-        self.0.lock().unwrap()
+    pub fn get(&self) -> ContextGuard<'_> {
+        ContextGuard(self.snapshot.lock().unwrap())
+    }
+
+    /// Change the database, the context then sees the changes
+    /// Returns None if the database cannot be changed
+    pub fn edit<T>(&self, f: impl FnOnce(&mut MainDatabase, &CorpusEditor) -> T) -> Option<T> {
+        let (db, editor) = self.editing.as_ref()?;
+
+        let mut snapshot = self.snapshot.lock().unwrap();
+        // Note, changing the database waits for all the snapshots to be dropped, ours included
+        *snapshot = None;
+        let mut db = db.lock().unwrap();
+        let res = f(&mut db, editor);
+        *snapshot = Some(db.snapshot());
+
+        Some(res)
+    }
+}
+
+impl std::fmt::Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("editable", &self.editing.is_some())
+            .finish()
     }
 }
 
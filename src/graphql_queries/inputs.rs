@@ -1,5 +1,10 @@
 use super::context::Context;
+use super::types::Certainty;
+use crate::authors_chrono::Author;
+use crate::query_driver::editor::{time_span, EditError};
 use crate::query_system::ids::AuthorId;
+use crate::query_system::lit_subset::LitSubset;
+use crate::query_system::sources::SourcesDatabase;
 use chrono::NaiveDate;
 use std::collections::BTreeSet;
 use std::collections::HashSet;
@@ -11,6 +16,13 @@ pub trait Filter {
     /// Get the authors that the filters restricts to
     fn get_authors(&self, context: &Context) -> BTreeSet<AuthorId>;
 
+    /// Get the sources that the filter restricts to, by default all those of its authors
+    fn get_subset(&self, context: &Context) -> LitSubset {
+        let authors = self.get_authors(context);
+        let db = context.get();
+        LitSubset::from_authors(authors.iter(), &db)
+    }
+
     /// Given filters with author sets A B, make a filter which computes A intersect B
     fn intersect<T>(self, other: T) -> FilterIntersect<Self, T>
     where
@@ -32,6 +44,19 @@ pub struct AuthorsInput {
     list: Option<Vec<String>>,
 }
 
+impl AuthorsInput {
+    /// The names of the list that are not the name of any author
+    pub fn unknown_names(&self, context: &Context) -> Vec<String> {
+        let db = context.get();
+        self.list
+            .iter()
+            .flatten()
+            .filter(|name| !db.authors().contains_left(&Author::new(name.as_str())))
+            .cloned()
+            .collect()
+    }
+}
+
 impl Filter for AuthorsInput {
     fn all() -> Self {
         Self {
@@ -68,6 +93,8 @@ impl Filter for AuthorsInput {
 pub struct Span {
     start_year: NaiveDate,
     end_year: NaiveDate,
+    #[graphql(description = "The least certain dating accepted, any of them by default")]
+    certainty: Option<Certainty>,
 }
 
 #[derive(juniper::GraphQLInputObject, Debug)]
@@ -89,19 +116,28 @@ impl Filter for SpanInput {
         }
     }
 
+    // Get the authors that wrote some of the sources in the span
     fn get_authors(&self, context: &Context) -> BTreeSet<AuthorId> {
+        let subset = self.get_subset(context);
+        let db = context.get();
+        subset
+            .sources()
+            .iter()
+            .map(|&source| db.associated_author(source))
+            .collect()
+    }
+
+    // Note, the sources of a work with a date of its own are dated by it, rather than by their author
+    fn get_subset(&self, context: &Context) -> LitSubset {
         let db = context.get();
         if self.use_all {
-            return db.authors().right_values().cloned().collect();
+            return LitSubset::from_authors(db.authors().right_values(), &db);
         }
         let span = self.span.as_ref().cloned().unwrap();
         let timespan = crate::authors_chrono::TimeSpan::new(span.start_year, span.end_year);
+        let certainty = span.certainty.unwrap_or(Certainty::Terminus).into();
 
-        db.authors()
-            .iter()
-            .filter(|(k, _)| k.in_timespan(&timespan))
-            .map(|(_, v)| *v)
-            .collect()
+        LitSubset::from_timespan_with(&timespan, certainty, db.authors(), &db)
     }
 }
 
@@ -124,4 +160,23 @@ where
             .cloned()
             .collect()
     }
+
+    fn get_subset(&self, context: &Context) -> LitSubset {
+        self.0
+            .get_subset(context)
+            .intersection(&self.1.get_subset(context))
+    }
+}
+
+#[derive(juniper::GraphQLInputObject, Debug)]
+#[graphql(description = "When an author was active, in years (negative before Christ)")]
+pub struct TimeSpanInput {
+    start: i32,
+    end: i32,
+}
+
+impl TimeSpanInput {
+    pub fn to_time_span(&self) -> Result<crate::authors_chrono::TimeSpan, EditError> {
+        time_span(self.start, self.end)
+    }
 }
@@ -1,21 +1,28 @@
 pub mod context;
 mod inputs;
+mod morphology;
 mod stats;
 mod types;
 
-use crate::latin_utilities::NormalizedLatinString;
+use crate::latin_utilities::variants::variant_key;
+use crate::query_driver::editor::{CorpusEditor, EditError};
+use crate::query_driver::MainDatabase as Database;
 use crate::query_system::traits::*;
-use crate::word_db::WordDatabase;
+use crate::word_db::{WordDatabase, WordId, WordPattern};
 use context::Context;
-use inputs::{AuthorsInput, Filter, SpanInput};
-use types::{Author, Form, Lemma, WordType};
+use inputs::{AuthorsInput, Filter, SpanInput, TimeSpanInput};
+use morphology::{Feature, PartOfSpeech};
+use types::{
+    Author, Form, FormLookup, Lemma, LemmaLookup, NotFound, Occurrence, Parallel, PhraseMatch,
+    PhraseMode, SearchMode, Source, VariantFamily, WordType, Work,
+};
 
-use juniper::{graphql_value, EmptyMutation, FieldError, FieldResult, RootNode};
+use juniper::{graphql_value, FieldError, FieldResult, RootNode};
 
-pub type Schema = RootNode<'static, Query, EmptyMutation<Context>>;
+pub type Schema = RootNode<'static, Query, Mutation>;
 
 pub fn schema() -> Schema {
-    Schema::new(Query, EmptyMutation::<Context>::new())
+    Schema::new(Query, Mutation)
 }
 
 pub struct Query;
@@ -53,9 +60,60 @@ impl Query {
             .collect())
     }
 
+    /// The works in the corpus, of an author if one is given
+    fn works(
+        context: &Context,
+        author: Option<String>,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<Work>> {
+        let db = context.get();
+
+        let limit = match first {
+            Some(i) if i >= 0 => i as usize,
+            Some(_) => {
+                return Err(FieldError::new(
+                    "Invalid number of records",
+                    graphql_value!({ "input_error" : "i"}),
+                ))
+            }
+            None => db.works().len(),
+        };
+
+        let author = match author {
+            Some(name) => match db
+                .authors()
+                .get_by_left(&crate::authors_chrono::Author::new(name))
+            {
+                Some(&id) => Some(id),
+                None => return Ok(Vec::new()),
+            },
+            None => None,
+        };
+
+        Ok(db
+            .works()
+            .iter()
+            .filter(|(work, _)| author.map(|a| work.author() == a).unwrap_or(true))
+            .map(|(_, &id)| Work::new(id))
+            .take(limit)
+            .collect())
+    }
+
+    /// The work of an author with a title, if there is one
+    fn work(context: &Context, author: String, title: String) -> Option<Work> {
+        let db = context.get();
+        let &author = db
+            .authors()
+            .get_by_left(&crate::authors_chrono::Author::new(author))?;
+        db.works()
+            .get_by_left(&crate::authors_chrono::Work::new(author, title))
+            .map(|&id| Work::new(id))
+    }
+
     fn word_type(context: &Context, word: String) -> FieldResult<WordType> {
-        let word = NormalizedLatinString::from(word.as_str());
         let db = context.get();
+        // Note, the words are looked up in the spelling of the corpus
+        let word = db.normalization().converter().convert(word);
         let possible_id = db.lookup_interned_word(word);
 
         if let Some(id) = possible_id {
@@ -77,26 +135,19 @@ impl Query {
         authors: AuthorsInput,
         rest_of_lit: SpanInput,
     ) -> FieldResult<Vec<Lemma>> {
-        use super::query_system::lit_subset::LitSubset;
-
-        let authors = authors.get_authors(context);
-        let rest_of_lit = rest_of_lit.get_authors(context);
+        let lit = authors.get_subset(context);
+        let rest_of_lit = rest_of_lit.get_subset(context);
         let db = context.get();
 
-        let lemmas = db.intersect_sources(
-            LitSubset::from_authors(authors.iter(), &db.snapshot()),
-            LitSubset::from_authors(rest_of_lit.iter(), &db.snapshot()),
-        );
+        let lemmas = db.intersect_sources(lit.clone(), rest_of_lit);
 
-        Ok(lemmas
-            .iter()
-            .map(|l| Lemma::from_iter(*l, authors.iter().cloned()))
-            .collect())
+        Ok(lemmas.iter().map(|l| Lemma::new(*l, lit.clone())).collect())
     }
 
     fn intersection_hist(context: &Context, authors: AuthorsInput) -> FieldResult<Vec<Lemma>> {
         use super::authors_chrono::TimeSpan;
         use super::query_system::lit_subset::LitSubset;
+        let lit = authors.get_subset(context);
         let authors = authors.get_authors(context);
         let max_timespan = authors
             .iter()
@@ -121,14 +172,201 @@ impl Query {
             &db.snapshot(),
         );
 
-        let lemmas = db.intersect_sources(
-            LitSubset::from_authors(authors.iter(), &db.snapshot()),
-            rest_of_lit,
+        let lemmas = db.intersect_sources(lit.clone(), rest_of_lit);
+
+        Ok(lemmas.iter().map(|l| Lemma::new(*l, lit.clone())).collect())
+    }
+
+    #[graphql(arguments(
+        source(description = "The authors to look for parallels in"),
+        target(description = "The authors the parallels are with"),
+        min_shared(description = "How many lemmas two lines need to share", default = 2,),
+        stop_words(
+            description = "How many of the most frequent lemmas to ignore",
+            default = 10,
+        ),
+        words(description = "Share the lemmas in windows of this many words, not in lines"),
+        first(description = "Only return the best parallels")
+    ))]
+    fn parallels(
+        context: &Context,
+        source: AuthorsInput,
+        target: AuthorsInput,
+        min_shared: i32,
+        stop_words: i32,
+        words: Option<i32>,
+        first: Option<i32>,
+    ) -> FieldResult<Vec<Parallel>> {
+        use super::query_system::lit_subset::LitSubset;
+        use super::query_system::parallels::{find_parallels, ParallelsConfig, Unit};
+
+        if min_shared < 0
+            || stop_words < 0
+            || words.map(|i| i < 1).unwrap_or(false)
+            || first.map(|i| i < 0).unwrap_or(false)
+        {
+            return Err(FieldError::new(
+                "Invalid number of records",
+                graphql_value!({ "input_error" : "i"}),
+            ));
+        }
+
+        let unknown: Vec<_> = source
+            .unknown_names(context)
+            .into_iter()
+            .chain(target.unknown_names(context))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(FieldError::new(
+                format!("Unknown authors {}", unknown.join(", ")),
+                graphql_value!({ "input_error" : "authors"}),
+            ));
+        }
+
+        let source = source.get_subset(context);
+        let target = target.get_subset(context);
+        let db = context.get();
+
+        let config = ParallelsConfig {
+            min_shared: min_shared as usize,
+            stop_words: stop_words as usize,
+            unit: match words {
+                Some(n) => Unit::Words(n as usize),
+                None => Unit::Line,
+            },
+        };
+        let res = find_parallels(
+            &**db,
+            &source,
+            &target,
+            &LitSubset::from_authors(db.authors().right_values(), &db),
+            &config,
         );
 
-        Ok(lemmas
+        let lit = source.union(&target);
+        Ok(res
+            .into_iter()
+            .take(first.map(|i| i as usize).unwrap_or(usize::MAX))
+            .map(|p| Parallel::new(p, lit.clone()))
+            .collect())
+    }
+
+    #[graphql(
+        arguments(
+            query(
+                description = "The words of the phrase, separated by whitespace"
+            ),
+            mode(
+                description = "Whether the words are forms or lemmas",
+                default = PhraseMode::Form,
+            ),
+            authors(
+                description = "The authors to query",
+                default = AuthorsInput::all(),
+            ),
+            span(
+                description = "The timespan to search",
+                default = SpanInput::all()
+            )
+        )
+    )]
+    fn phrase(
+        context: &Context,
+        query: String,
+        mode: PhraseMode,
+        authors: AuthorsInput,
+        span: SpanInput,
+    ) -> FieldResult<Vec<PhraseMatch>> {
+        use super::query_system::ngrams::Phrase;
+        use super::query_system::types;
+
+        let lit = authors.intersect(span).get_subset(context);
+        let db = context.get();
+
+        // Note, a word that was never seen cannot be part of any match
+        let converter = db.normalization().converter();
+        let words: Option<Vec<_>> = query
+            .split_whitespace()
+            .map(|w| db.lookup_interned_word(converter.convert(w)))
+            .collect();
+        let words = match words {
+            Some(words) if !words.is_empty() => words,
+            Some(_) => {
+                return Err(FieldError::new(
+                    "Empty phrase",
+                    graphql_value!({ "input_error" : "query"}),
+                ))
+            }
+            None => return Ok(Vec::new()),
+        };
+
+        let phrase = match mode {
+            PhraseMode::Form => Phrase::Forms(words.into_iter().map(types::Form).collect()),
+            PhraseMode::Lemma => Phrase::Lemmas(words.into_iter().map(types::Lemma).collect()),
+        };
+
+        Ok(db
+            .phrase_occurrences(phrase, lit)
+            .iter()
+            .cloned()
+            .map(PhraseMatch::new)
+            .collect())
+    }
+
+    #[graphql(
+        arguments(
+            lemma(
+                description = "The lemma the occurrences should be read as, if any"
+            ),
+            part_of_speech(
+                description = "The part of speech of the occurrences, if any"
+            ),
+            features(
+                description = "The features the occurrences should all have",
+            ),
+            authors(
+                description = "The authors to query",
+                default = AuthorsInput::all(),
+            ),
+            span(
+                description = "The timespan to search",
+                default = SpanInput::all()
+            )
+        )
+    )]
+    /// The occurrences with the given morphology (i.e. the perfect subjunctives of an author)
+    /// Note, the ablative absolutes of a verb can be found among its ablative participles
+    fn morphology(
+        context: &Context,
+        lemma: Option<String>,
+        part_of_speech: Option<PartOfSpeech>,
+        features: Vec<Feature>,
+        authors: AuthorsInput,
+        span: SpanInput,
+    ) -> FieldResult<Vec<Occurrence>> {
+        use super::query_system::morphology::Morphology;
+        use super::query_system::types;
+
+        let lit = authors.intersect(span).get_subset(context);
+        let db = context.get();
+
+        let mut morphology = Morphology::new(
+            part_of_speech.map(Into::into),
+            features.into_iter().map(Into::into),
+        );
+        if let Some(lemma) = lemma {
+            // Note, a lemma that was never seen has no occurrences
+            match db.lookup_interned_word(db.normalization().converter().convert(lemma)) {
+                Some(id) => morphology = morphology.with_lemma(types::Lemma(id)),
+                None => return Ok(Vec::new()),
+            }
+        }
+
+        Ok(db
+            .morphology_occurrences(morphology, lit)
             .iter()
-            .map(|l| Lemma::from_iter(*l, authors.iter().cloned()))
+            .cloned()
+            .map(Occurrence::new)
             .collect())
     }
 
@@ -152,15 +390,23 @@ impl Query {
         lemma: String,
         authors: AuthorsInput,
         span: SpanInput,
-    ) -> FieldResult<Lemma> {
-        let nw = NormalizedLatinString::from(lemma.as_str());
-        let authors = authors.intersect(span).get_authors(context);
+    ) -> FieldResult<LemmaLookup> {
+        let lit = authors.intersect(span).get_subset(context);
 
-        let db = context.get();
-        let id = db.intern_word(nw);
-        let lemma = crate::query_system::types::Lemma(id);
+        let is_lemma = |db: &Database, id| db.lemmatizer().has_lemma(id);
+        let found = {
+            let db = context.get();
+            db.lookup_interned_word(db.normalization().converter().convert(&lemma))
+                .filter(|&id| is_lemma(&db, id))
+        };
 
-        Ok(Lemma::from_iter(lemma, authors))
+        Ok(match found {
+            Some(id) => {
+                let lemma = crate::query_system::types::Lemma(id);
+                LemmaLookup::Found(Lemma::new(lemma, lit))
+            }
+            None => LemmaLookup::NotFound(not_found(context, &lemma, is_lemma)?),
+        })
     }
 
     #[graphql(
@@ -183,14 +429,548 @@ impl Query {
         form: String,
         authors: AuthorsInput,
         span: SpanInput,
-    ) -> FieldResult<Form> {
-        let nw = NormalizedLatinString::from(form.as_str());
-        let authors = authors.intersect(span).get_authors(context);
+    ) -> FieldResult<FormLookup> {
+        let lit = authors.intersect(span).get_subset(context);
 
-        let db = context.get();
-        let id = db.intern_word(nw);
-        let form = crate::query_system::types::Form(id);
+        // Note, a form of the texts is found even if the lemmatizer does not know it
+        let found = {
+            let db = context.get();
+            db.lookup_interned_word(db.normalization().converter().convert(&form))
+        };
+
+        Ok(match found {
+            Some(id) => {
+                let form = crate::query_system::types::Form(id);
+                FormLookup::Found(Form::new(form, lit))
+            }
+            None => FormLookup::NotFound(not_found(context, &form, |db, id| {
+                db.lemmatizer().has_form(id)
+            })?),
+        })
+    }
+
+    #[graphql(
+        arguments(
+            pattern(
+                description = "What to look for, read according to the mode"
+            ),
+            mode(
+                description = "Whether the pattern is a wildcard, a regex or a word to match loosely",
+                default = SearchMode::Wildcard,
+            ),
+            distance(
+                description = "How many edits a fuzzy match can be away from the pattern",
+                default = 1,
+            ),
+            first(
+                description = "The most forms to return"
+            ),
+            authors(
+                description = "The authors to query",
+                default = AuthorsInput::all(),
+            ),
+            span(
+                description = "The timespan to search",
+                default = SpanInput::all()
+            )
+        )
+    )]
+    /// The forms of the lemmatizer that match a pattern and occur in the texts of the authors,
+    /// in alphabetical order (or by distance first, for a fuzzy search)
+    fn search_forms(
+        context: &Context,
+        pattern: String,
+        mode: SearchMode,
+        distance: i32,
+        first: Option<i32>,
+        authors: AuthorsInput,
+        span: SpanInput,
+    ) -> FieldResult<Vec<Form>> {
+        let lit = authors.intersect(span).get_subset(context);
+        // Note, the forms that do not occur are left out before the first ones are taken
+        let occurring = context.get().forms_in_subset(lit.clone());
+
+        let forms = search_words(context, &pattern, mode, distance, first, |db, id| {
+            db.lemmatizer().has_form(id)
+                && occurring.contains(&crate::query_system::types::Form(id))
+        })?;
+        Ok(forms
+            .into_iter()
+            .map(|id| Form::new(crate::query_system::types::Form(id), lit.clone()))
+            .collect())
+    }
+
+    #[graphql(
+        arguments(
+            pattern(
+                description = "What to look for, read according to the mode"
+            ),
+            mode(
+                description = "Whether the pattern is a wildcard, a regex or a word to match loosely",
+                default = SearchMode::Wildcard,
+            ),
+            distance(
+                description = "How many edits a fuzzy match can be away from the pattern",
+                default = 1,
+            ),
+            first(
+                description = "The most lemmas to return"
+            ),
+            authors(
+                description = "The authors to query",
+                default = AuthorsInput::all(),
+            ),
+            span(
+                description = "The timespan to search",
+                default = SpanInput::all()
+            )
+        )
+    )]
+    /// The lemmas of the lemmatizer that match a pattern and occur in the texts of the authors,
+    /// in alphabetical order (or by distance first, for a fuzzy search)
+    fn search_lemmas(
+        context: &Context,
+        pattern: String,
+        mode: SearchMode,
+        distance: i32,
+        first: Option<i32>,
+        authors: AuthorsInput,
+        span: SpanInput,
+    ) -> FieldResult<Vec<Lemma>> {
+        let lit = authors.intersect(span).get_subset(context);
+        let occurring = context.get().lemmas_in_subset(lit.clone());
+
+        let lemmas = search_words(context, &pattern, mode, distance, first, |db, id| {
+            db.lemmatizer().has_lemma(id)
+                && occurring.contains(&crate::query_system::types::Lemma(id))
+        })?;
+        Ok(lemmas
+            .into_iter()
+            .map(|id| Lemma::new(crate::query_system::types::Lemma(id), lit.clone()))
+            .collect())
+    }
+
+    #[graphql(
+        arguments(
+            form(
+                description = "One of the spellings of the form"
+            ),
+            authors(
+                description = "The authors to query",
+                default = AuthorsInput::all(),
+            ),
+            span(
+                description = "The timespan to search",
+                default = SpanInput::all()
+            )
+        )
+    )]
+    /// The family of spelling variants of a form (i.e. `affero` for `adfero`), none if the form is not known
+    fn variants(
+        context: &Context,
+        form: String,
+        authors: AuthorsInput,
+        span: SpanInput,
+    ) -> FieldResult<Option<VariantFamily>> {
+        let lit = authors.intersect(span).get_subset(context);
+
+        let (key, forms) = {
+            let db = context.get();
+            let form = db.normalization().converter().convert(form);
+            let key = variant_key(form.inner());
+            let forms: Vec<_> = match db.variant_families().get(&key) {
+                Some(family) => family.iter().cloned().collect(),
+                None => db
+                    .lookup_interned_word(form)
+                    .filter(|&id| db.lemmatizer().get_possible_lemmas(id).is_some())
+                    .map(crate::query_system::types::Form)
+                    .into_iter()
+                    .collect(),
+            };
+            (key, forms)
+        };
+
+        if forms.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(VariantFamily::new(context, key, forms, lit)))
+    }
+}
+
+// Get the interned words that match a pattern and a test, at most `first` of them
+fn search_words(
+    context: &Context,
+    pattern: &str,
+    mode: SearchMode,
+    distance: i32,
+    first: Option<i32>,
+    test: impl Fn(&Database, WordId) -> bool,
+) -> FieldResult<Vec<WordId>> {
+    let limit = match first {
+        Some(i) if i >= 0 => i as usize,
+        Some(_) => {
+            return Err(FieldError::new(
+                "Invalid number of records",
+                graphql_value!({ "input_error" : "first"}),
+            ))
+        }
+        None => usize::MAX,
+    };
+
+    if distance < 0 {
+        return Err(FieldError::new(
+            "Invalid distance",
+            graphql_value!({ "input_error" : "distance"}),
+        ));
+    }
+
+    let db = context.get();
+    let converter = db.normalization().converter();
+    let pattern = match mode {
+        SearchMode::Wildcard => WordPattern::wildcard(pattern, &converter),
+        SearchMode::Fuzzy => WordPattern::fuzzy(pattern, distance as usize, &converter),
+        SearchMode::Regex => WordPattern::regex(pattern).map_err(|err| {
+            FieldError::new(
+                format!("Invalid regex: {}", err),
+                graphql_value!({ "input_error" : "pattern"}),
+            )
+        })?,
+    };
+
+    Ok(db
+        .search_words(&pattern)
+        .into_iter()
+        .filter(|&id| test(&db, id))
+        .take(limit)
+        .collect())
+}
+
+// How far, and how many, the suggestions for a word that was not found are
+const SUGGESTION_DISTANCE: i32 = 2;
+const SUGGESTIONS: i32 = 5;
+
+// A word that was not found, with the closest words that pass a test
+fn not_found(
+    context: &Context,
+    word: &str,
+    test: impl Fn(&Database, WordId) -> bool,
+) -> FieldResult<NotFound> {
+    let suggestions = search_words(
+        context,
+        word,
+        SearchMode::Fuzzy,
+        SUGGESTION_DISTANCE,
+        Some(SUGGESTIONS),
+        test,
+    )?;
+
+    let db = context.get();
+    let word = db.normalization().converter().convert(word);
+    Ok(NotFound::new(
+        word.inner().to_string(),
+        suggestions
+            .into_iter()
+            .map(|id| db.lookup_word_str(id).to_string())
+            .collect(),
+    ))
+}
+
+// Run an edit on the database, if the context allows it
+fn edit<T>(
+    context: &Context,
+    f: impl FnOnce(&mut Database, &CorpusEditor) -> Result<T, EditError>,
+) -> FieldResult<T> {
+    match context.edit(f) {
+        Some(res) => res.map_err(|err| {
+            FieldError::new(
+                err.to_string(),
+                graphql_value!({ "edit_error" : "The corpus was not changed"}),
+            )
+        }),
+        None => Err(FieldError::new(
+            "The database is read only",
+            graphql_value!({ "edit_error" : "No editor available"}),
+        )),
+    }
+}
+
+fn to_time_span(
+    span: Option<TimeSpanInput>,
+) -> FieldResult<Option<crate::authors_chrono::TimeSpan>> {
+    match span {
+        Some(span) => Ok(Some(span.to_time_span().map_err(|err| {
+            FieldError::new(
+                err.to_string(),
+                graphql_value!({ "input_error" : "timeSpan"}),
+            )
+        })?)),
+        None => Ok(None),
+    }
+}
+
+pub struct Mutation;
+
+#[juniper::object(Context = Context)]
+impl Mutation {
+    #[graphql(arguments(
+        name(description = "The name of the author, which is also the name of its folder"),
+        time_span(description = "When the author was active"),
+    ))]
+    fn add_author(
+        context: &Context,
+        name: String,
+        time_span: Option<TimeSpanInput>,
+    ) -> FieldResult<Author> {
+        let span = to_time_span(time_span)?;
+        edit(context, |db, editor| editor.add_author(db, &name, span)).map(Author::new)
+    }
+
+    fn rename_author(context: &Context, name: String, new_name: String) -> FieldResult<Author> {
+        edit(context, |db, editor| {
+            editor.rename_author(db, &name, &new_name)
+        })
+        .map(Author::new)
+    }
+
+    #[graphql(arguments(time_span(description = "The new time span, none to clear it")))]
+    fn set_time_span(
+        context: &Context,
+        author: String,
+        time_span: Option<TimeSpanInput>,
+    ) -> FieldResult<Author> {
+        let span = to_time_span(time_span)?;
+        edit(context, |db, editor| {
+            editor.set_time_span(db, &author, span)
+        })
+        .map(Author::new)
+    }
+
+    #[graphql(arguments(
+        author(description = "The current author of the source"),
+        source(description = "The file name of the source"),
+        to(description = "The author to move the source to"),
+    ))]
+    fn move_source(
+        context: &Context,
+        author: String,
+        source: String,
+        to: String,
+    ) -> FieldResult<Source> {
+        edit(context, |db, editor| {
+            let source = editor.find_source(db, &author, &source)?;
+            editor.move_source(db, source, &to)
+        })
+        .map(Source::new)
+    }
+
+    #[graphql(arguments(
+        author(description = "The author of the source"),
+        name(description = "The file name of the source"),
+        text(description = "The content of the source"),
+    ))]
+    fn upload_source(
+        context: &Context,
+        author: String,
+        name: String,
+        text: String,
+    ) -> FieldResult<Source> {
+        edit(context, |db, editor| {
+            editor.upload_source(db, &author, &name, &text)
+        })
+        .map(Source::new)
+    }
+
+    /// Choose the lemma of a single occurrence of an ambiguous form, false if nothing changed
+    #[graphql(arguments(
+        author(description = "The author of the source"),
+        source(description = "The file name of the source"),
+        line_no(description = "The (zero based) line of the occurrence"),
+        index(description = "The position of the occurrence among the words of its line"),
+        lemma(description = "The lemma to count the occurrence under, none to forget it"),
+    ))]
+    fn choose_lemma(
+        context: &Context,
+        author: String,
+        source: String,
+        line_no: i32,
+        index: i32,
+        lemma: Option<String>,
+    ) -> FieldResult<bool> {
+        if line_no < 0 || index < 0 {
+            return Err(FieldError::new(
+                "Invalid position",
+                graphql_value!({ "input_error" : "lineNo, index"}),
+            ));
+        }
+
+        edit(context, |db, editor| {
+            let source = editor.find_source(db, &author, &source)?;
+            editor.set_override(
+                db,
+                source,
+                line_no as usize,
+                index as usize,
+                lemma.as_deref(),
+            )
+        })
+    }
+
+    /// Add a form -> lemma pair to the lemmatizer, false if it was already there
+    fn add_lemma(context: &Context, form: String, lemma: String) -> FieldResult<bool> {
+        edit(context, |db, editor| editor.add_lemma(db, &form, &lemma))
+    }
+
+    /// Remove a form -> lemma pair from the lemmatizer, false if it was not there
+    fn remove_lemma(context: &Context, form: String, lemma: String) -> FieldResult<bool> {
+        edit(context, |db, editor| editor.remove_lemma(db, &form, &lemma))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_driver::driver_init;
+    use crate::query_driver::test_corpus::TestCorpus;
+    use juniper::Variables;
+    use salsa::ParallelDatabase;
+
+    #[test]
+    fn test_lookup() {
+        let corpus = TestCorpus::new("lookup")
+            .with_lemmatizer("arma,arma,arma\ncano,cano,cano")
+            .with_source("Vergilius/aeneid.txt", "arma cano");
+
+        let db = driver_init(corpus.config()).unwrap();
+        let words = db.word_db().len();
+        let context = Context::new(db.snapshot());
+        let query = r#"{
+            found: form(form: "Arma") { ... on Form { count } }
+            missing: lemma(lemma: "armo") { ... on NotFound { word suggestions } }
+        }"#;
+        let (res, errors) =
+            juniper::execute(query, None, &schema(), &Variables::new(), &context).unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            res,
+            graphql_value!({
+                "found": { "count": 1 },
+                "missing": { "word": "armo", "suggestions": ["arma"] },
+            })
+        );
+        assert_eq!(db.word_db().len(), words);
+    }
+
+    #[test]
+    fn test_parallels() {
+        let corpus = TestCorpus::new("parallels")
+            .with_lemmatizer("arma,arma,arma\ncano,cano,cano")
+            .with_source("Vergilius/aeneid.txt", "arma cano")
+            .with_source("Ovidius/amores.txt", "cano arma");
+
+        let db = driver_init(corpus.config()).unwrap();
+        let context = Context::new(db.snapshot());
+        let query = r#"{
+            parallels(
+                source: { useAll: false, list: ["Vergilius"] },
+                target: { useAll: false, list: ["Ovidius"] },
+                stopWords: 0
+            ) { source { line } target { line } }
+        }"#;
+        let (res, errors) =
+            juniper::execute(query, None, &schema(), &Variables::new(), &context).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(
+            res,
+            graphql_value!({
+                "parallels": [{ "source": { "line": "arma cano" }, "target": { "line": "cano arma" } }],
+            })
+        );
+
+        // A misspelled author is reported, rather than giving no parallels
+        let query = r#"{
+            parallels(
+                source: { useAll: false, list: ["Vergilius"] },
+                target: { useAll: false, list: ["Lucanus"] }
+            ) { score }
+        }"#;
+        let (_, errors) =
+            juniper::execute(query, None, &schema(), &Variables::new(), &context).unwrap();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_search() {
+        let corpus = TestCorpus::new("search")
+            .with_lemmatizer("arma,arma,arma\narmis,armis,arma\narmenta,armenta,armentum")
+            .with_source("Vergilius/aeneid.txt", "arma")
+            .with_source("Ovidius/amores.txt", "armis armenta");
+
+        let db = driver_init(corpus.config()).unwrap();
+        let context = Context::new(db.snapshot());
+        let query = r#"{
+            searchForms(pattern: "arm*", first: 1, authors: { useAll: false, list: ["Ovidius"] }) {
+                form
+            }
+            searchLemmas(pattern: "arm*", authors: { useAll: false, list: ["Vergilius"] }) {
+                lemma
+            }
+            variants(form: "arma") { __typename }
+        }"#;
+        let (res, errors) =
+            juniper::execute(query, None, &schema(), &Variables::new(), &context).unwrap();
+
+        // Only the words in the texts of the authors are returned, so `arma` does not use up `first`
+        assert!(errors.is_empty());
+        assert_eq!(
+            res,
+            graphql_value!({
+                "searchForms": [{ "form": "armenta" }],
+                "searchLemmas": [{ "lemma": "arma" }],
+                "variants": { "__typename": "VariantFamily" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_work_dates() {
+        let corpus = TestCorpus::new("work_dates")
+            .with_lemmatizer("arma,arma,arma")
+            .with_source("amores.txt", "arma")
+            .with_source("tristia.txt", "arma arma")
+            .with_file(
+                "manifest.toml",
+                r#"
+            [[work]]
+            author = "Ovidius"
+            title = "Amores"
+            date = { from = 1, to = 5 }
+            files = ["amores.txt"]
+
+            [[work]]
+            author = "Ovidius"
+            title = "Tristia"
+            date = { from = 10, to = 15 }
+            files = ["tristia.txt"]
+            "#,
+            );
+        let config = corpus
+            .config()
+            .with_manifest(corpus.dir().join("manifest.toml"));
+        let db = driver_init(config).unwrap();
+        let context = Context::new(db.snapshot());
+
+        // Note, the span of the author covers both works, but only the later one is in the span
+        let query = r#"{
+            form(
+                form: "arma",
+                span: { useAll: false, span: { startYear: "0009-01-01", endYear: "0020-12-31" } }
+            ) { ... on Form { count } }
+        }"#;
+        let (res, errors) =
+            juniper::execute(query, None, &schema(), &Variables::new(), &context).unwrap();
 
-        Ok(Form::from_iter(form, authors))
+        assert!(errors.is_empty());
+        assert_eq!(res, graphql_value!({ "form": { "count": 2 } }));
     }
 }
@@ -0,0 +1,120 @@
+//! The morphological analyses of the forms, as exposed through GraphQL
+
+use super::context::Context;
+use crate::latin_lemmatizer::morphology;
+use crate::query_system::types;
+use crate::word_db::WordDatabase;
+
+// Mirror an enum of the lemmatizer, converting both ways
+macro_rules! mirror_enum {
+    ($name:ident, $($variant:ident),*) => {
+        #[derive(juniper::GraphQLEnum, Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum $name {
+            $($variant),*
+        }
+
+        impl From<$name> for morphology::$name {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => morphology::$name::$variant),*
+                }
+            }
+        }
+
+        impl From<morphology::$name> for $name {
+            fn from(value: morphology::$name) -> Self {
+                match value {
+                    $(morphology::$name::$variant => $name::$variant),*
+                }
+            }
+        }
+    };
+}
+
+mirror_enum!(
+    PartOfSpeech,
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Interjection,
+    Numeral
+);
+
+mirror_enum!(
+    Feature,
+    Nominative,
+    Genitive,
+    Dative,
+    Accusative,
+    Ablative,
+    Vocative,
+    Locative,
+    Singular,
+    Plural,
+    Masculine,
+    Feminine,
+    Neuter,
+    First,
+    Second,
+    Third,
+    Present,
+    Imperfect,
+    Future,
+    Perfect,
+    Pluperfect,
+    FuturePerfect,
+    Indicative,
+    Subjunctive,
+    Imperative,
+    Infinitive,
+    Participle,
+    Gerund,
+    Gerundive,
+    Supine,
+    Active,
+    Passive,
+    Comparative,
+    Superlative
+);
+
+/// One of the ways a form can be read as one of its lemmas
+pub struct Analysis {
+    lemma: types::Lemma,
+    analysis: morphology::Analysis,
+}
+
+impl Analysis {
+    pub(crate) fn new(lemma: types::Lemma, analysis: morphology::Analysis) -> Self {
+        Analysis { lemma, analysis }
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Analysis {
+    fn lemma(&self, context: &Context) -> String {
+        let db = context.get();
+        db.lookup_word_str(self.lemma.0).to_string()
+    }
+
+    fn part_of_speech(&self) -> Option<PartOfSpeech> {
+        self.analysis.part_of_speech().map(PartOfSpeech::from)
+    }
+
+    fn features(&self) -> Vec<Feature> {
+        self.analysis
+            .features()
+            .iter()
+            .cloned()
+            .map(Feature::from)
+            .collect()
+    }
+
+    /// The tag as it appeared in the lemmatizer
+    fn tag(&self) -> &str {
+        self.analysis.tag()
+    }
+}
@@ -1,13 +1,22 @@
 use super::context::Context;
+use super::morphology::Analysis;
+use crate::authors_chrono;
+use crate::filesystem::SourceFormat;
+use crate::query_system::concordance::{self, Window};
 use crate::query_system::ids::AuthorId;
 use crate::query_system::ids::FormDataId;
 use crate::query_system::ids::SourceId;
+use crate::query_system::ids::WorkId;
 use crate::query_system::lit_subset::LitSubset;
+use crate::query_system::parallels::{self, LineRef};
 use crate::query_system::traits::*;
 use crate::query_system::types;
+use crate::query_system::variants::VariantCounts;
+use crate::query_system::weights::{CountMode, Weighting};
 use crate::word_db::WordDatabase;
 
 use chrono::prelude::Datelike;
+use juniper::{graphql_value, FieldError, FieldResult};
 use std::sync::Arc;
 
 pub struct Author {
@@ -27,6 +36,39 @@ impl TimeSpan {
     fn end(&self) -> i32 {
         self.time_span.end().year()
     }
+
+    /// How much the dates can be trusted
+    fn certainty(&self) -> Certainty {
+        self.time_span.certainty().into()
+    }
+}
+
+#[derive(juniper::GraphQLEnum, Debug, Clone, Copy)]
+pub enum Certainty {
+    /// Only a bound of the dates is known
+    Terminus,
+    Approximate,
+    Exact,
+}
+
+impl From<authors_chrono::Certainty> for Certainty {
+    fn from(certainty: authors_chrono::Certainty) -> Self {
+        match certainty {
+            authors_chrono::Certainty::Terminus => Certainty::Terminus,
+            authors_chrono::Certainty::Approximate => Certainty::Approximate,
+            authors_chrono::Certainty::Exact => Certainty::Exact,
+        }
+    }
+}
+
+impl From<Certainty> for authors_chrono::Certainty {
+    fn from(certainty: Certainty) -> Self {
+        match certainty {
+            Certainty::Terminus => authors_chrono::Certainty::Terminus,
+            Certainty::Approximate => authors_chrono::Certainty::Approximate,
+            Certainty::Exact => authors_chrono::Certainty::Exact,
+        }
+    }
 }
 
 impl Author {
@@ -66,6 +108,66 @@ impl Author {
     fn time_span(&self, context: &Context) -> Option<TimeSpan> {
         self.tspan(context)
     }
+
+    fn works(&self, context: &Context) -> Vec<Work> {
+        let db = context.get();
+        let mut works: Vec<_> = db
+            .works()
+            .iter()
+            .filter(|(work, _)| work.author() == self.id)
+            .map(|(work, &id)| (work.title().to_string(), id))
+            .collect();
+        works.sort();
+        works.into_iter().map(|(_, id)| Work::new(id)).collect()
+    }
+}
+
+/// A work of an author, made of one or more sources
+pub struct Work {
+    id: WorkId,
+}
+
+impl Work {
+    pub(crate) fn new(id: WorkId) -> Self {
+        Work { id }
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Work {
+    fn title(&self, context: &Context) -> String {
+        context
+            .get()
+            .lookup_intern_work(self.id)
+            .title()
+            .to_string()
+    }
+
+    fn author(&self, context: &Context) -> Author {
+        Author::new(context.get().lookup_intern_work(self.id).author())
+    }
+
+    /// When the work was written, if it is known
+    fn time_span(&self, context: &Context) -> Option<TimeSpan> {
+        let db = context.get();
+        db.lookup_intern_work(self.id)
+            .tspan()
+            .cloned()
+            .map(|time_span| TimeSpan { time_span })
+    }
+
+    fn genre(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        db.lookup_intern_work(self.id).genre().map(String::from)
+    }
+
+    /// The sources the work is made of, in the order of their paths
+    fn sources(&self, context: &Context) -> Vec<Source> {
+        let db = context.get();
+        let mut sources: Vec<_> = db.work_sources(self.id).iter().cloned().collect();
+        sources.sort_by_key(|id| db.sources().get_by_right(id).cloned());
+        sources.into_iter().map(Source::new).collect()
+    }
 }
 
 pub struct Source {
@@ -73,7 +175,7 @@ pub struct Source {
 }
 
 impl Source {
-    fn new(p: SourceId) -> Self {
+    pub(crate) fn new(p: SourceId) -> Self {
         Source { source_id: p }
     }
 }
@@ -98,12 +200,83 @@ impl Source {
 
         Author::new(author_id)
     }
+
+    /// The work the source is part of
+    fn work(&self, context: &Context) -> Work {
+        Work::new(context.get().associated_work(self.source_id))
+    }
+
+    /// The title of the work in the manifest, or else the one given in the header of a TEI document
+    fn title(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        if let Some(work) = db.manifest_entry(self.source_id) {
+            return Some(work.title().to_string());
+        }
+        if db.source_format(self.source_id) != SourceFormat::Tei {
+            return None;
+        }
+        db.source_tei(self.source_id).title().map(String::from)
+    }
+
+    /// The genre of the work, as given in the manifest
+    fn genre(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        db.manifest_entry(self.source_id)
+            .and_then(|work| work.genre())
+            .map(String::from)
+    }
+
+    /// The language register of the work (i.e. poetry, prose), as given in the manifest
+    fn register(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        db.manifest_entry(self.source_id)
+            .and_then(|work| work.register())
+            .map(String::from)
+    }
+
+    /// The author given in the header, for a TEI document
+    fn attributed_to(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        if db.source_format(self.source_id) != SourceFormat::Tei {
+            return None;
+        }
+        db.source_tei(self.source_id).author().map(String::from)
+    }
+
+    /// The lemmas that were chosen by hand for some occurrences in the source
+    fn chosen_lemmas(&self, context: &Context) -> Vec<ChosenLemma> {
+        let db = context.get();
+        db.source_overrides(self.source_id)
+            .iter()
+            .map(|(&(line_no, index), lemma)| ChosenLemma {
+                line_no: line_no as i32,
+                index: index as i32,
+                lemma: db.lookup_word_str(lemma.0).to_string(),
+            })
+            .collect()
+    }
+}
+
+/// The lemma chosen by hand for an occurrence of an ambiguous form
+#[derive(juniper::GraphQLObject)]
+pub struct ChosenLemma {
+    /// The (zero based) line of the source in which the word appears
+    line_no: i32,
+    /// The position of the word among the ones in its line
+    index: i32,
+    lemma: String,
 }
 
 pub struct Occurrence {
     id: FormDataId,
 }
 
+impl Occurrence {
+    pub(crate) fn new(id: FormDataId) -> Self {
+        Occurrence { id }
+    }
+}
+
 #[juniper::object(Context = Context)]
 impl Occurrence {
     fn line(&self, context: &Context) -> String {
@@ -118,16 +291,260 @@ impl Occurrence {
         Source::new(fd.source())
     }
 
+    /// The canonical reference of the line (i.e. `Aen. 1.33`), if the source has any
+    fn citation(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        db.lookup_intern_form_data(self.id).citation(&**db)
+    }
+
+    /// The (zero based) line of the source in which the word appears
+    fn line_no(&self, context: &Context) -> i32 {
+        let db = context.get();
+        db.lookup_intern_form_data(self.id).line_no() as i32
+    }
+
+    /// The position of the word among the ones in its line
+    fn index(&self, context: &Context) -> i32 {
+        let db = context.get();
+        db.lookup_intern_form_data(self.id).index() as i32
+    }
+
+    /// The offset, in characters, of the word in its line
+    fn offset(&self, context: &Context) -> i32 {
+        let db = context.get();
+        db.lookup_intern_form_data(self.id).offset() as i32
+    }
+
+    /// The enclitic that was split from the word, if any
+    fn enclitic(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        let fd = db.lookup_intern_form_data(self.id);
+        fd.enclitic().map(|e| e.as_str().to_string())
+    }
+
+    /// The lemma that was chosen by hand for this occurrence, if any
+    fn chosen_lemma(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        let fd = db.lookup_intern_form_data(self.id);
+        fd.chosen_lemma(&**db)
+            .map(|lemma| db.lookup_word_str(lemma.0).to_string())
+    }
+
+    /// Note, an occurrence whose lemma was chosen by hand is not ambiguous
     fn ambiguos(&self, context: &Context) -> bool {
         let db = context.get();
         let fd = db.lookup_intern_form_data(self.id);
-        // TODO: Global empty vec
-        let form = Form::new(fd.form(), Arc::new(Vec::new()));
+        if fd.chosen_lemma(&**db).is_some() {
+            return false;
+        }
+        let form = Form::new(fd.form(), LitSubset::from_sources(&[]));
         drop(db);
         form.is_ambig(context)
     }
 }
 
+#[derive(juniper::GraphQLEnum, Clone, Copy)]
+pub enum WindowUnit {
+    Tokens,
+    Lines,
+}
+
+/// How a search pattern is read
+#[derive(juniper::GraphQLEnum, Clone, Copy)]
+pub enum SearchMode {
+    /// `*` stands for any letters, `?` for a single one (i.e. `amaui*`, `*bilis`)
+    Wildcard,
+    /// A regex over the normalized words
+    Regex,
+    /// The words within some edits of the pattern
+    Fuzzy,
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Copy)]
+pub enum ContextSort {
+    Textual,
+    Left,
+    Right,
+}
+
+impl From<ContextSort> for concordance::ContextSort {
+    fn from(sort: ContextSort) -> Self {
+        match sort {
+            ContextSort::Textual => concordance::ContextSort::Textual,
+            ContextSort::Left => concordance::ContextSort::Left,
+            ContextSort::Right => concordance::ContextSort::Right,
+        }
+    }
+}
+
+pub struct ConcordanceEntry {
+    line: concordance::ConcordanceLine,
+}
+
+#[juniper::object(Context = Context)]
+impl ConcordanceEntry {
+    fn left(&self) -> String {
+        self.line.left()
+    }
+
+    fn keyword(&self) -> &str {
+        self.line.keyword()
+    }
+
+    fn right(&self) -> String {
+        self.line.right()
+    }
+
+    fn occurrence(&self) -> Occurrence {
+        Occurrence {
+            id: self.line.occurrence(),
+        }
+    }
+}
+
+fn concordance_entries(
+    context: &Context,
+    occurrences: impl IntoIterator<Item = FormDataId>,
+    window: i32,
+    unit: WindowUnit,
+    sort: ContextSort,
+) -> FieldResult<Vec<ConcordanceEntry>> {
+    if window < 0 {
+        return Err(FieldError::new(
+            "Invalid window size",
+            graphql_value!({ "input_error" : "window"}),
+        ));
+    }
+
+    let window = match unit {
+        WindowUnit::Tokens => Window::Tokens(window as usize),
+        WindowUnit::Lines => Window::Lines(window as usize),
+    };
+
+    let db = context.get();
+    Ok(
+        concordance::concordance(&**db, occurrences, window, sort.into())
+            .into_iter()
+            .map(|line| ConcordanceEntry { line })
+            .collect(),
+    )
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Copy)]
+pub enum PhraseMode {
+    Form,
+    Lemma,
+}
+
+/// An occurrence of a phrase, as a sequence of consecutive words
+pub struct PhraseMatch {
+    occurrences: Vec<FormDataId>,
+}
+
+impl PhraseMatch {
+    pub(crate) fn new(occurrences: Vec<FormDataId>) -> Self {
+        PhraseMatch { occurrences }
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl PhraseMatch {
+    fn source(&self, context: &Context) -> Option<Source> {
+        let db = context.get();
+        self.occurrences
+            .first()
+            .map(|&fd| Source::new(db.lookup_intern_form_data(fd).source()))
+    }
+
+    fn occurrences(&self) -> Vec<Occurrence> {
+        self.occurrences
+            .iter()
+            .map(|&id| Occurrence { id })
+            .collect()
+    }
+}
+
+/// A line (or a window of words) that takes part in a parallel
+pub struct ParallelLine {
+    line: LineRef,
+}
+
+#[juniper::object(Context = Context)]
+impl ParallelLine {
+    fn source(&self) -> Source {
+        Source::new(self.line.source())
+    }
+
+    /// The text of the line, or of the lines a window of words runs over
+    fn line(&self, context: &Context) -> String {
+        let db = context.get();
+        (self.line.line_no()..=self.line.last_line_no())
+            .map(|line_no| {
+                db.get_line(self.line.source(), line_no)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The (zero based) line of the source, the first one for a window of words
+    fn line_no(&self) -> i32 {
+        self.line.line_no() as i32
+    }
+
+    /// The (zero based) last line of a window of words
+    fn last_line_no(&self) -> i32 {
+        self.line.last_line_no() as i32
+    }
+
+    /// The canonical reference of the line (i.e. `Aen. 1.33`), if the source has any
+    fn citation(&self, context: &Context) -> Option<String> {
+        let db = context.get();
+        self.line.citation(&**db)
+    }
+}
+
+/// A pair of lines sharing some lemmas
+pub struct Parallel {
+    parallel: parallels::Parallel,
+    lit: LitSubset,
+}
+
+impl Parallel {
+    pub(crate) fn new(parallel: parallels::Parallel, lit: LitSubset) -> Self {
+        Parallel { parallel, lit }
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Parallel {
+    /// How significant the parallel is, the higher the rarer the shared lemmas
+    fn score(&self) -> f64 {
+        self.parallel.score()
+    }
+
+    fn shared_lemmas(&self) -> Vec<Lemma> {
+        self.parallel
+            .shared()
+            .iter()
+            .map(|&l| Lemma::new(l, self.lit.clone()))
+            .collect()
+    }
+
+    fn source(&self) -> ParallelLine {
+        ParallelLine {
+            line: self.parallel.source(),
+        }
+    }
+
+    fn target(&self) -> ParallelLine {
+        ParallelLine {
+            line: self.parallel.target(),
+        }
+    }
+}
+
 #[derive(juniper::GraphQLEnum)]
 pub enum WordType {
     Form,
@@ -135,21 +552,57 @@ pub enum WordType {
     NotFound,
 }
 
+/// A word that was never seen, with the known words it might be a misspelling of
+#[derive(juniper::GraphQLObject)]
+#[graphql(Context = Context, Scalar = juniper::DefaultScalarValue)]
+pub struct NotFound {
+    /// The word, as it was normalized
+    word: String,
+    /// The closest words, nearest first
+    suggestions: Vec<String>,
+}
+
+impl NotFound {
+    pub(crate) fn new(word: String, suggestions: Vec<String>) -> Self {
+        NotFound { word, suggestions }
+    }
+}
+
+/// A form, or the reason it was not found
+pub enum FormLookup {
+    Found(Form),
+    NotFound(NotFound),
+}
+
+juniper::graphql_union!(FormLookup: Context |&self| {
+    instance_resolvers: |_| {
+        &Form => match *self { FormLookup::Found(ref form) => Some(form), _ => None },
+        &NotFound => match *self { FormLookup::NotFound(ref not_found) => Some(not_found), _ => None },
+    }
+});
+
+/// A lemma, or the reason it was not found
+pub enum LemmaLookup {
+    Found(Lemma),
+    NotFound(NotFound),
+}
+
+juniper::graphql_union!(LemmaLookup: Context |&self| {
+    instance_resolvers: |_| {
+        &Lemma => match *self { LemmaLookup::Found(ref lemma) => Some(lemma), _ => None },
+        &NotFound => match *self { LemmaLookup::NotFound(ref not_found) => Some(not_found), _ => None },
+    }
+});
+
 pub struct Form {
     form: types::Form,
-    authors: Arc<Vec<AuthorId>>,
+    lit: LitSubset,
 }
 
 impl Form {
-    pub(crate) fn new(form: types::Form, authors: Arc<Vec<AuthorId>>) -> Self {
-        Form { form, authors }
-    }
-
-    pub(crate) fn from_iter(
-        form: types::Form,
-        authors: impl IntoIterator<Item = AuthorId>,
-    ) -> Self {
-        Form::new(form, Arc::new(authors.into_iter().collect()))
+    /// The form, counted in a subset of the literature
+    pub(crate) fn new(form: types::Form, lit: LitSubset) -> Self {
+        Form { form, lit }
     }
 
     pub(crate) fn is_ambig(&self, context: &Context) -> bool {
@@ -165,12 +618,7 @@ impl Form {
 impl Form {
     fn form(&self, context: &Context) -> String {
         let db = context.get();
-
-        let id = self.form.0;
-
-        let word = db.lookup_word(id);
-
-        word.inner().to_string()
+        db.lookup_word_str(self.form.0).to_string()
     }
 
     fn lemmas(&self, context: &Context) -> Vec<Lemma> {
@@ -183,61 +631,219 @@ impl Form {
             .map(|v| {
                 v.into_iter()
                     .map(types::Lemma)
-                    .map(|l| Lemma::new(l, self.authors.clone()))
+                    .map(|l| Lemma::new(l, self.lit.clone()))
                     .collect()
             })
             .unwrap_or_else(Vec::new)
     }
 
+    /// The ways the form can be read as each of its lemmas
+    fn analyses(&self, context: &Context) -> Vec<Analysis> {
+        let db = context.get();
+        let id = self.form.0;
+        let lemm = db.lemmatizer();
+
+        let mut lemmas: Vec<_> = lemm
+            .get_possible_lemmas(id)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        lemmas.sort();
+
+        lemmas
+            .into_iter()
+            .flat_map(|lemma| {
+                lemm.get_analyses(id, lemma)
+                    .into_iter()
+                    .flatten()
+                    .map(move |analysis| Analysis::new(types::Lemma(lemma), analysis.clone()))
+            })
+            .collect()
+    }
+
     fn ambiguos(&self, context: &Context) -> bool {
         self.is_ambig(context)
     }
 
     fn count(&self, context: &Context) -> i32 {
         let db = context.get();
-        db.count_form_occurrences_subset(
-            self.form,
-            LitSubset::from_authors(self.authors.iter(), &db),
-        ) as i32
+        db.count_form_occurrences_subset(self.form, self.lit.clone()) as i32
     }
 
-    fn occurrences(&self, context: &Context) -> Vec<Occurrence> {
+    #[graphql(arguments(variants(
+        description = "Whether the occurrences of the other spellings of the form are included",
+        default = false,
+    )))]
+    fn occurrences(&self, context: &Context, variants: bool) -> Vec<Occurrence> {
         let db = context.get();
-        db.form_occurrences_subset(self.form, LitSubset::from_authors(self.authors.iter(), &db))
+        let subset = self.lit.clone();
+        let forms = if variants {
+            db.form_variants(self.form)
+        } else {
+            Arc::new(std::iter::once(self.form).collect())
+        };
+
+        forms
             .iter()
-            .map(|s| Occurrence { id: *s })
+            .flat_map(|&form| {
+                let occurrences = db.form_occurrences_subset(form, subset.clone());
+                occurrences.iter().cloned().collect::<Vec<_>>()
+            })
+            .map(|id| Occurrence { id })
             .collect()
     }
+
+    #[graphql(
+        arguments(
+            window(
+                description = "The amount of context on each side",
+                default = 5,
+            ),
+            unit(
+                description = "Whether the window is in words or in lines",
+                default = WindowUnit::Tokens,
+            ),
+            sort(
+                description = "How to order the occurrences",
+                default = ContextSort::Textual,
+            )
+        )
+    )]
+    /// The occurrences together with their context (KWIC)
+    fn concordance(
+        &self,
+        context: &Context,
+        window: i32,
+        unit: WindowUnit,
+        sort: ContextSort,
+    ) -> FieldResult<Vec<ConcordanceEntry>> {
+        let occurrences = {
+            let db = context.get();
+            db.form_occurrences_subset(self.form, self.lit.clone())
+        };
+        concordance_entries(context, occurrences.iter().cloned(), window, unit, sort)
+    }
 }
 
-pub struct Lemma {
-    lemma: types::Lemma,
-    authors: Arc<Vec<AuthorId>>,
+/// How many occurrences of a variant an author has
+#[derive(juniper::GraphQLObject)]
+pub struct AuthorCount {
+    author: String,
+    count: i32,
 }
 
-impl Lemma {
-    pub(crate) fn new(lemma: types::Lemma, authors: Arc<Vec<AuthorId>>) -> Self {
-        Lemma { lemma, authors }
+/// How many occurrences of a variant fall in a century
+/// Note, the centuries are numbered as in the authors file, i.e. `-1` spans the first century BC
+#[derive(juniper::GraphQLObject)]
+pub struct CenturyCount {
+    century: i32,
+    count: i32,
+}
+
+/// A spelling of a family of variants, with its distribution in the literature
+pub struct Variant {
+    counts: VariantCounts,
+    lit: LitSubset,
+}
+
+#[juniper::object(Context = Context)]
+impl Variant {
+    fn form(&self) -> Form {
+        Form::new(self.counts.form(), self.lit.clone())
+    }
+
+    fn count(&self) -> i32 {
+        self.counts.count() as i32
+    }
+
+    /// The authors that use the variant, by name
+    fn authors(&self, context: &Context) -> Vec<AuthorCount> {
+        let db = context.get();
+        let authors = db.authors();
+        let mut res: Vec<_> = self
+            .counts
+            .authors()
+            .iter()
+            .filter_map(|(id, &count)| {
+                let author = authors.get_by_right(id)?;
+                Some(AuthorCount {
+                    author: author.name().to_string(),
+                    count: count as i32,
+                })
+            })
+            .collect();
+        res.sort_by(|a, b| a.author.cmp(&b.author));
+        res
+    }
+
+    fn centuries(&self) -> Vec<CenturyCount> {
+        self.counts
+            .centuries()
+            .iter()
+            .map(|(&century, &count)| CenturyCount {
+                century,
+                count: count as i32,
+            })
+            .collect()
     }
+}
 
-    pub(crate) fn from_iter(
-        lemma: types::Lemma,
-        authors: impl IntoIterator<Item = AuthorId>,
+/// The spellings of a form (i.e. `adfero` and `affero`), grouped by a common key
+pub struct VariantFamily {
+    key: String,
+    variants: Vec<Variant>,
+}
+
+impl VariantFamily {
+    pub(crate) fn new(
+        context: &Context,
+        key: String,
+        forms: impl IntoIterator<Item = types::Form>,
+        lit: LitSubset,
     ) -> Self {
-        Lemma::new(lemma, Arc::new(authors.into_iter().collect()))
+        let db = context.get();
+        let variants = forms
+            .into_iter()
+            .map(|form| Variant {
+                counts: VariantCounts::new(&**db, form, lit.clone()),
+                lit: lit.clone(),
+            })
+            .collect();
+
+        VariantFamily { key, variants }
     }
 }
 
 #[juniper::object(Context = Context)]
-impl Lemma {
-    fn lemma(&self, context: &Context) -> String {
-        let db = context.get();
+impl VariantFamily {
+    /// The spelling the variants are brought back to
+    fn key(&self) -> &str {
+        &self.key
+    }
 
-        let id = self.lemma.0;
+    fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+}
 
-        let word = db.lookup_word(id);
+pub struct Lemma {
+    lemma: types::Lemma,
+    lit: LitSubset,
+}
 
-        word.inner().to_string()
+impl Lemma {
+    /// The lemma, counted in a subset of the literature
+    pub(crate) fn new(lemma: types::Lemma, lit: LitSubset) -> Self {
+        Lemma { lemma, lit }
+    }
+}
+
+#[juniper::object(Context = Context)]
+impl Lemma {
+    fn lemma(&self, context: &Context) -> String {
+        let db = context.get();
+        db.lookup_word_str(self.lemma.0).to_string()
     }
 
     fn forms(&self, context: &Context) -> Vec<Form> {
@@ -250,7 +856,7 @@ impl Lemma {
             .map(|v| {
                 v.into_iter()
                     .map(types::Form)
-                    .map(|f| Form::new(f, self.authors.clone()))
+                    .map(|f| Form::new(f, self.lit.clone()))
                     .collect()
             })
             .unwrap_or_else(Vec::new)
@@ -258,20 +864,68 @@ impl Lemma {
 
     fn count(&self, context: &Context) -> i32 {
         let db = context.get();
-        db.count_lemma_occurrences_subset(
-            self.lemma,
-            LitSubset::from_authors(self.authors.iter(), &db),
-        ) as i32
+        db.count_lemma_occurrences_subset(self.lemma, self.lit.clone(), CountMode::Every)
+            .value() as i32
+    }
+
+    #[graphql(arguments(window(
+        description = "How many words around an ambiguous occurrence are considered",
+        default = 0,
+    )))]
+    /// The count with the ambiguous occurrences split among their lemmas,
+    /// by how often each lemma appears unambiguously in the whole literature
+    fn expected_count(&self, context: &Context, window: i32) -> FieldResult<f64> {
+        if window < 0 {
+            return Err(FieldError::new(
+                "Invalid window size",
+                graphql_value!({ "input_error" : "window"}),
+            ));
+        }
+
+        let db = context.get();
+        let corpus = LitSubset::from_authors(db.authors().right_values(), &db);
+        let mode = CountMode::Weighted(Weighting::new(corpus).with_context(window as usize));
+        Ok(db
+            .count_lemma_occurrences_subset(self.lemma, self.lit.clone(), mode)
+            .value())
     }
 
     fn occurrences(&self, context: &Context) -> Vec<Occurrence> {
         let db = context.get();
-        db.lemma_occurrences_subset(
-            self.lemma,
-            LitSubset::from_authors(self.authors.iter(), &db),
+        db.lemma_occurrences_subset(self.lemma, self.lit.clone())
+            .iter()
+            .map(|s| Occurrence { id: *s })
+            .collect()
+    }
+
+    #[graphql(
+        arguments(
+            window(
+                description = "The amount of context on each side",
+                default = 5,
+            ),
+            unit(
+                description = "Whether the window is in words or in lines",
+                default = WindowUnit::Tokens,
+            ),
+            sort(
+                description = "How to order the occurrences",
+                default = ContextSort::Textual,
+            )
         )
-        .iter()
-        .map(|s| Occurrence { id: *s })
-        .collect()
+    )]
+    /// The occurrences together with their context (KWIC)
+    fn concordance(
+        &self,
+        context: &Context,
+        window: i32,
+        unit: WindowUnit,
+        sort: ContextSort,
+    ) -> FieldResult<Vec<ConcordanceEntry>> {
+        let occurrences = {
+            let db = context.get();
+            db.lemma_occurrences_subset(self.lemma, self.lit.clone())
+        };
+        concordance_entries(context, occurrences.iter().cloned(), window, unit, sort)
     }
 }
@@ -1,12 +1,18 @@
+use super::morphology::Analysis;
 use super::NaiveLemmatizer;
 use crate::word_db::{WordDatabase, WordId};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// The analyses of the (form, lemma) pairs, for the pairs that have some
+pub type Analyses = HashMap<(WordId, WordId), BTreeSet<Analysis>>;
 
 #[derive(Debug, Default, Clone)]
 pub struct CompressedLemmatizer {
     form_to_lemma: HashMap<WordId, HashSet<WordId>>,
     lemma_to_form: HashMap<WordId, HashSet<WordId>>,
+    analyses: Analyses,
 }
 
 impl CompressedLemmatizer {
@@ -19,14 +25,58 @@ impl CompressedLemmatizer {
             form_to_lemma.insert(form, lemmas);
         }
 
+        let analyses = lemm
+            .analyses
+            .into_iter()
+            .map(|((form, lemma), analyses)| {
+                ((db.intern_word(form), db.intern_word(lemma)), analyses)
+            })
+            .collect();
+
+        Self::from_mapping_with_analyses(form_to_lemma, analyses)
+    }
+
+    /// Instantiate from the mapping of the forms to their lemmas
+    pub fn from_mapping(form_to_lemma: HashMap<WordId, HashSet<WordId>>) -> Self {
+        Self::from_mapping_with_analyses(form_to_lemma, Analyses::new())
+    }
+
+    /// Instantiate from the mapping of the forms to their lemmas and the analyses of the pairs
+    /// Note, the analyses of pairs that are not in the mapping are dropped
+    pub fn from_mapping_with_analyses(
+        form_to_lemma: HashMap<WordId, HashSet<WordId>>,
+        mut analyses: Analyses,
+    ) -> Self {
         let lemma_to_form = super::invert_mapping(&form_to_lemma);
+        analyses.retain(|(form, lemma), _| {
+            form_to_lemma
+                .get(form)
+                .into_iter()
+                .any(|lemmas| lemmas.contains(lemma))
+        });
 
         CompressedLemmatizer {
             form_to_lemma,
             lemma_to_form,
+            analyses,
         }
     }
 
+    /// The mapping of the forms to their lemmas
+    pub fn mapping(&self) -> &HashMap<WordId, HashSet<WordId>> {
+        &self.form_to_lemma
+    }
+
+    /// The analyses of all the (form, lemma) pairs
+    pub fn analyses(&self) -> &Analyses {
+        &self.analyses
+    }
+
+    /// The ways a form can be read as a lemma, if the lemmatizer knows any
+    pub fn get_analyses(&self, form: WordId, lemma: WordId) -> Option<&BTreeSet<Analysis>> {
+        self.analyses.get(&(form, lemma))
+    }
+
     pub fn num_lemmas(&self) -> usize {
         self.lemma_to_form.len()
     }
@@ -65,3 +115,51 @@ impl CompressedLemmatizer {
         }
     }
 }
+
+/// The lemmatizer some sources are read with: a shared one, with their own readings on top (if any)
+#[derive(Debug, Clone)]
+pub struct LemmatizerView {
+    base: Arc<CompressedLemmatizer>,
+    extra: Option<Arc<CompressedLemmatizer>>,
+}
+
+impl LemmatizerView {
+    pub fn new(base: Arc<CompressedLemmatizer>, extra: Option<Arc<CompressedLemmatizer>>) -> Self {
+        LemmatizerView { base, extra }
+    }
+
+    fn lemmatizers(&self) -> impl Iterator<Item = &CompressedLemmatizer> {
+        std::iter::once(&*self.base).chain(self.extra.as_deref())
+    }
+
+    pub fn get_possible_lemmas(&self, form: WordId) -> HashSet<WordId> {
+        self.lemmatizers()
+            .flat_map(|lemm| lemm.get_possible_lemmas(form))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// The ways a form can be read as a lemma, in either of the lemmatizers
+    pub fn get_analyses(&self, form: WordId, lemma: WordId) -> BTreeSet<Analysis> {
+        self.lemmatizers()
+            .flat_map(|lemm| lemm.get_analyses(form, lemma))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+// Note, the lemmatizers are never changed in place, so they are the same if they are shared
+impl PartialEq for LemmatizerView {
+    fn eq(&self, other: &Self) -> bool {
+        let same_extra = match (&self.extra, &other.extra) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        Arc::ptr_eq(&self.base, &other.base) && same_extra
+    }
+}
+
+impl Eq for LemmatizerView {}
@@ -3,19 +3,25 @@
 //! For example, a lemmatizer could feasibly resolve the word `rosae` to the lemma `rosa`
 
 pub mod compressed;
+pub mod morphology;
 pub mod parsers;
 
-use crate::latin_utilities::{NormalizedLatinString, StandardLatinConverter};
-use std::collections::{HashMap, HashSet};
+use crate::latin_utilities::{NormalizedLatinString, Normalizer, StandardLatinConverter};
+use morphology::Analysis;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 
 type Mapping = HashMap<NormalizedLatinString, HashSet<NormalizedLatinString>>;
 
+/// The analyses of the (form, lemma) pairs, for the pairs that have some
+type Analyses = HashMap<(NormalizedLatinString, NormalizedLatinString), BTreeSet<Analysis>>;
+
 /// A lemmatizer that uses a simple hashmap lookup to resolve lemmas
 #[derive(Debug, Default, Clone)]
 pub struct NaiveLemmatizer {
     form_to_lemma: Mapping,
     lemma_to_form: Mapping,
+    analyses: Analyses,
     converter: StandardLatinConverter,
 }
 
@@ -43,14 +49,52 @@ where
 
 impl NaiveLemmatizer {
     pub fn new(form_to_lemma: Mapping) -> Self {
+        Self::new_with_analyses(form_to_lemma, Analyses::new())
+    }
+
+    /// Instantiate, together with the morphological analyses of the forms
+    pub fn new_with_analyses(form_to_lemma: Mapping, analyses: Analyses) -> Self {
         // TODO, deduplicate similar mappings
         NaiveLemmatizer {
             lemma_to_form: invert_mapping(&form_to_lemma),
             form_to_lemma,
+            analyses,
             converter: StandardLatinConverter::default(),
         }
     }
 
+    /// Convert the keys of the lookups with a converter other than the standard one
+    /// (i.e. the one the lemmatizer was read with)
+    pub fn with_converter(self, converter: StandardLatinConverter) -> Self {
+        NaiveLemmatizer { converter, ..self }
+    }
+
+    /// The same readings, with the forms normalized further (i.e. with the rules of an author)
+    /// Note, the lemmas are kept as they are, so that they are shared by all the spellings
+    pub fn normalize_forms(&self, normalizer: &impl Normalizer) -> NaiveLemmatizer {
+        let normalize = |form: &NormalizedLatinString| {
+            NormalizedLatinString::instantiate(normalizer.normalize(form.inner()))
+        };
+
+        let mut mapping = Mapping::new();
+        for (form, lemmas) in &self.form_to_lemma {
+            mapping
+                .entry(normalize(form))
+                .or_default()
+                .extend(lemmas.iter().cloned());
+        }
+
+        let mut analyses = Analyses::new();
+        for ((form, lemma), readings) in &self.analyses {
+            analyses
+                .entry((normalize(form), lemma.clone()))
+                .or_default()
+                .extend(readings.iter().cloned());
+        }
+
+        NaiveLemmatizer::new_with_analyses(mapping, analyses).with_converter(self.converter.clone())
+    }
+
     pub fn num_lemmas(&self) -> usize {
         self.lemma_to_form.len()
     }
@@ -81,6 +125,15 @@ impl NaiveLemmatizer {
         self.lemma_to_form.get(lemma)
     }
 
+    /// The ways a form can be read as a lemma, if the lemmatizer knows any
+    pub fn get_analyses(
+        &self,
+        form: &NormalizedLatinString,
+        lemma: &NormalizedLatinString,
+    ) -> Option<&BTreeSet<Analysis>> {
+        self.analyses.get(&(form.clone(), lemma.clone()))
+    }
+
     pub fn convert_and_get_possible_lemmas(
         &self,
         key: &str,
@@ -175,4 +228,18 @@ mod tests {
             assert_eq!(*query, results_forms[i]);
         }
     }
+
+    #[test]
+    fn test_normalize_forms() {
+        use crate::latin_utilities::Profile;
+
+        let mut form_to_lemma = Mapping::new();
+        form_to_lemma.insert("poenae".into(), hashset_from_vec(vec!["poena".into()]));
+        let lemmatizer =
+            NaiveLemmatizer::new(form_to_lemma).normalize_forms(&Profile::epigraphic());
+
+        assert!(lemmatizer.has_form(&"pene".into()));
+        assert!(!lemmatizer.has_form(&"poenae".into()));
+        assert!(lemmatizer.has_lemma(&"poena".into()));
+    }
 }
@@ -0,0 +1,480 @@
+//! Morphological analyses of a form, as given by the lemmatizer
+//! An analysis is made of a part of speech and of a set of features (case, number, tense...),
+//! which are recognized from the usual abbreviations (`masc dat sg`, `perf subj act 3 pl`)
+//! or from positional tags in the manner of MULTEXT-East (`Vmsl1s-a`)
+//! The tag the analysis was read from is always kept, so that nothing is lost when some of it
+//! is not understood
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The part of speech of a form
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PartOfSpeech {
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+    Pronoun,
+    Preposition,
+    Conjunction,
+    Interjection,
+    Numeral,
+}
+
+impl PartOfSpeech {
+    /// Recognize a part of speech from its abbreviation
+    /// ```
+    /// use latin_db::latin_lemmatizer::morphology::PartOfSpeech;
+    /// assert_eq!(PartOfSpeech::from_abbreviation("adv"), Some(PartOfSpeech::Adverb));
+    /// ```
+    pub fn from_abbreviation(abbreviation: &str) -> Option<Self> {
+        Some(match abbreviation.to_lowercase().trim_end_matches('.') {
+            "noun" | "subst" => PartOfSpeech::Noun,
+            "verb" => PartOfSpeech::Verb,
+            "adj" => PartOfSpeech::Adjective,
+            "adv" => PartOfSpeech::Adverb,
+            "pron" => PartOfSpeech::Pronoun,
+            "prep" => PartOfSpeech::Preposition,
+            "conj" => PartOfSpeech::Conjunction,
+            "interj" => PartOfSpeech::Interjection,
+            "num" => PartOfSpeech::Numeral,
+            _ => return None,
+        })
+    }
+}
+
+/// A morphological feature of a form
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Feature {
+    // Case
+    Nominative,
+    Genitive,
+    Dative,
+    Accusative,
+    Ablative,
+    Vocative,
+    Locative,
+    // Number
+    Singular,
+    Plural,
+    // Gender
+    Masculine,
+    Feminine,
+    Neuter,
+    // Person
+    First,
+    Second,
+    Third,
+    // Tense
+    Present,
+    Imperfect,
+    Future,
+    Perfect,
+    Pluperfect,
+    FuturePerfect,
+    // Mood (and the nominal forms of the verb)
+    Indicative,
+    Subjunctive,
+    Imperative,
+    Infinitive,
+    Participle,
+    Gerund,
+    Gerundive,
+    Supine,
+    // Voice
+    Active,
+    Passive,
+    // Degree
+    Comparative,
+    Superlative,
+}
+
+impl Feature {
+    /// Recognize a feature from its abbreviation
+    /// ```
+    /// use latin_db::latin_lemmatizer::morphology::Feature;
+    /// assert_eq!(Feature::from_abbreviation("abl"), Some(Feature::Ablative));
+    /// assert_eq!(Feature::from_abbreviation("ablative"), None);
+    /// ```
+    pub fn from_abbreviation(abbreviation: &str) -> Option<Self> {
+        Some(match abbreviation.to_lowercase().trim_end_matches('.') {
+            "nom" => Feature::Nominative,
+            "gen" => Feature::Genitive,
+            "dat" => Feature::Dative,
+            "acc" => Feature::Accusative,
+            "abl" => Feature::Ablative,
+            "voc" => Feature::Vocative,
+            "loc" => Feature::Locative,
+            "sg" | "sing" => Feature::Singular,
+            "pl" | "plur" => Feature::Plural,
+            "masc" | "m" => Feature::Masculine,
+            "fem" | "f" => Feature::Feminine,
+            "neut" | "neutr" => Feature::Neuter,
+            "1" | "1st" => Feature::First,
+            "2" | "2nd" => Feature::Second,
+            "3" | "3rd" => Feature::Third,
+            "pres" => Feature::Present,
+            "impf" | "imperf" => Feature::Imperfect,
+            "fut" => Feature::Future,
+            "perf" | "pf" => Feature::Perfect,
+            "plupf" | "plup" | "pqp" => Feature::Pluperfect,
+            "futperf" | "fut_perf" | "futp" => Feature::FuturePerfect,
+            "ind" => Feature::Indicative,
+            "subj" | "cong" => Feature::Subjunctive,
+            "imper" | "imp" => Feature::Imperative,
+            "inf" => Feature::Infinitive,
+            "part" | "ptc" => Feature::Participle,
+            "ger" => Feature::Gerund,
+            "gerv" | "gerundive" => Feature::Gerundive,
+            "sup" => Feature::Supine,
+            "act" => Feature::Active,
+            "pass" => Feature::Passive,
+            "comp" => Feature::Comparative,
+            "superl" => Feature::Superlative,
+            _ => return None,
+        })
+    }
+}
+
+/// One of the ways a form can be read as a lemma
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Analysis {
+    pos: Option<PartOfSpeech>,
+    features: BTreeSet<Feature>,
+    tag: String,
+}
+
+impl Analysis {
+    pub fn new(
+        pos: Option<PartOfSpeech>,
+        features: impl IntoIterator<Item = Feature>,
+        tag: impl Into<String>,
+    ) -> Self {
+        Analysis {
+            pos,
+            features: features.into_iter().collect(),
+            tag: tag.into(),
+        }
+    }
+
+    /// Read an analysis from a tag made of abbreviations (i.e. `masc dat sg`)
+    /// ```
+    /// use latin_db::latin_lemmatizer::morphology::{Analysis, Feature};
+    /// let analysis = Analysis::from_tag("masc dat sg");
+    /// assert!(analysis.has(Feature::Dative));
+    /// assert_eq!(analysis.tag(), "masc dat sg");
+    /// ```
+    pub fn from_tag(tag: &str) -> Self {
+        let tag = tag.trim();
+        let mut pos = None;
+        let mut features = BTreeSet::new();
+        for abbreviation in tag.split(|c: char| c.is_whitespace() || c == '|' || c == ',') {
+            if let Some(feature) = Feature::from_abbreviation(abbreviation) {
+                features.insert(feature);
+            } else if pos.is_none() {
+                pos = PartOfSpeech::from_abbreviation(abbreviation);
+            }
+        }
+
+        Analysis {
+            pos,
+            features,
+            tag: tag.to_string(),
+        }
+    }
+
+    /// Read an analysis from a positional tag, whose first letter is the part of speech and
+    /// whose other letters are the values of its attributes, in order (`-` if there is none):
+    /// - noun `N`: type, gender, number, case
+    /// - verb `V`: type, mood, tense, person, number, gender, voice, case
+    /// - adjective `A`: type, degree, gender, number, case
+    /// - pronoun `P`: type, person, gender, number, case
+    /// - adverb `R`: type, degree
+    /// - numeral `M`: type, gender, number, case
+    /// - adposition `S`, conjunction `C` and interjection `I`: type
+    ///
+    /// The genders are `m`, `f` and `n`, the numbers `s` and `p`, the cases `n`, `g`, `d`, `a`,
+    /// `b` (ablative), `v` and `l`, the persons `1` to `3`, the voices `a` and `p`, the degrees
+    /// `p`, `c` and `s`, the moods `i`, `s` (subjunctive), `m` (imperative), `n` (infinitive),
+    /// `p` (participle), `g` (gerund), `d` (gerundive) and `u` (supine) and the tenses `p`, `i`,
+    /// `f`, `s` (perfect), `l` (pluperfect) and `t` (future perfect)
+    /// The values that are not understood are skipped, and None is returned if the tag is not
+    /// a positional one
+    /// ```
+    /// use latin_db::latin_lemmatizer::morphology::{Analysis, Feature, PartOfSpeech};
+    /// let analysis = Analysis::from_positional("Vmsl1s-a").unwrap();
+    /// assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Verb));
+    /// assert!(analysis.has(Feature::Pluperfect));
+    /// assert!(Analysis::from_positional("masc dat sg").is_none());
+    /// ```
+    pub fn from_positional(tag: &str) -> Option<Self> {
+        use Attribute::*;
+
+        let tag = tag.trim();
+        let mut letters = tag.chars();
+        let (pos, attributes): (_, &[Attribute]) = match letters.next()? {
+            'N' => (PartOfSpeech::Noun, &[Type, Gender, Number, Case]),
+            'V' => (
+                PartOfSpeech::Verb,
+                &[Type, Mood, Tense, Person, Number, Gender, Voice, Case],
+            ),
+            'A' => (
+                PartOfSpeech::Adjective,
+                &[Type, Degree, Gender, Number, Case],
+            ),
+            'P' => (PartOfSpeech::Pronoun, &[Type, Person, Gender, Number, Case]),
+            'R' => (PartOfSpeech::Adverb, &[Type, Degree]),
+            'M' => (PartOfSpeech::Numeral, &[Type, Gender, Number, Case]),
+            'S' => (PartOfSpeech::Preposition, &[Type]),
+            'C' => (PartOfSpeech::Conjunction, &[Type]),
+            'I' => (PartOfSpeech::Interjection, &[Type]),
+            _ => return None,
+        };
+        if !letters
+            .clone()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return None;
+        }
+
+        let features = attributes
+            .iter()
+            .zip(letters)
+            .filter_map(|(&attribute, letter)| attribute.feature(letter))
+            .collect();
+
+        Some(Analysis {
+            pos: Some(pos),
+            features,
+            tag: tag.to_string(),
+        })
+    }
+
+    /// Read an analysis from the universal part of speech and features of a CoNLL-U token
+    /// (i.e. `VERB` and `Aspect=Perf|Mood=Sub|Number=Sing|Person=3|Tense=Past|Voice=Act`)
+    /// ```
+    /// use latin_db::latin_lemmatizer::morphology::{Analysis, Feature, PartOfSpeech};
+    /// let analysis = Analysis::from_universal("NOUN", "Case=Abl|Gender=Fem|Number=Sing");
+    /// assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Noun));
+    /// assert!(analysis.has(Feature::Ablative));
+    /// ```
+    pub fn from_universal(upos: &str, raw_feats: &str) -> Self {
+        let pos = match upos {
+            "NOUN" | "PROPN" => Some(PartOfSpeech::Noun),
+            "VERB" | "AUX" => Some(PartOfSpeech::Verb),
+            "ADJ" => Some(PartOfSpeech::Adjective),
+            "ADV" => Some(PartOfSpeech::Adverb),
+            "PRON" | "DET" => Some(PartOfSpeech::Pronoun),
+            "ADP" => Some(PartOfSpeech::Preposition),
+            "CCONJ" | "SCONJ" => Some(PartOfSpeech::Conjunction),
+            "INTJ" => Some(PartOfSpeech::Interjection),
+            "NUM" => Some(PartOfSpeech::Numeral),
+            _ => None,
+        };
+
+        let feats: Vec<_> = raw_feats
+            .split('|')
+            .filter_map(|feat| {
+                let mut pair = feat.splitn(2, '=');
+                Some((pair.next()?, pair.next()?))
+            })
+            .collect();
+        let value = |name: &str| feats.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+        let perfective = value("Aspect") == Some("Perf");
+
+        let mut features = BTreeSet::new();
+        for &(name, value) in &feats {
+            let feature = match (name, value) {
+                ("Case", "Nom") => Feature::Nominative,
+                ("Case", "Gen") => Feature::Genitive,
+                ("Case", "Dat") => Feature::Dative,
+                ("Case", "Acc") => Feature::Accusative,
+                ("Case", "Abl") => Feature::Ablative,
+                ("Case", "Voc") => Feature::Vocative,
+                ("Case", "Loc") => Feature::Locative,
+                ("Number", "Sing") => Feature::Singular,
+                ("Number", "Plur") => Feature::Plural,
+                ("Gender", "Masc") => Feature::Masculine,
+                ("Gender", "Fem") => Feature::Feminine,
+                ("Gender", "Neut") => Feature::Neuter,
+                ("Person", "1") => Feature::First,
+                ("Person", "2") => Feature::Second,
+                ("Person", "3") => Feature::Third,
+                // Note, the perfect is the past (or the present, for the perfect passive
+                // participle) with the perfective aspect
+                ("Tense", "Pres") if perfective => Feature::Perfect,
+                ("Tense", "Pres") => Feature::Present,
+                ("Tense", "Past") if perfective => Feature::Perfect,
+                ("Tense", "Past") => Feature::Imperfect,
+                ("Tense", "Fut") if perfective => Feature::FuturePerfect,
+                ("Tense", "Fut") => Feature::Future,
+                ("Tense", "Pqp") => Feature::Pluperfect,
+                ("Mood", "Ind") => Feature::Indicative,
+                ("Mood", "Sub") => Feature::Subjunctive,
+                ("Mood", "Imp") => Feature::Imperative,
+                ("VerbForm", "Inf") => Feature::Infinitive,
+                ("VerbForm", "Part") => Feature::Participle,
+                ("VerbForm", "Ger") => Feature::Gerund,
+                ("VerbForm", "Gdv") => Feature::Gerundive,
+                ("VerbForm", "Sup") => Feature::Supine,
+                ("Voice", "Act") => Feature::Active,
+                ("Voice", "Pass") => Feature::Passive,
+                ("Degree", "Cmp") => Feature::Comparative,
+                ("Degree", "Sup") | ("Degree", "Abs") => Feature::Superlative,
+                _ => continue,
+            };
+            features.insert(feature);
+        }
+
+        let tag = if feats.is_empty() {
+            upos.to_string()
+        } else {
+            format!("{} {}", upos, raw_feats)
+        };
+
+        Analysis { pos, features, tag }
+    }
+
+    /// The part of speech, if it is known
+    pub fn part_of_speech(&self) -> Option<PartOfSpeech> {
+        self.pos
+    }
+
+    pub fn features(&self) -> &BTreeSet<Feature> {
+        &self.features
+    }
+
+    /// The tag as it appeared in the lemmatizer
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// Whether the analysis is of the given part of speech (if any) and has all the features
+    pub fn matches(&self, pos: Option<PartOfSpeech>, features: &BTreeSet<Feature>) -> bool {
+        (pos.is_none() || pos == self.pos) && features.is_subset(&self.features)
+    }
+}
+
+// The attributes of the positional tags, in the order they appear in
+#[derive(Debug, Clone, Copy)]
+enum Attribute {
+    Type,
+    Gender,
+    Number,
+    Case,
+    Person,
+    Mood,
+    Tense,
+    Voice,
+    Degree,
+}
+
+impl Attribute {
+    fn feature(self, letter: char) -> Option<Feature> {
+        use Attribute::*;
+
+        Some(match (self, letter) {
+            (Gender, 'm') => Feature::Masculine,
+            (Gender, 'f') => Feature::Feminine,
+            (Gender, 'n') => Feature::Neuter,
+            (Number, 's') => Feature::Singular,
+            (Number, 'p') => Feature::Plural,
+            (Case, 'n') => Feature::Nominative,
+            (Case, 'g') => Feature::Genitive,
+            (Case, 'd') => Feature::Dative,
+            (Case, 'a') => Feature::Accusative,
+            (Case, 'b') => Feature::Ablative,
+            (Case, 'v') => Feature::Vocative,
+            (Case, 'l') => Feature::Locative,
+            (Person, '1') => Feature::First,
+            (Person, '2') => Feature::Second,
+            (Person, '3') => Feature::Third,
+            (Mood, 'i') => Feature::Indicative,
+            (Mood, 's') => Feature::Subjunctive,
+            (Mood, 'm') => Feature::Imperative,
+            (Mood, 'n') => Feature::Infinitive,
+            (Mood, 'p') => Feature::Participle,
+            (Mood, 'g') => Feature::Gerund,
+            (Mood, 'd') => Feature::Gerundive,
+            (Mood, 'u') => Feature::Supine,
+            (Tense, 'p') => Feature::Present,
+            (Tense, 'i') => Feature::Imperfect,
+            (Tense, 'f') => Feature::Future,
+            (Tense, 's') => Feature::Perfect,
+            (Tense, 'l') => Feature::Pluperfect,
+            (Tense, 't') => Feature::FuturePerfect,
+            (Voice, 'a') => Feature::Active,
+            (Voice, 'p') => Feature::Passive,
+            (Degree, 'c') => Feature::Comparative,
+            (Degree, 's') => Feature::Superlative,
+            // Note, the type (i.e. common or proper noun) and the positive degree have no feature
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tag() {
+        let analysis = Analysis::from_tag("perf subj act 3 pl");
+        assert_eq!(analysis.part_of_speech(), None);
+        assert_eq!(
+            analysis.features().iter().cloned().collect::<Vec<_>>(),
+            vec![
+                Feature::Plural,
+                Feature::Third,
+                Feature::Perfect,
+                Feature::Subjunctive,
+                Feature::Active
+            ]
+        );
+
+        let analysis = Analysis::from_tag("adj abl sg comp");
+        assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Adjective));
+        assert!(analysis.matches(
+            Some(PartOfSpeech::Adjective),
+            &vec![Feature::Ablative, Feature::Comparative]
+                .into_iter()
+                .collect()
+        ));
+        assert!(!analysis.matches(None, &std::iter::once(Feature::Plural).collect()));
+
+        // Unknown abbreviations are only kept in the tag
+        let analysis = Analysis::from_tag("VmH");
+        assert!(analysis.features().is_empty());
+        assert_eq!(analysis.tag(), "VmH");
+    }
+
+    #[test]
+    fn test_from_positional() {
+        let analysis = Analysis::from_positional("Ncfsb").unwrap();
+        assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Noun));
+        assert_eq!(
+            analysis.features().iter().cloned().collect::<Vec<_>>(),
+            vec![Feature::Ablative, Feature::Singular, Feature::Feminine]
+        );
+
+        // The attributes that are not given or not understood are skipped
+        let analysis = Analysis::from_positional("Vmps-smpb").unwrap();
+        assert!(analysis.matches(
+            Some(PartOfSpeech::Verb),
+            &vec![Feature::Perfect, Feature::Participle, Feature::Ablative]
+                .into_iter()
+                .collect()
+        ));
+        let analysis = Analysis::from_positional("VmH").unwrap();
+        assert!(analysis.features().is_empty());
+        assert_eq!(analysis.tag(), "VmH");
+
+        assert!(Analysis::from_positional("adj abl sg").is_none());
+        assert!(Analysis::from_positional("").is_none());
+    }
+}
@@ -0,0 +1,314 @@
+//! Parser for treebanks in the CoNLL-U format (i.e. the Universal Dependencies ones)
+//! Each word is a line of tab separated fields (id, form, lemma, part of speech, tag, features...),
+//! and the sentences are separated by blank lines
+//! Besides building a lemmatizer, a treebank can be read as a source whose lemmas are known
+
+use super::error::ParsingError;
+use super::{ParserBuilder, ParserWrapper};
+use crate::latin_lemmatizer::morphology::Analysis;
+use crate::latin_lemmatizer::{Analyses, Mapping, NaiveLemmatizer, StandardLatinConverter};
+use crate::latin_utilities::tokenizer::Token;
+use crate::latin_utilities::{NormalizedLatinString, Profile};
+
+/// The extension of the sources in the CoNLL-U format
+pub const EXTENSION: &str = "conllu";
+
+/// A line of a CoNLL-U file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Line<'a> {
+    /// A comment, or a blank line which ends a sentence
+    Blank,
+    /// A token made of several words, spanning the given number of ids (i.e. `uirumque`)
+    MultiWord(usize, &'a str),
+    Word(Word<'a>),
+}
+
+/// A (syntactic) word of a CoNLL-U file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Word<'a> {
+    pub form: &'a str,
+    pub lemma: &'a str,
+    pub upos: &'a str,
+    pub feats: &'a str,
+    /// Whether no space follows the word in the text
+    pub no_space_after: bool,
+}
+
+/// Read a single line
+/// Note, the empty nodes of the enhanced dependencies (i.e. `8.1`) are read as blank
+pub fn parse_line(line: &str) -> Result<Line<'_>, ParsingError> {
+    let line = line.trim_end_matches(&['\n', '\r'][..]);
+    if line.trim().is_empty() || line.starts_with('#') {
+        return Ok(Line::Blank);
+    }
+
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 6 {
+        return Err(ParsingError::LineFormatError(line.to_string()));
+    }
+
+    let id = fields[0];
+    if id.contains('.') {
+        return Ok(Line::Blank);
+    }
+
+    let invalid = || ParsingError::LineFormatError(line.to_string());
+    let mut range = id.splitn(2, '-');
+    let start: usize = range.next().unwrap().parse().map_err(|_| invalid())?;
+    if let Some(end) = range.next() {
+        let end: usize = end.parse().map_err(|_| invalid())?;
+        if end < start {
+            return Err(invalid());
+        }
+        return Ok(Line::MultiWord(end - start + 1, fields[1]));
+    }
+
+    Ok(Line::Word(Word {
+        form: fields[1],
+        lemma: fields[2],
+        upos: fields[3],
+        feats: fields[5],
+        no_space_after: fields
+            .get(9)
+            .map(|misc| misc.split('|').any(|m| m == "SpaceAfter=No"))
+            .unwrap_or(false),
+    }))
+}
+
+#[derive(Debug)]
+pub struct ConlluFormatParserBuilder {
+    mapping: Mapping,
+    analyses: Analyses,
+    converter: StandardLatinConverter,
+}
+
+pub type ConlluFormatParser = ParserWrapper<ConlluFormatParserBuilder>;
+
+/// Instantiate a parser for when working with treebanks in the CoNLL-U format
+pub fn new() -> ConlluFormatParser {
+    ConlluFormatParser::new()
+}
+
+impl ParserBuilder for ConlluFormatParserBuilder {
+    type ErrorTy = ParsingError;
+
+    fn new() -> Self {
+        ConlluFormatParserBuilder {
+            mapping: Mapping::new(),
+            analyses: Analyses::new(),
+            converter: StandardLatinConverter::default(),
+        }
+    }
+
+    fn with_profile(self, profile: Profile) -> Self {
+        ConlluFormatParserBuilder {
+            converter: self.converter.with_profile(profile),
+            ..self
+        }
+    }
+
+    fn read_line_as_str(&mut self, line: impl AsRef<str>) -> Result<(), Self::ErrorTy> {
+        let word = match parse_line(line.as_ref())? {
+            Line::Word(word) => word,
+            _ => return Ok(()),
+        };
+
+        // Note, punctuation and words without a lemma are skipped
+        let (form, lemma) = (
+            self.converter.convert(word.form),
+            self.converter.convert(word.lemma),
+        );
+        if form.inner().is_empty() || lemma.inner().is_empty() {
+            return Ok(());
+        }
+
+        self.analyses
+            .entry((form.clone(), lemma.clone()))
+            .or_default()
+            .insert(Analysis::from_universal(word.upos, word.feats));
+
+        // Update the mapping
+        self.mapping.entry(form).or_default().insert(lemma);
+
+        Ok(())
+    }
+
+    fn build(self) -> NaiveLemmatizer {
+        NaiveLemmatizer::new_with_analyses(self.mapping, self.analyses)
+            .with_converter(self.converter)
+    }
+}
+
+/// A treebank read as a source, with a line of text for each sentence
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Treebank {
+    text: String,
+    tokens: Vec<Token>,
+    lemmas: Vec<NormalizedLatinString>,
+}
+
+impl Treebank {
+    /// Read a treebank, skipping the lines that are not valid
+    pub fn parse(content: &str) -> Self {
+        Self::parse_with(content, &StandardLatinConverter::default())
+    }
+
+    /// Read a treebank, with its lemmas normalized by a converter other than the standard one
+    pub fn parse_with(content: &str, converter: &StandardLatinConverter) -> Self {
+        let mut res = Treebank::default();
+        let (mut line, mut line_no) = (String::new(), 0);
+        // The words still to come of a multi word token, together with its offset
+        let mut pending = (0, 0);
+
+        for raw in content.lines() {
+            let parsed = match parse_line(raw) {
+                Ok(parsed) => parsed,
+                Err(_) => continue,
+            };
+
+            let word = match parsed {
+                Line::Blank if raw.trim().is_empty() && !line.is_empty() => {
+                    res.end_sentence(&mut line);
+                    line_no += 1;
+                    continue;
+                }
+                Line::Blank => continue,
+                Line::MultiWord(words, surface) => {
+                    pending = (words, Self::append(&mut line, surface));
+                    continue;
+                }
+                Line::Word(word) => word,
+            };
+
+            let offset = if pending.0 > 0 {
+                pending.0 -= 1;
+                pending.1
+            } else {
+                let offset = Self::append(&mut line, word.form);
+                if word.no_space_after {
+                    line.pop();
+                }
+                offset
+            };
+
+            let (form, lemma) = (converter.convert(word.form), converter.convert(word.lemma));
+            if form.inner().is_empty() {
+                continue;
+            }
+
+            let index = res
+                .tokens
+                .iter()
+                .rev()
+                .take_while(|t| t.line_no() == line_no)
+                .count();
+            res.tokens
+                .push(Token::new(line_no, index, offset, word.form.to_string()));
+            res.lemmas.push(lemma);
+        }
+
+        if !line.is_empty() {
+            res.end_sentence(&mut line);
+        }
+
+        res
+    }
+
+    // Add a word to the sentence, followed by a space, returning its offset
+    fn append(line: &mut String, word: &str) -> usize {
+        let offset = line.chars().count();
+        line.push_str(word);
+        line.push(' ');
+        offset
+    }
+
+    fn end_sentence(&mut self, line: &mut String) {
+        self.text.push_str(line.trim_end());
+        self.text.push('\n');
+        line.clear();
+    }
+
+    /// The text of the sentences, one per line
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The words, in the order they appear
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// The lemma of each word (empty if it has none)
+    pub fn lemmas(&self) -> &[NormalizedLatinString] {
+        &self.lemmas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::latin_lemmatizer::morphology::{Feature, PartOfSpeech};
+
+    const SENTENCE: &str = "# sent_id = 1\n\
+        # text = Arma uirumque cano.\n\
+        1\tArma\tarma\tNOUN\t_\tCase=Acc|Gender=Neut|Number=Plur\t3\tobj\t_\t_\n\
+        2-3\tuirumque\t_\t_\t_\t_\t_\t_\t_\t_\n\
+        2\tuirum\tuir\tNOUN\t_\tCase=Acc|Gender=Masc|Number=Sing\t1\tconj\t_\t_\n\
+        3\tque\tque\tCCONJ\t_\t_\t2\tcc\t_\t_\n\
+        4\tcano\tcano\tVERB\t_\tMood=Ind|Number=Sing|Person=1|Tense=Pres|Voice=Act\t0\troot\t_\tSpaceAfter=No\n\
+        5\t.\t.\tPUNCT\t_\t_\t4\tpunct\t_\t_\n\
+        \n";
+
+    #[test]
+    fn test_lemmatizer() {
+        let parser = new().read_all(SENTENCE.as_bytes()).unwrap();
+        let lemmatizer = parser.build();
+
+        assert_eq!(lemmatizer.num_forms(), 4);
+        assert!(lemmatizer.has_lemma(&"uir".into()));
+        assert!(!lemmatizer.has_form(&"uirumque".into()));
+
+        let analyses = lemmatizer
+            .get_analyses(&"cano".into(), &"cano".into())
+            .unwrap();
+        let analysis = analyses.iter().next().unwrap();
+        assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Verb));
+        assert!(analysis.has(Feature::Present));
+        assert!(analysis.has(Feature::First));
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let mut parser = new();
+        assert!(parser.read_line(b"1\tArma\tarma" as &[u8]).is_err());
+        assert!(parser.read_line(b"x-2\tArma\t_\t_\t_\t_" as &[u8]).is_err());
+    }
+
+    #[test]
+    fn test_treebank() {
+        let treebank = Treebank::parse(&format!("{}{}", SENTENCE, SENTENCE));
+        assert_eq!(
+            treebank.text(),
+            "Arma uirumque cano.\nArma uirumque cano.\n"
+        );
+
+        let words: Vec<_> = treebank
+            .tokens()
+            .iter()
+            .map(|t| (t.line_no(), t.index(), t.offset(), t.word()))
+            .collect();
+        assert_eq!(
+            &words[..4],
+            &[
+                (0, 0, 0, "Arma"),
+                (0, 1, 5, "uirum"),
+                (0, 2, 5, "que"),
+                (0, 3, 14, "cano")
+            ]
+        );
+        assert_eq!(words.len(), 8);
+        assert_eq!(words[7], (1, 3, 14, "cano"));
+        assert_eq!(treebank.lemmas()[1], "uir");
+    }
+}
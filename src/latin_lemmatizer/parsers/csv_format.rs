@@ -1,13 +1,19 @@
 //! Parser for data in the CSV format
+//! Each line is made of the form, its spelling, the lemma and optionally the inflection class
+//! (i.e. `V3`, whose initial is the part of speech), an id and the morphological tag, either
+//! a positional one (i.e. `Vmsl1s-a`) or abbreviations
 
 use super::error::ParsingError;
 use super::{ParserBuilder, ParserWrapper};
-use crate::latin_lemmatizer::{Mapping, NaiveLemmatizer, StandardLatinConverter};
+use crate::latin_lemmatizer::morphology::{Analysis, PartOfSpeech};
+use crate::latin_lemmatizer::{Analyses, Mapping, NaiveLemmatizer, StandardLatinConverter};
+use crate::latin_utilities::Profile;
 use std::collections::HashSet;
 
 #[derive(Debug)]
 pub struct CSVFormatParserBuilder {
     mapping: Mapping,
+    analyses: Analyses,
     converter: StandardLatinConverter,
 }
 
@@ -24,10 +30,18 @@ impl ParserBuilder for CSVFormatParserBuilder {
     fn new() -> Self {
         CSVFormatParserBuilder {
             mapping: Mapping::new(),
+            analyses: Analyses::new(),
             converter: StandardLatinConverter::default(),
         }
     }
 
+    fn with_profile(self, profile: Profile) -> Self {
+        CSVFormatParserBuilder {
+            converter: self.converter.with_profile(profile),
+            ..self
+        }
+    }
+
     fn read_line_as_str(&mut self, line: impl AsRef<str>) -> Result<(), Self::ErrorTy> {
         let line = line.as_ref();
         let segments: Vec<&str> = line.split(',').collect();
@@ -40,6 +54,13 @@ impl ParserBuilder for CSVFormatParserBuilder {
 
         let (lemma, form) = (self.converter.convert(lemma), self.converter.convert(form));
 
+        if let Some(analysis) = analysis(&segments[3..]) {
+            self.analyses
+                .entry((form.clone(), lemma.clone()))
+                .or_default()
+                .insert(analysis);
+        }
+
         // Update the mapping
         self.mapping
             .entry(form)
@@ -50,14 +71,47 @@ impl ParserBuilder for CSVFormatParserBuilder {
     }
 
     fn build(self) -> NaiveLemmatizer {
-        NaiveLemmatizer::new(self.mapping)
+        NaiveLemmatizer::new_with_analyses(self.mapping, self.analyses)
+            .with_converter(self.converter)
     }
 }
 
+// The analysis given by the columns after the lemma, if there are any
+fn analysis(columns: &[&str]) -> Option<Analysis> {
+    let class = columns.first().map(|s| s.trim()).unwrap_or("");
+    let tag = columns
+        .iter()
+        .skip(2)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if class.is_empty() && tag.is_empty() {
+        return None;
+    }
+
+    let parsed = Analysis::from_positional(&tag).unwrap_or_else(|| Analysis::from_tag(&tag));
+    let pos = match class.chars().next() {
+        Some('N') => Some(PartOfSpeech::Noun),
+        Some('V') => Some(PartOfSpeech::Verb),
+        Some('A') => Some(PartOfSpeech::Adjective),
+        _ => parsed.part_of_speech(),
+    };
+    let tag = [class, &tag]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(Analysis::new(pos, parsed.features().iter().cloned(), tag))
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::latin_lemmatizer::morphology::Feature;
 
     #[test]
     fn test_line() {
@@ -68,5 +122,42 @@ mod tests {
         let lemmatizer = parser.build();
         assert_eq!(lemmatizer.num_forms(), 1);
         assert!(lemmatizer.has_form(&"iungam".into()));
+
+        let analyses = lemmatizer
+            .get_analyses(&"iungam".into(), &"iungo".into())
+            .unwrap();
+        assert_eq!(analyses.len(), 1);
+        let analysis = analyses.iter().next().unwrap();
+        assert_eq!(analysis.part_of_speech(), Some(PartOfSpeech::Verb));
+        assert_eq!(analysis.tag(), "V3 VmH");
+    }
+
+    #[test]
+    fn test_positional() {
+        let mut parser = new();
+        parser
+            .read_line(b"amauissem,amauissem,amo,V1,i1,,Vmsl1s-a" as &[u8])
+            .unwrap();
+
+        let lemmatizer = parser.build();
+        let analyses = lemmatizer
+            .get_analyses(&"amauissem".into(), &"amo".into())
+            .unwrap();
+        let analysis = analyses.iter().next().unwrap();
+        assert!(analysis.has(Feature::Subjunctive));
+        assert!(analysis.has(Feature::Pluperfect));
+        assert!(!analysis.has(Feature::Indicative));
+        assert_eq!(analysis.tag(), "V1 Vmsl1s-a");
+    }
+
+    #[test]
+    fn test_no_analysis() {
+        let mut parser = new();
+        parser.read_line(b"uenit,uenit,uenio" as &[u8]).unwrap();
+
+        let lemmatizer = parser.build();
+        assert!(lemmatizer
+            .get_analyses(&"uenit".into(), &"uenio".into())
+            .is_none());
     }
 }
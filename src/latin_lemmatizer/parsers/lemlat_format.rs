@@ -2,12 +2,15 @@
 
 use super::error::ParsingError;
 use super::{ParserBuilder, ParserWrapper};
-use crate::latin_lemmatizer::{Mapping, NaiveLemmatizer, StandardLatinConverter};
+use crate::latin_lemmatizer::morphology::Analysis;
+use crate::latin_lemmatizer::{Analyses, Mapping, NaiveLemmatizer, StandardLatinConverter};
+use crate::latin_utilities::Profile;
 use std::collections::HashSet;
 
 #[derive(Debug)]
 pub struct LemlatFormatParserBuilder {
     mapping: Mapping,
+    analyses: Analyses,
     converter: StandardLatinConverter,
 }
 
@@ -24,11 +27,19 @@ impl ParserBuilder for LemlatFormatParserBuilder {
     fn new() -> Self {
         LemlatFormatParserBuilder {
             mapping: Mapping::new(),
+            analyses: Analyses::new(),
             converter: StandardLatinConverter::default(),
         }
     }
 
     // Used to reduce the cost of calling BufReader::new
+    fn with_profile(self, profile: Profile) -> Self {
+        LemlatFormatParserBuilder {
+            converter: self.converter.with_profile(profile),
+            ..self
+        }
+    }
+
     fn read_line_as_str(&mut self, line: impl AsRef<str>) -> Result<(), Self::ErrorTy> {
         let line = line.as_ref();
         let header_body: Vec<&str> = line.split('\t').collect();
@@ -49,6 +60,18 @@ impl ParserBuilder for LemlatFormatParserBuilder {
             // Convert to normal form
             let (lemma, form) = (self.converter.convert(lemma), self.converter.convert(form));
 
+            // The tag is in parentheses after the form, i.e. `Aaroni (masc dat sg)`
+            let tag = record
+                .find('(')
+                .map(|start| record[start + 1..].trim_end_matches(')'))
+                .filter(|tag| !tag.trim().is_empty());
+            if let Some(tag) = tag {
+                self.analyses
+                    .entry((form.clone(), lemma.clone()))
+                    .or_default()
+                    .insert(Analysis::from_tag(tag));
+            }
+
             // Update the mapping
             self.mapping
                 .entry(form)
@@ -60,7 +83,8 @@ impl ParserBuilder for LemlatFormatParserBuilder {
     }
 
     fn build(self) -> NaiveLemmatizer {
-        NaiveLemmatizer::new(self.mapping)
+        NaiveLemmatizer::new_with_analyses(self.mapping, self.analyses)
+            .with_converter(self.converter)
     }
 }
 
@@ -68,16 +92,27 @@ impl ParserBuilder for LemlatFormatParserBuilder {
 mod tests {
 
     use super::*;
+    use crate::latin_lemmatizer::morphology::Feature;
 
     #[test]
     fn test_line() {
         let mut parser = new();
-        let res = parser.read_line(b"Aaron	28308	Aaron (masc nom sg)	Aaroni (masc dat sg)" as &[u8]);
+        let res =
+            parser.read_line(b"Aaron	28308	Aaron (masc nom sg)	Aaroni (masc dat sg)" as &[u8]);
         res.unwrap();
 
         let lemmatizer = parser.build();
         assert_eq!(lemmatizer.num_forms(), 2);
         assert!(lemmatizer.has_form(&"Aaron".into()));
         assert!(lemmatizer.has_form(&"Aaroni".into()));
+
+        let analyses = lemmatizer
+            .get_analyses(&"Aaroni".into(), &"Aaron".into())
+            .unwrap();
+        let analysis = analyses.iter().next().unwrap();
+        assert_eq!(analysis.tag(), "masc dat sg");
+        assert!(analysis.has(Feature::Masculine));
+        assert!(analysis.has(Feature::Dative));
+        assert!(analysis.has(Feature::Singular));
     }
 }
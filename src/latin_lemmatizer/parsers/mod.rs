@@ -2,10 +2,12 @@
 //! In particular, with this we can build a [`NaiveLemmatizer`](struct.NaiveLemmatizer.html)
 
 use super::NaiveLemmatizer;
+use crate::latin_utilities::Profile;
 use error::CompositeParsingError;
 use std::fmt::Debug;
 use std::io::{prelude::*, BufReader};
 
+pub mod conllu_format;
 pub mod csv_format;
 pub mod error;
 pub mod lemlat_format;
@@ -18,6 +20,9 @@ pub trait ParserBuilder {
     /// Initiate the builder
     fn new() -> Self;
 
+    /// Normalize the forms and the lemmas with a profile
+    fn with_profile(self, profile: Profile) -> Self;
+
     /// Read a single line
     fn read_line_as_str(&mut self, line: impl AsRef<str>) -> Result<(), Self::ErrorTy>;
 
@@ -40,6 +45,11 @@ where
         ParserWrapper(T::new())
     }
 
+    /// Normalize the forms and the lemmas with a profile, instead of the classical one
+    pub fn with_profile(self, profile: Profile) -> Self {
+        ParserWrapper(self.0.with_profile(profile))
+    }
+
     /// Read a line from a general source
     pub fn read_line(&mut self, reader: impl Read) -> Result<(), ErrorTy<T>> {
         let mut reader = BufReader::new(reader);
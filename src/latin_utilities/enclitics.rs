@@ -0,0 +1,106 @@
+//! Handling of enclitics, i.e. particles that are appended to a word
+//! (`senatusque`, `estne`, `plusue`, `mecum`)
+
+use super::NormalizedLatinString;
+use serde::{Deserialize, Serialize};
+
+/// An enclitic that was split off a form
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Enclitic {
+    Que,
+    Ne,
+    Ve,
+    Cum,
+}
+
+// The pronouns that take `cum` as a postposition
+const CUM_HOSTS: [&str; 9] = [
+    "me", "te", "se", "nobis", "uobis", "quo", "qua", "qui", "quibus",
+];
+
+impl Enclitic {
+    /// The enclitic as it appears in normalized form
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Enclitic::Que => "que",
+            Enclitic::Ne => "ne",
+            // Note, v -> u in normalized strings
+            Enclitic::Ve => "ue",
+            Enclitic::Cum => "cum",
+        }
+    }
+
+    /// Get the possible ways to split an enclitic from a form, in order of preference
+    /// ```
+    /// use latin_db::latin_utilities::{Enclitic, NormalizedLatinString};
+    /// let res = Enclitic::split(&NormalizedLatinString::from("senatusque"));
+    /// assert_eq!(res[0], ("senatus".into(), Enclitic::Que));
+    /// ```
+    pub fn split(form: &NormalizedLatinString) -> Vec<(NormalizedLatinString, Enclitic)> {
+        let word = form.inner();
+        let mut res = Vec::new();
+
+        if let Some(host) = strip(word, Enclitic::Cum) {
+            if CUM_HOSTS.contains(&host) {
+                res.push((NormalizedLatinString::instantiate(host), Enclitic::Cum));
+            }
+        }
+
+        for &enclitic in &[Enclitic::Que, Enclitic::Ne, Enclitic::Ve] {
+            if let Some(host) = strip(word, enclitic) {
+                res.push((NormalizedLatinString::instantiate(host), enclitic));
+            }
+        }
+
+        res
+    }
+}
+
+// Strip the enclitic, if it leaves something behind
+fn strip(word: &str, enclitic: Enclitic) -> Option<&str> {
+    let host = word.strip_suffix(enclitic.as_str())?;
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(s: &str) -> Vec<(NormalizedLatinString, Enclitic)> {
+        Enclitic::split(&s.into())
+    }
+
+    #[test]
+    fn test_no_enclitic() {
+        assert!(split("").is_empty());
+        assert!(split("rosa").is_empty());
+        assert!(split("ne").is_empty());
+    }
+
+    #[test]
+    fn test_que() {
+        let res = split("uirumque");
+        assert_eq!(res[0], ("uirum".into(), Enclitic::Que));
+        // The less likely -ue split is still proposed
+        assert_eq!(res[1], ("uirumq".into(), Enclitic::Ve));
+    }
+
+    #[test]
+    fn test_ne_ve() {
+        assert_eq!(split("estne"), vec![("est".into(), Enclitic::Ne)]);
+        assert_eq!(split("plusve"), vec![("plus".into(), Enclitic::Ve)]);
+    }
+
+    #[test]
+    fn test_cum() {
+        assert_eq!(split("mecum"), vec![("me".into(), Enclitic::Cum)]);
+        assert_eq!(split("nobiscum"), vec![("nobis".into(), Enclitic::Cum)]);
+        assert_eq!(split("quibuscum"), vec![("quibus".into(), Enclitic::Cum)]);
+        // Not a pronoun
+        assert!(split("uerbocum").is_empty());
+    }
+}
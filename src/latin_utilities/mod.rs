@@ -3,9 +3,19 @@
 //! format that resembles closely Italian.
 //! The high level concept is to normalize unicode, remove all non alphabetical
 //! characters, and to then replace j, v to i, u
+//! Raw text can be split into words with the [`LatinTokenizer`](tokenizer/struct.LatinTokenizer.html)
+//! Other spellings (i.e. the medieval one) can be normalized further with a [`Profile`](normalizer/struct.Profile.html),
+//! and the variants that are left grouped in families with [`variant_key`](variants/fn.variant_key.html)
 
+pub mod enclitics;
 mod normalized_latin_string;
+pub mod normalizer;
+pub mod tokenizer;
+pub mod variants;
+pub use self::enclitics::Enclitic;
 pub use self::normalized_latin_string::NormalizedLatinString;
+pub use self::normalizer::{Normalizer, Profile};
+pub use self::tokenizer::LatinTokenizer;
 
 use lazy_static::lazy_static;
 use std::collections::HashSet;
@@ -14,7 +24,9 @@ use unicode_normalization::UnicodeNormalization;
 /// A converter which can be use to turn a `&str` into a
 ///  [`NormalizedLatingString`](struct.NormalizedLatinString.html)
 #[derive(Debug, Clone, Default)]
-pub struct StandardLatinConverter;
+pub struct StandardLatinConverter {
+    profile: Profile,
+}
 
 lazy_static! {
     static ref ALLOWED: HashSet<char> = {
@@ -27,6 +39,15 @@ lazy_static! {
 }
 
 impl StandardLatinConverter {
+    /// Normalize the spelling further with a profile, after the standard conversion
+    pub fn with_profile(self, profile: Profile) -> Self {
+        StandardLatinConverter { profile }
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
     /// Convert a str to the correctly parsed form, converting j -> i, v -> u
     /// ```
     /// use latin_db::latin_utilities::StandardLatinConverter;
@@ -57,6 +78,10 @@ impl StandardLatinConverter {
             res = res.replace(to_replace, replacement);
         }
 
+        if !self.profile.is_empty() {
+            res = self.profile.normalize(&res);
+        }
+
         NormalizedLatinString::instantiate(res)
     }
 }
@@ -131,6 +156,13 @@ mod tests {
         assert_eq!(converter.convert("a'vitus puella"), "auitus puella");
     }
 
+    #[test]
+    fn test_profile() {
+        let converter = make().with_profile(Profile::medieval());
+        assert_eq!(converter.convert("Gratia michi"), "gracia mii");
+        assert_eq!(make().convert("Gratia michi"), "gratia michi");
+    }
+
     #[test]
     fn test_underscore() {
         let converter = make();
@@ -0,0 +1,281 @@
+//! Orthographic normalizations, applied on top of the standard conversion
+//! Epigraphic and medieval texts spell the same words differently (`pena` for `poena`,
+//! `gracia` for `gratia`, `michi` for `mihi`), so a profile of rules can bring them together
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something that normalizes words that were already converted (i.e. lowercase, with u and i)
+pub trait Normalizer {
+    fn normalize(&self, word: &str) -> String;
+}
+
+/// A single orthographic rule
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Rule {
+    /// `ae` -> `e`
+    AeToE,
+    /// `oe` -> `e`
+    OeToE,
+    /// `ti` -> `ci` before a vowel, unless after `s`, `t` or `x`
+    TiToCi,
+    /// Drop the `h`, except in `ch`, `ph` and `th`
+    /// Note, the `ch` of `michi` and `nichil` is the `h` of `mihi` and `nihil`, so it is dropped too
+    DropH,
+    /// `y` -> `i`
+    YToI,
+    /// `ph` -> `f`
+    PhToF,
+}
+
+const RULES: [Rule; 6] = [
+    Rule::AeToE,
+    Rule::OeToE,
+    Rule::TiToCi,
+    Rule::DropH,
+    Rule::YToI,
+    Rule::PhToF,
+];
+
+fn is_vowel(c: char) -> bool {
+    "aeiou".contains(c)
+}
+
+impl Rule {
+    /// The name of the rule in a profile
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::AeToE => "ae",
+            Rule::OeToE => "oe",
+            Rule::TiToCi => "ti",
+            Rule::DropH => "h",
+            Rule::YToI => "y",
+            Rule::PhToF => "ph",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        RULES.iter().cloned().find(|rule| rule.name() == name)
+    }
+}
+
+impl Normalizer for Rule {
+    fn normalize(&self, word: &str) -> String {
+        let chars: Vec<char> = word.chars().collect();
+        match self {
+            Rule::AeToE => word.replace("ae", "e"),
+            Rule::OeToE => word.replace("oe", "e"),
+            Rule::YToI => word.replace('y', "i"),
+            Rule::PhToF => word.replace("ph", "f"),
+            Rule::TiToCi => chars
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| {
+                    let before = if i > 0 { chars[i - 1] } else { ' ' };
+                    let assibilated = c == 't'
+                        && chars.get(i + 1) == Some(&'i')
+                        && chars.get(i + 2).cloned().is_some_and(is_vowel)
+                        && !"stx".contains(before);
+                    if assibilated {
+                        'c'
+                    } else {
+                        c
+                    }
+                })
+                .collect(),
+            Rule::DropH => {
+                let chars: Vec<char> = word.replace("ichi", "ihi").chars().collect();
+                chars
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, &c)| c != 'h' || (i > 0 && "cpt".contains(chars[i - 1])))
+                    .map(|(_, &c)| c)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A named profile, or rules that do not make up one, is not known
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProfileError(String);
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown normalization profile or rule {:?}", self.0)
+    }
+}
+
+impl Error for ProfileError {}
+
+/// A set of rules, applied in order
+/// Note, the default profile (i.e. `classical`) has no rules, so only the standard conversion is done
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Profile {
+    rules: Vec<Rule>,
+}
+
+impl Profile {
+    pub fn new(rules: impl IntoIterator<Item = Rule>) -> Self {
+        Profile::default().with_rules(rules)
+    }
+
+    /// The spelling of the classical editions, which needs no rules
+    pub fn classical() -> Self {
+        Profile::default()
+    }
+
+    /// The spelling of the inscriptions
+    pub fn epigraphic() -> Self {
+        Profile::new(vec![Rule::AeToE, Rule::OeToE, Rule::DropH, Rule::YToI])
+    }
+
+    /// The spelling of the medieval manuscripts
+    pub fn medieval() -> Self {
+        Profile::new(vec![
+            Rule::AeToE,
+            Rule::OeToE,
+            Rule::TiToCi,
+            Rule::PhToF,
+            Rule::DropH,
+            Rule::YToI,
+        ])
+    }
+
+    /// Add some rules after the ones of the profile, skipping the ones it already has
+    pub fn with_rules(mut self, rules: impl IntoIterator<Item = Rule>) -> Self {
+        for rule in rules {
+            if !self.rules.contains(&rule) {
+                self.rules.push(rule);
+            }
+        }
+        self
+    }
+
+    /// The profile with the rules of another one after its own
+    pub fn then(self, other: &Profile) -> Self {
+        self.with_rules(other.rules.iter().cloned())
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Normalizer for Profile {
+    fn normalize(&self, word: &str) -> String {
+        self.rules
+            .iter()
+            .fold(word.to_string(), |word, rule| rule.normalize(&word))
+    }
+}
+
+// The profiles that are known by name
+type NamedProfile = (&'static str, fn() -> Profile);
+
+const PROFILES: [NamedProfile; 3] = [
+    ("classical", Profile::classical),
+    ("epigraphic", Profile::epigraphic),
+    ("medieval", Profile::medieval),
+];
+
+/// Read a profile by its name (i.e. `medieval`), or as its rules (i.e. `ae+oe+y`)
+impl FromStr for Profile {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((_, profile)) = PROFILES.iter().find(|(name, _)| *name == s) {
+            return Ok(profile());
+        }
+
+        let rules = s
+            .split('+')
+            .map(|name| Rule::from_name(name.trim()).ok_or_else(|| ProfileError(name.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Profile::new(rules))
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((name, _)) = PROFILES.iter().find(|(_, profile)| profile() == *self) {
+            return write!(f, "{}", name);
+        }
+
+        let names: Vec<_> = self.rules.iter().map(|rule| rule.name()).collect();
+        write!(f, "{}", names.join("+"))
+    }
+}
+
+impl TryFrom<String> for Profile {
+    type Error = ProfileError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Profile> for String {
+    fn from(profile: Profile) -> Self {
+        profile.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let cases = [
+            (Rule::AeToE, "quaestio", "questio"),
+            (Rule::OeToE, "poena", "pena"),
+            (Rule::TiToCi, "gratia", "gracia"),
+            (Rule::TiToCi, "ostium", "ostium"),
+            (Rule::TiToCi, "tibi", "tibi"),
+            (
+                Rule::DropH,
+                "homo habet philosophiam",
+                "omo abet philosophiam",
+            ),
+            (Rule::DropH, "michi nichil", "mii niil"),
+            (Rule::YToI, "hymnus", "himnus"),
+            (Rule::PhToF, "philosophia", "filosofia"),
+        ];
+
+        for (rule, word, normalized) in cases.iter() {
+            assert_eq!(rule.normalize(word), *normalized);
+        }
+    }
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!(Profile::classical().normalize("michi"), "michi");
+        assert_eq!(Profile::medieval().normalize("michi"), "mii");
+        assert_eq!(Profile::medieval().normalize("mihi"), "mii");
+        assert_eq!(Profile::epigraphic().normalize("haeres"), "eres");
+
+        let profile = Profile::classical().then(&"y+ae".parse().unwrap());
+        assert_eq!(profile.rules(), &[Rule::YToI, Rule::AeToE]);
+        assert_eq!(Profile::epigraphic().then(&profile).rules().len(), 4);
+    }
+
+    #[test]
+    fn test_names() {
+        for s in &["classical", "epigraphic", "medieval", "ae+ti"] {
+            let profile: Profile = s.parse().unwrap();
+            assert_eq!(profile.to_string(), *s);
+        }
+        assert_eq!("".parse::<Profile>(), Err(ProfileError("".to_string())));
+        assert!("ae+ei".parse::<Profile>().is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Splitting of raw Latin text into word tokens
+//! A token is a maximal run of letters, which may contain editorial marks such as
+//! apostrophes, brackets and hyphens (these are later removed by the converter).
+//! Any other character (whitespace of any kind, punctuation, digits) separates tokens,
+//! and words hyphenated across the end of a line are joined back together
+
+use unicode_normalization::char::is_combining_mark;
+
+/// A single word in a text, together with its position
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct Token {
+    line_no: usize,
+    index: usize,
+    offset: usize,
+    word: String,
+}
+
+impl Token {
+    pub fn new(line_no: usize, index: usize, offset: usize, word: String) -> Self {
+        Token {
+            line_no,
+            index,
+            offset,
+            word,
+        }
+    }
+
+    /// The raw (not normalized) word, stripped of surrounding punctuation
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+
+    /// The (zero based) line in which the token starts
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    /// The position of the token among the ones starting in its line
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The offset, in chars, of the start of the token in its line
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// A tokenizer to split Latin text into [`Token`](struct.Token.html)s
+#[derive(Debug, Clone, Default)]
+pub struct LatinTokenizer;
+
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic() || is_combining_mark(c)
+}
+
+// Marks that can appear inside of a word (elisions, editorial integrations, hyphenation)
+fn is_joiner(c: char) -> bool {
+    matches!(
+        c,
+        '\'' | '’' | '-' | '[' | ']' | '<' | '>' | '{' | '}' | '(' | ')'
+    )
+}
+
+// Make a token out of a run of letters and joiners, returning also if it ended with an hyphen
+fn make_token(line_no: usize, index: usize, start: usize, run: &str) -> Option<(Token, bool)> {
+    let trimmed = run.trim_start_matches(is_joiner);
+    let word = trimmed.trim_end_matches(is_joiner);
+
+    if word.is_empty() {
+        return None;
+    }
+
+    let skipped = run.chars().count() - trimmed.chars().count();
+    let hyphenated = trimmed[word.len()..].contains('-');
+
+    Some((
+        Token {
+            line_no,
+            index,
+            offset: start + skipped,
+            word: word.to_string(),
+        },
+        hyphenated,
+    ))
+}
+
+// Tokenize a single line, returning if the line ends in an hyphenated word
+fn tokenize_line(line_no: usize, line: &str) -> (Vec<Token>, bool) {
+    let mut tokens = Vec::new();
+    let mut run: Option<(usize, String)> = None;
+    let mut hyphenated = false;
+
+    for (offset, c) in line.chars().enumerate() {
+        if is_letter(c) || is_joiner(c) {
+            run.get_or_insert_with(|| (offset, String::new())).1.push(c);
+            continue;
+        }
+
+        if let Some((start, word)) = run.take() {
+            hyphenated = false;
+            if let Some((token, h)) = make_token(line_no, tokens.len(), start, &word) {
+                tokens.push(token);
+                hyphenated = h;
+            }
+        }
+
+        // Only trailing whitespace can follow a word split over two lines
+        if !c.is_whitespace() {
+            hyphenated = false;
+        }
+    }
+
+    if let Some((start, word)) = run.take() {
+        hyphenated = false;
+        if let Some((token, h)) = make_token(line_no, tokens.len(), start, &word) {
+            tokens.push(token);
+            hyphenated = h;
+        }
+    }
+
+    (tokens, hyphenated)
+}
+
+impl LatinTokenizer {
+    /// Split a text in tokens, in the order they appear
+    /// ```
+    /// use latin_db::latin_utilities::tokenizer::LatinTokenizer;
+    /// let res = LatinTokenizer.tokenize("arma\tuirum-\nque, cano");
+    /// let words: Vec<_> = res.iter().map(|t| t.word()).collect();
+    /// assert_eq!(words, vec!["arma", "uirumque", "cano"]);
+    /// ```
+    pub fn tokenize(&self, text: impl AsRef<str>) -> Vec<Token> {
+        let mut res: Vec<Token> = Vec::new();
+        let mut pending_hyphen = false;
+
+        for (line_no, line) in text.as_ref().lines().enumerate() {
+            let (tokens, hyphenated) = tokenize_line(line_no, line);
+            let mut tokens = tokens.into_iter();
+
+            // Join the first word of this line with the last of the previous one
+            if pending_hyphen {
+                if let Some(continuation) = tokens.next() {
+                    // Note, a pending hyphen implies that a token has been pushed
+                    res.last_mut().unwrap().word.push_str(continuation.word());
+                }
+            }
+
+            res.extend(tokens);
+            pending_hyphen = hyphenated;
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn words(s: &str) -> Vec<String> {
+        LatinTokenizer
+            .tokenize(s)
+            .into_iter()
+            .map(|t| t.word)
+            .collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(words("").is_empty());
+        assert!(words(" \t \n\n ").is_empty());
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(
+            words("arma  uirumque\tcano"),
+            vec!["arma", "uirumque", "cano"]
+        );
+        assert_eq!(
+            words(" arma\u{a0}uirumque \r\ncano "),
+            vec!["arma", "uirumque", "cano"]
+        );
+    }
+
+    #[test]
+    fn test_punctuation() {
+        assert_eq!(
+            words("Notum est omnibus, Celse,"),
+            vec!["Notum", "est", "omnibus", "Celse"]
+        );
+        assert_eq!(
+            words("«quo usque?» tandem;abutere"),
+            vec!["quo", "usque", "tandem", "abutere"]
+        );
+        assert_eq!(words("12 arma 3uirum"), vec!["arma", "uirum"]);
+        assert_eq!(words("-- . ; --"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_editorial_marks() {
+        assert_eq!(
+            words("imp(eratori) [Ca]esari"),
+            vec!["imp(eratori", "Ca]esari"]
+        );
+        assert_eq!(words("e'do a-vitus"), vec!["e'do", "a-vitus"]);
+    }
+
+    #[test]
+    fn test_hyphenation() {
+        assert_eq!(
+            words("arma uirum-\nque cano"),
+            vec!["arma", "uirumque", "cano"]
+        );
+        assert_eq!(words("uirum- \n  que"), vec!["uirumque"]);
+        assert_eq!(words("ar-\nmauirum-\nque"), vec!["armauirumque"]);
+        // Not a line end, or nothing to join with
+        assert_eq!(words("uirum- que"), vec!["uirum", "que"]);
+        assert_eq!(words("uirum-.\nque"), vec!["uirum", "que"]);
+        assert_eq!(words("uirum-\n\nque"), vec!["uirum", "que"]);
+    }
+
+    #[test]
+    fn test_positions() {
+        let tokens = LatinTokenizer.tokenize("Arma, (uirumque)\n  cano  Troiae");
+        let positions: Vec<_> = tokens.iter().map(|t| (t.line_no(), t.offset())).collect();
+        assert_eq!(positions, vec![(0, 0), (0, 7), (1, 2), (1, 8)]);
+    }
+
+    #[test]
+    fn test_indices() {
+        let tokens = LatinTokenizer.tokenize("et arma, et uirum-\nque cano");
+        let indices: Vec<_> = tokens.iter().map(|t| (t.line_no(), t.index())).collect();
+        assert_eq!(indices, vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1)]);
+    }
+
+    #[test]
+    fn test_positions_unicode() {
+        let tokens = LatinTokenizer.tokenize("amóre ínermus");
+        let positions: Vec<_> = tokens.iter().map(|t| t.offset()).collect();
+        assert_eq!(positions, vec![0, 6]);
+    }
+
+    proptest! {
+        #[test]
+        fn doesnt_crash(s in "\\PC*") {
+            LatinTokenizer.tokenize(s);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Spelling variants that are still apart after the normalization
+//! (i.e. `adfero`/`affero`, `inpendium`/`impendium`, `quom`/`cum`)
+//! Each form is reduced to a key, undoing the assimilation of the prefixes and the archaic spellings,
+//! and the forms with the same key make up a family
+
+// The assimilated prefixes, with the spelling they are brought back to
+const ASSIMILATIONS: [(&str, &str); 29] = [
+    ("acc", "adc"),
+    ("acq", "adq"),
+    ("aff", "adf"),
+    ("agg", "adg"),
+    ("all", "adl"),
+    ("app", "adp"),
+    ("arr", "adr"),
+    ("ass", "ads"),
+    ("att", "adt"),
+    ("imb", "inb"),
+    ("imm", "inm"),
+    ("imp", "inp"),
+    ("ill", "inl"),
+    ("irr", "inr"),
+    ("comb", "conb"),
+    ("comm", "conm"),
+    ("comp", "conp"),
+    ("coll", "conl"),
+    ("corr", "conr"),
+    ("occ", "obc"),
+    ("off", "obf"),
+    ("opp", "obp"),
+    ("succ", "subc"),
+    ("suff", "subf"),
+    ("sugg", "subg"),
+    ("summ", "subm"),
+    ("supp", "subp"),
+    ("surr", "subr"),
+    ("eff", "exf"),
+];
+
+// The archaic words, with their classical spelling
+const ARCHAIC_WORDS: [(&str, &str); 5] = [
+    ("quom", "cum"),
+    ("quum", "cum"),
+    ("quoi", "cui"),
+    ("quoius", "cuius"),
+    ("uolt", "uult"),
+];
+
+// The archaic beginnings of a word (i.e. `optumus`, `lubet`, `uolnus`)
+const ARCHAIC_STEMS: [(&str, &str); 8] = [
+    ("optum", "optim"),
+    ("maxum", "maxim"),
+    ("proxum", "proxim"),
+    ("pessum", "pessim"),
+    ("minum", "minim"),
+    ("lub", "lib"),
+    ("uoln", "uuln"),
+    ("uort", "uert"),
+];
+
+// The archaic endings of a word, after a `u` (i.e. `equom`)
+// Note, `-uos` is left alone, since it is also the classical accusative plural
+const ARCHAIC_ENDINGS: [(&str, &str); 1] = [("uom", "uum")];
+
+/// Reduce a normalized form to the key of its family of variants
+/// ```
+/// use latin_db::latin_utilities::variants::variant_key;
+/// assert_eq!(variant_key("affero"), variant_key("adfero"));
+/// assert_eq!(variant_key("quom"), variant_key("cum"));
+/// ```
+pub fn variant_key(form: &str) -> String {
+    if let Some((_, classical)) = ARCHAIC_WORDS.iter().find(|(archaic, _)| *archaic == form) {
+        return classical.to_string();
+    }
+
+    let mut res = form.to_string();
+    if let Some((archaic, classical)) = ARCHAIC_STEMS.iter().find(|(s, _)| res.starts_with(s)) {
+        res = format!("{}{}", classical, &res[archaic.len()..]);
+    }
+
+    if let Some((archaic, classical)) = ARCHAIC_ENDINGS.iter().find(|(e, _)| res.ends_with(e)) {
+        res = format!("{}{}", &res[..res.len() - archaic.len()], classical);
+    }
+
+    if let Some((assimilated, prefix)) = ASSIMILATIONS.iter().find(|(a, _)| res.starts_with(a)) {
+        res = format!("{}{}", prefix, &res[assimilated.len()..]);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_families() {
+        let families = [
+            vec!["adfero", "affero"],
+            vec!["inpendium", "impendium"],
+            vec!["quom", "quum", "cum"],
+            vec!["conlega", "collega"],
+            vec!["optumus", "optimus"],
+            vec!["seruom", "seruum"],
+            vec!["uolt", "uult"],
+        ];
+
+        for family in families.iter() {
+            let key = variant_key(family[0]);
+            assert!(
+                family.iter().all(|form| variant_key(form) == key),
+                "{:?}",
+                family
+            );
+        }
+    }
+
+    #[test]
+    fn test_apart() {
+        assert_ne!(variant_key("fatuos"), variant_key("fatuus"));
+        assert_ne!(variant_key("adfero"), variant_key("infero"));
+        assert_eq!(variant_key("rosa"), "rosa");
+    }
+}
@@ -1,9 +1,11 @@
 pub mod arguments;
 pub mod authors_chrono;
+pub mod citations;
 pub mod filesystem;
 pub mod graphql_queries;
 pub mod latin_lemmatizer;
 pub mod latin_utilities;
 pub mod query_driver;
 pub mod query_system;
+pub mod tei;
 pub mod word_db;
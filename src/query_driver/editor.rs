@@ -0,0 +1,872 @@
+//! Changes to the corpus and to its metadata while the database is running
+//! Every change is applied to the database and written back to the files it was built from,
+//! so that it is still there the next time the database is built
+
+use super::{
+    add_to_work, default_work, overrides, remove_from_work, Configuration, LemmMode, MainDatabase,
+};
+use crate::authors_chrono::parsers::format_span;
+use crate::authors_chrono::{Author, TimeSpan, Work};
+use crate::filesystem::FileSystem;
+use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
+use crate::latin_utilities::{NormalizedLatinString, Normalizer, StandardLatinConverter};
+use crate::query_system::ids::*;
+use crate::query_system::traits::*;
+use crate::query_system::types::Lemma;
+
+use log::info;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The various ways a change can fail
+#[derive(Debug)]
+pub enum EditError {
+    /// No author with this name
+    UnknownAuthor(String),
+    /// An author with this name already exists
+    AuthorExists(String),
+    /// No source with this name
+    UnknownSource(String),
+    /// A source with this name already exists
+    SourceExists(String),
+    /// The name cannot be used for a file
+    InvalidName(String),
+    /// The time span has its end before its start
+    InvalidTimeSpan(i32, i32),
+    /// There is no authors file to record time spans in
+    NoAuthorsFile,
+    /// No word at this line and index of the source
+    UnknownOccurrence(usize, usize),
+    /// The lemma cannot be a reading of the word
+    InvalidLemma(String),
+    /// There is no file to record the chosen lemmas in
+    NoOverridesFile,
+    /// The lemmatizer is a treebank, which is not edited
+    ReadOnlyLemmatizer,
+    /// The files are listed in a manifest, rather than laid out in the folders of the authors
+    ManifestLayout,
+    /// The author has no folder of its own (i.e. it is named in the headers of its sources)
+    NoAuthorFolder(String),
+    /// Reading or writing the files failed
+    Io(io::Error),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::UnknownAuthor(name) => write!(f, "Unknown author {}", name),
+            EditError::AuthorExists(name) => write!(f, "Author {} already exists", name),
+            EditError::UnknownSource(name) => write!(f, "Unknown source {}", name),
+            EditError::SourceExists(name) => write!(f, "Source {} already exists", name),
+            EditError::InvalidName(name) => write!(f, "Invalid name {:?}", name),
+            EditError::InvalidTimeSpan(start, end) => {
+                write!(f, "Invalid time span from {} to {}", start, end)
+            }
+            EditError::NoAuthorsFile => write!(f, "No authors file was specified"),
+            EditError::UnknownOccurrence(line_no, index) => {
+                write!(f, "No word {} in line {}", index, line_no)
+            }
+            EditError::InvalidLemma(lemma) => write!(f, "{} is not a reading of the word", lemma),
+            EditError::NoOverridesFile => write!(f, "No overrides file was specified"),
+            EditError::ReadOnlyLemmatizer => write!(f, "The lemmatizer cannot be edited"),
+            EditError::ManifestLayout => {
+                write!(f, "The files of the corpus are listed in a manifest")
+            }
+            EditError::NoAuthorFolder(name) => write!(f, "Author {} has no folder", name),
+            EditError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+impl From<io::Error> for EditError {
+    fn from(err: io::Error) -> Self {
+        EditError::Io(err)
+    }
+}
+
+/// Build a time span from a start and an end year
+pub fn time_span(start: i32, end: i32) -> Result<TimeSpan, EditError> {
+    let year = |y| chrono::NaiveDate::from_ymd_opt(y, 1, 1);
+    match (year(start), year(end)) {
+        (Some(from), Some(to)) if start <= end => Ok(TimeSpan::new(from, to)),
+        _ => Err(EditError::InvalidTimeSpan(start, end)),
+    }
+}
+
+// Names become files and folders, so they must stay in their directory
+fn check_name(name: &str) -> Result<(), EditError> {
+    if name.trim().is_empty() || name.starts_with('.') || name.contains(&['/', '\\', '#'][..]) {
+        return Err(EditError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+// The line of an author in the authors file, i.e. `Publius Ovidius Naso #(1a, 1d)`
+fn author_line(author: &Author) -> String {
+    match author.tspan() {
+        Some(span) => format!("{} #{}", author.name(), format_span(span)),
+        None => format!("{} #?", author.name()),
+    }
+}
+
+// Rewrite a file line by line, dropping the lines mapped to None
+fn rewrite(path: &Path, map: impl Fn(&str) -> Option<String>) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let lines: Vec<_> = content.lines().filter_map(map).collect();
+
+    // Write to the side, so that a failure does not leave half a file
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, lines.join("\n") + "\n")?;
+    fs::rename(tmp_path, path)
+}
+
+fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let needs_newline = !matches!(fs::read(path)?.last(), None | Some(b'\n'));
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    if needs_newline {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", line)
+}
+
+/// Applies changes to the database, and to the files described by a configuration
+#[derive(Clone)]
+pub struct CorpusEditor {
+    config: Configuration,
+}
+
+impl CorpusEditor {
+    /// Instantiate an editor for the database built from this configuration
+    pub fn new(config: Configuration) -> Self {
+        CorpusEditor { config }
+    }
+
+    fn find_author(&self, db: &MainDatabase, name: &str) -> Result<AuthorId, EditError> {
+        db.authors()
+            .get_by_left(&Author::new(name))
+            .cloned()
+            .ok_or_else(|| EditError::UnknownAuthor(name.to_string()))
+    }
+
+    /// Find a source by the name of its author and of its file
+    pub fn find_source(
+        &self,
+        db: &MainDatabase,
+        author: &str,
+        name: &str,
+    ) -> Result<SourceId, EditError> {
+        let author = self.find_author(db, author)?;
+        db.associated_sources(author)
+            .iter()
+            .cloned()
+            .find(|id| {
+                db.sources().get_by_right(id).and_then(|p| p.file_name()) == Some(OsStr::new(name))
+            })
+            .ok_or_else(|| EditError::UnknownSource(name.to_string()))
+    }
+
+    fn author_dir(&self, name: &str) -> PathBuf {
+        self.config.data_dir.join(name)
+    }
+
+    // The folder of an existing author, where its files are moved to
+    fn author_folder(&self, name: &str) -> Result<PathBuf, EditError> {
+        let dir = self.author_dir(name);
+        if dir.is_dir() {
+            Ok(dir)
+        } else {
+            Err(EditError::NoAuthorFolder(name.to_string()))
+        }
+    }
+
+    // The files are only moved around when they are in the folders of their authors
+    fn check_layout(&self) -> Result<(), EditError> {
+        match self.config.manifest_path {
+            Some(_) => Err(EditError::ManifestLayout),
+            None => Ok(()),
+        }
+    }
+
+    // Write the description of an author, replacing the one under its old name
+    fn persist_author(&self, old_name: &str, author: &Author) -> Result<(), EditError> {
+        let authors_path = match &self.config.authors_path {
+            Some(path) => path,
+            None if author.tspan().is_some() => return Err(EditError::NoAuthorsFile),
+            // Note, the folders are enough to know the authors
+            None => return Ok(()),
+        };
+
+        let line = author_line(author);
+        let content = fs::read_to_string(authors_path)?;
+        let is_old =
+            |l: &str| !l.contains('~') && l.split('#').next().map(str::trim) == Some(old_name);
+        if content.lines().any(is_old) {
+            rewrite(authors_path, |l| {
+                Some(if is_old(l) {
+                    line.clone()
+                } else {
+                    l.to_string()
+                })
+            })?;
+        } else {
+            append_line(authors_path, &line)?;
+        }
+
+        // The dates of the works of the author follow its name
+        if old_name != author.name() {
+            let is_work = |l: &str| {
+                let mut names = l.split('#').next().unwrap_or("").splitn(2, '/');
+                !l.contains('~')
+                    && names.next().map(str::trim) == Some(old_name)
+                    && names.next().is_some()
+            };
+            rewrite(authors_path, |l| {
+                Some(match l.split_once('/').map(|(_, rest)| rest) {
+                    Some(rest) if is_work(l) => format!("{} /{}", author.name(), rest),
+                    _ => l.to_string(),
+                })
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Add an author, with no sources for now
+    pub fn add_author(
+        &self,
+        db: &mut MainDatabase,
+        name: &str,
+        span: Option<TimeSpan>,
+    ) -> Result<AuthorId, EditError> {
+        check_name(name)?;
+        if db.authors().contains_left(&Author::new(name)) {
+            return Err(EditError::AuthorExists(name.to_string()));
+        }
+
+        let author = match span {
+            Some(span) => Author::new_with_tspan(name, span),
+            None => Author::new(name),
+        };
+
+        self.persist_author(name, &author)?;
+        fs::create_dir_all(self.author_dir(name))?;
+
+        info!("Added author {}", name);
+        let id = db.intern_author(author);
+        db.set_associated_sources(id, Arc::new(HashSet::new()));
+        Ok(id)
+    }
+
+    /// Give another name to an author, moving its folder
+    pub fn rename_author(
+        &self,
+        db: &mut MainDatabase,
+        name: &str,
+        new_name: &str,
+    ) -> Result<AuthorId, EditError> {
+        self.check_layout()?;
+        check_name(new_name)?;
+        let id = self.find_author(db, name)?;
+        if db.authors().contains_left(&Author::new(new_name)) {
+            return Err(EditError::AuthorExists(new_name.to_string()));
+        }
+        let old_dir = self.author_folder(name)?;
+
+        let author = match db.lookup_intern_author(id).tspan() {
+            Some(span) => Author::new_with_tspan(new_name, span.clone()),
+            None => Author::new(new_name),
+        };
+
+        self.persist_author(name, &author)?;
+        let new_dir = self.author_dir(new_name);
+        fs::rename(old_dir, &new_dir)?;
+
+        info!("Renamed author {} to {}", name, new_name);
+        for &source in db.associated_sources(id).iter() {
+            let file_name = db
+                .sources()
+                .get_by_right(&source)
+                .and_then(|p| p.file_name());
+            if let Some(file_name) = file_name {
+                let path = new_dir.join(file_name);
+                db.fs.relocate_source(source, path);
+            }
+        }
+
+        db.authors.remove_by_right(&id);
+        db.authors.insert(author, id);
+        self.persist_overrides(db)?;
+        Ok(id)
+    }
+
+    /// Set (or clear) when an author was active
+    pub fn set_time_span(
+        &self,
+        db: &mut MainDatabase,
+        name: &str,
+        span: Option<TimeSpan>,
+    ) -> Result<AuthorId, EditError> {
+        let id = self.find_author(db, name)?;
+        let author = match span {
+            Some(span) => Author::new_with_tspan(name, span),
+            None => Author::new(name),
+        };
+
+        self.persist_author(name, &author)?;
+
+        db.authors.remove_by_right(&id);
+        db.authors.insert(author, id);
+        Ok(id)
+    }
+
+    /// Move a source to the folder of another author
+    pub fn move_source(
+        &self,
+        db: &mut MainDatabase,
+        source: SourceId,
+        to: &str,
+    ) -> Result<SourceId, EditError> {
+        self.check_layout()?;
+        let to = self.find_author(db, to)?;
+        let from = db.associated_author(source);
+        if from == to {
+            return Ok(source);
+        }
+
+        let old_path = db.sources().get_by_right(&source).cloned().unwrap();
+        let file_name = old_path.file_name().unwrap();
+        let new_path = self
+            .author_folder(db.lookup_intern_author(to).name())?
+            .join(file_name);
+        if new_path.exists() {
+            return Err(EditError::SourceExists(
+                file_name.to_string_lossy().to_string(),
+            ));
+        }
+
+        fs::rename(&old_path, &new_path)?;
+        info!("Moved {:?} to {:?}", old_path, new_path);
+        db.fs.relocate_source(source, new_path);
+
+        let mut old_sources = (*db.associated_sources(from)).clone();
+        old_sources.remove(&source);
+        db.set_associated_sources(from, Arc::new(old_sources));
+
+        let mut new_sources = (*db.associated_sources(to)).clone();
+        new_sources.insert(source);
+        db.set_associated_sources(to, Arc::new(new_sources));
+        db.set_associated_author(source, to);
+
+        // Note, the source is now part of a work of the same title by the new author
+        let work = remove_from_work(db, source);
+        let moved = Work::new(to, work.title());
+        let moved = match work.tspan() {
+            Some(span) => moved.with_tspan(span.clone()),
+            None => moved,
+        };
+        let moved = match work.genre() {
+            Some(genre) => moved.with_genre(genre),
+            None => moved,
+        };
+        add_to_work(db, source, moved);
+        self.persist_overrides(db)?;
+
+        Ok(source)
+    }
+
+    /// Add a new source to an author
+    pub fn upload_source(
+        &self,
+        db: &mut MainDatabase,
+        author: &str,
+        name: &str,
+        text: &str,
+    ) -> Result<SourceId, EditError> {
+        self.check_layout()?;
+        check_name(name)?;
+        let author_id = self.find_author(db, author)?;
+        let path = self.author_folder(author)?.join(name);
+        if path.exists() {
+            return Err(EditError::SourceExists(name.to_string()));
+        }
+
+        fs::write(&path, text)?;
+        info!("Uploaded {:?}", path);
+
+        let work = default_work(author_id, &path);
+        let id = db.intern_source(path);
+        let mut sources = (*db.associated_sources(author_id)).clone();
+        sources.insert(id);
+        db.set_associated_sources(author_id, Arc::new(sources));
+        db.set_associated_author(id, author_id);
+        add_to_work(db, id, work);
+
+        Ok(id)
+    }
+
+    // Write the chosen lemmas again, i.e. after the paths of the sources changed
+    fn persist_overrides(&self, db: &MainDatabase) -> io::Result<()> {
+        match &self.config.overrides_path {
+            Some(path) if !db.overrides().is_empty() => {
+                overrides::save(db, path, &self.config.data_dir)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Choose the lemma of a single occurrence of a word (or forget it, if None),
+    /// returning false if nothing changed
+    pub fn set_override(
+        &self,
+        db: &mut MainDatabase,
+        source: SourceId,
+        line_no: usize,
+        index: usize,
+        lemma: Option<&str>,
+    ) -> Result<bool, EditError> {
+        let path = self
+            .config
+            .overrides_path
+            .as_ref()
+            .ok_or(EditError::NoOverridesFile)?;
+        let fd = db
+            .source_forms(source)
+            .iter()
+            .find(|fd| fd.line_no() == line_no && fd.index() == index)
+            .cloned()
+            .ok_or(EditError::UnknownOccurrence(line_no, index))?;
+
+        let lemma = match lemma {
+            Some(lemma) => {
+                let converter = db.normalization().converter();
+                let id = db
+                    .lookup_interned_word(converter.convert(lemma))
+                    .filter(|id| {
+                        db.source_lemmatizer(source)
+                            .get_possible_lemmas(fd.form().0)
+                            .contains(id)
+                    })
+                    .ok_or_else(|| EditError::InvalidLemma(lemma.to_string()))?;
+                Some(Lemma(id))
+            }
+            None => None,
+        };
+
+        let old = db.overrides();
+        let mut overrides = (*old).clone();
+        if !overrides.set(source, line_no, index, lemma) {
+            return Ok(false);
+        }
+
+        db.set_overrides(Arc::new(overrides));
+        if let Err(err) = overrides::save(db, path, &self.config.data_dir) {
+            db.set_overrides(old);
+            return Err(err.into());
+        }
+
+        info!(
+            "Chose {:?} for word {} in line {} of {:?}",
+            lemma.map(|l| db.lookup_word_str(l.0).to_string()),
+            index,
+            line_no,
+            source
+        );
+        Ok(true)
+    }
+
+    // The lemmatizer file, with a form -> lemma pair either added or removed
+    fn persist_lemma(
+        &self,
+        form: &NormalizedLatinString,
+        lemma: &NormalizedLatinString,
+        add: bool,
+    ) -> io::Result<()> {
+        let path = &self.config.lemmatizer_path;
+        let converter =
+            StandardLatinConverter::default().with_profile(self.config.normalization.clone());
+        let is = |s: &str, word: &NormalizedLatinString| converter.convert(s) == *word;
+
+        match (&self.config.lemm_mode, add) {
+            (LemmMode::CSVFormat, true) => append_line(
+                path,
+                &format!("{},{},{}", form.inner(), form.inner(), lemma.inner()),
+            ),
+            (LemmMode::LemlatFormat, true) => {
+                append_line(path, &format!("{}\t\t{}", lemma.inner(), form.inner()))
+            }
+            (LemmMode::CSVFormat, false) => rewrite(path, |line| {
+                let segments: Vec<_> = line.split(',').collect();
+                let pair = segments.len() >= 3 && is(segments[0], form) && is(segments[2], lemma);
+                if pair {
+                    None
+                } else {
+                    Some(line.to_string())
+                }
+            }),
+            (LemmMode::ConlluFormat, _) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Treebanks are not edited",
+            )),
+            (LemmMode::LemlatFormat, false) => rewrite(path, |line| {
+                let fields: Vec<_> = line.split('\t').collect();
+                if fields.len() < 2 || !is(fields[0], lemma) {
+                    return Some(line.to_string());
+                }
+
+                // Drop the records of the form, keeping the lemma and its id
+                let kept: Vec<_> =
+                    fields[..2]
+                        .iter()
+                        .chain(fields[2..].iter().filter(|record| {
+                            !is(record.split(' ').next().unwrap_or_default(), form)
+                        }))
+                        .cloned()
+                        .collect();
+                Some(kept.join("\t"))
+            }),
+        }
+    }
+
+    // Set the lemmatizer to a new mapping
+    fn update_lemmatizer(
+        &self,
+        db: &mut MainDatabase,
+        form: &str,
+        lemma: &str,
+        add: bool,
+    ) -> Result<bool, EditError> {
+        if self.config.lemm_mode == LemmMode::ConlluFormat {
+            return Err(EditError::ReadOnlyLemmatizer);
+        }
+
+        let converter = db.normalization().converter();
+        for word in &[form, lemma] {
+            if converter.convert(word).inner().is_empty() {
+                return Err(EditError::InvalidName(word.to_string()));
+            }
+        }
+        let (form, lemma) = (converter.convert(form), converter.convert(lemma));
+
+        // Note, the pair is also changed in the lemmatizers in the spelling of the authors
+        // with rules of their own
+        let scoped = db.scoped_lemmatizers();
+        let mut targets = vec![(None, db.lemmatizer(), form.clone())];
+        for (profile, lemm) in scoped.profiles() {
+            let variant = NormalizedLatinString::instantiate(profile.normalize(form.inner()));
+            targets.push((Some(profile.clone()), lemm.clone(), variant));
+        }
+
+        // Note, an unknown word cannot be part of a pair to remove
+        let mut lemma_id = db.lookup_interned_str(lemma.inner());
+        if lemma_id.is_none() && !add {
+            return Ok(false);
+        }
+
+        let mut new_words = false;
+        let mut updated = Vec::new();
+        for (profile, lemm, form) in targets {
+            let form_id = db.lookup_interned_str(form.inner());
+            let known = match (form_id, lemma_id) {
+                (Some(form_id), Some(lemma_id)) => lemm
+                    .get_possible_lemmas(form_id)
+                    .is_some_and(|lemmas| lemmas.contains(&lemma_id)),
+                _ => false,
+            };
+            if known == add {
+                continue;
+            }
+
+            // Note, the words are only interned once a new pair is added
+            new_words |= form_id.is_none() || lemma_id.is_none();
+            let form_id = form_id.unwrap_or_else(|| db.intern_word(form));
+            let lemma_id = *lemma_id.get_or_insert_with(|| db.intern_word(lemma.clone()));
+
+            let mut mapping = lemm.mapping().clone();
+            if add {
+                mapping.entry(form_id).or_default().insert(lemma_id);
+            } else if let Some(lemmas) = mapping.get_mut(&form_id) {
+                lemmas.remove(&lemma_id);
+                if lemmas.is_empty() {
+                    mapping.remove(&form_id);
+                }
+            }
+
+            // Note, the analyses of a removed pair are dropped with it
+            let analyses = lemm.analyses().clone();
+            let lemm = CompressedLemmatizer::from_mapping_with_analyses(mapping, analyses);
+            updated.push((profile, Arc::new(lemm)));
+        }
+
+        if updated.is_empty() {
+            return Ok(false);
+        }
+
+        self.persist_lemma(&form, &lemma, add)?;
+        info!(
+            "{} {} -> {}",
+            if add { "Added" } else { "Removed" },
+            form.inner(),
+            lemma.inner()
+        );
+
+        // The sources might contain the new words, so they have to be parsed again
+        if new_words {
+            db.preparsed = Arc::new(Default::default());
+            let version = db.vocabulary_version();
+            db.set_vocabulary_version(version + 1);
+        }
+
+        let mut scoped = (*scoped).clone();
+        for (profile, lemm) in updated {
+            match profile {
+                Some(profile) => scoped = scoped.with_profile(profile, lemm),
+                None => db.set_lemmatizer(lemm),
+            }
+        }
+        db.set_scoped_lemmatizers(Arc::new(scoped));
+        Ok(true)
+    }
+
+    /// Make a form a possible reading of a lemma, returning false if it already was
+    pub fn add_lemma(
+        &self,
+        db: &mut MainDatabase,
+        form: &str,
+        lemma: &str,
+    ) -> Result<bool, EditError> {
+        self.update_lemmatizer(db, form, lemma, true)
+    }
+
+    /// Stop reading a form as a lemma, returning false if it already was not
+    pub fn remove_lemma(
+        &self,
+        db: &mut MainDatabase,
+        form: &str,
+        lemma: &str,
+    ) -> Result<bool, EditError> {
+        self.update_lemmatizer(db, form, lemma, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::driver_init;
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+    use crate::query_system::lit_subset::LitSubset;
+    use crate::query_system::types::Lemma;
+    use chrono::Datelike;
+    use salsa::ParallelDatabase;
+
+    // A small corpus, with an authors file
+    fn make_corpus(name: &str) -> TestCorpus {
+        TestCorpus::new(name)
+            .with_lemmatizer("arma,arma,arma\nuirum,uirum,uir\ncano,cano,cano")
+            .with_authors("Vergilius #(1a)\nVergilius / aeneid #(1a)\n")
+            .with_source("Vergilius/aeneid.txt", "arma uirumque cano\ntroiae")
+    }
+
+    fn count(db: &MainDatabase, lemma: &str) -> usize {
+        let lemma = Lemma(db.lookup_interned_word(lemma.into()).unwrap());
+        let all = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
+        db.lemma_occurrences_subset(lemma, all).len()
+    }
+
+    fn span(db: &MainDatabase, name: &str) -> Option<(i32, i32)> {
+        db.authors()
+            .get_by_left(&Author::new(name))
+            .map(|&id| db.lookup_intern_author(id))
+            .and_then(|a| a.tspan().map(|s| (s.start().year(), s.end().year())))
+    }
+
+    #[test]
+    fn test_authors() {
+        let corpus = make_corpus("edit_authors");
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let editor = CorpusEditor::new(config.clone());
+
+        let ovid = editor
+            .add_author(&mut db, "Ovidius", Some(time_span(-100, 100).unwrap()))
+            .unwrap();
+        assert!(editor.add_author(&mut db, "Ovidius", None).is_err());
+        assert!(editor.add_author(&mut db, "../Ovidius", None).is_err());
+        assert!(time_span(100, -100).is_err());
+        assert!(time_span(-100, i32::MAX).is_err());
+        assert!(db.associated_sources(ovid).is_empty());
+
+        editor.rename_author(&mut db, "Vergilius", "Maro").unwrap();
+        editor.set_time_span(&mut db, "Maro", None).unwrap();
+        assert!(editor.find_source(&db, "Maro", "aeneid.txt").is_ok());
+        assert_eq!(count(&db, "arma"), 1);
+
+        // Rebuild from the files
+        editor
+            .upload_source(&mut db, "Ovidius", "amores.txt", "arma")
+            .unwrap();
+        let db = driver_init(config).unwrap();
+        assert_eq!(span(&db, "Ovidius"), Some((-100, 100)));
+        assert_eq!(span(&db, "Maro"), None);
+        let maro = *db.authors().get_by_left(&Author::new("Maro")).unwrap();
+        let aeneid = db.works().get_by_left(&Work::new(maro, "aeneid")).unwrap();
+        let aeneid = db.lookup_intern_work(*aeneid);
+        assert_eq!(aeneid.tspan().unwrap().start().year(), -100);
+        assert!(db
+            .authors()
+            .get_by_left(&Author::new("Vergilius"))
+            .is_none());
+        assert_eq!(count(&db, "arma"), 2);
+    }
+
+    #[test]
+    fn test_sources() {
+        let corpus = make_corpus("edit_sources");
+        let (dir, config) = (corpus.dir(), corpus.config());
+        let mut db = driver_init(config.clone()).unwrap();
+        let editor = CorpusEditor::new(config.clone());
+
+        let ovid = editor.add_author(&mut db, "Ovidius", None).unwrap();
+        let amores = editor
+            .upload_source(&mut db, "Ovidius", "amores.txt", "arma cano")
+            .unwrap();
+        assert!(editor
+            .upload_source(&mut db, "Ovidius", "amores.txt", "")
+            .is_err());
+        assert_eq!(db.associated_author(amores), ovid);
+        assert_eq!(count(&db, "cano"), 2);
+
+        let aeneid = editor.find_source(&db, "Vergilius", "aeneid.txt").unwrap();
+        editor.move_source(&mut db, aeneid, "Ovidius").unwrap();
+        assert_eq!(db.associated_sources(ovid).len(), 2);
+        assert_eq!(db.associated_author(aeneid), ovid);
+        // Note, the work keeps its date
+        let work = db.lookup_intern_work(db.associated_work(aeneid));
+        assert_eq!(work.author(), ovid);
+        assert!(work.tspan().is_some());
+        assert!(dir
+            .join("corpus")
+            .join("Ovidius")
+            .join("aeneid.txt")
+            .exists());
+        assert_eq!(
+            db.source_text(aeneid).as_str(),
+            "arma uirumque cano\ntroiae"
+        );
+
+        // The files listed in a manifest stay where they are
+        let editor = CorpusEditor::new(config.with_manifest(dir.join("manifest.toml")));
+        assert!(matches!(
+            editor.move_source(&mut db, aeneid, "Vergilius"),
+            Err(EditError::ManifestLayout)
+        ));
+        assert!(matches!(
+            editor.upload_source(&mut db, "Ovidius", "tristia.txt", ""),
+            Err(EditError::ManifestLayout)
+        ));
+    }
+
+    #[test]
+    fn test_header_authors() {
+        let corpus = TestCorpus::new("edit_header_authors")
+            .with_lemmatizer("arma,arma,arma")
+            .with_source(
+                "perseus/aeneid.xml",
+                r#"<TEI><teiHeader><titleStmt><author>P. Vergilius Maro</author></titleStmt>
+                <date notBefore="-0029" notAfter="-0019"/></teiHeader>
+                <text><body><l>arma</l></body></text></TEI>"#,
+            )
+            .with_source("perseus/fragment.txt", "arma");
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let editor = CorpusEditor::new(config);
+
+        // The TEI source is given to the author of its header, the other one to its folder
+        assert_eq!(span(&db, "P. Vergilius Maro"), Some((-29, -19)));
+        assert!(editor.find_source(&db, "perseus", "fragment.txt").is_ok());
+
+        // The author has no folder to rename or to move files to
+        assert!(matches!(
+            editor.rename_author(&mut db, "P. Vergilius Maro", "Maro"),
+            Err(EditError::NoAuthorFolder(_))
+        ));
+        let fragment = editor.find_source(&db, "perseus", "fragment.txt").unwrap();
+        assert!(matches!(
+            editor.move_source(&mut db, fragment, "P. Vergilius Maro"),
+            Err(EditError::NoAuthorFolder(_))
+        ));
+    }
+
+    #[test]
+    fn test_lemmas() {
+        let corpus = make_corpus("edit_lemmas");
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let editor = CorpusEditor::new(config.clone());
+        assert_eq!(count(&db, "arma"), 1);
+
+        // A new word, which is now found in the sources
+        assert!(editor.add_lemma(&mut db, "troiae", "troia").unwrap());
+        assert!(!editor.add_lemma(&mut db, "troiae", "troia").unwrap());
+        assert_eq!(count(&db, "troia"), 1);
+
+        assert!(editor.remove_lemma(&mut db, "arma", "arma").unwrap());
+        assert_eq!(count(&db, "arma"), 0);
+
+        // Removing a pair of unknown words leaves the vocabulary alone
+        assert!(!editor.remove_lemma(&mut db, "graecia", "graecus").unwrap());
+        assert!(db.lookup_interned_word("graecus".into()).is_none());
+
+        let db = driver_init(config).unwrap();
+        assert_eq!(count(&db, "troia"), 1);
+        assert!(db.lookup_interned_word("arma".into()).is_none());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let corpus = make_corpus("edit_overrides");
+        let (dir, config) = (corpus.dir(), corpus.config());
+        let mut db = driver_init(config.clone()).unwrap();
+        let aeneid = CorpusEditor::new(config.clone())
+            .find_source(&db, "Vergilius", "aeneid.txt")
+            .unwrap();
+        assert!(CorpusEditor::new(config.clone())
+            .set_override(&mut db, aeneid, 0, 2, Some("cano"))
+            .is_err());
+
+        let config = config.with_overrides(dir.join("overrides.tsv"));
+        let editor = CorpusEditor::new(config.clone());
+        editor.add_lemma(&mut db, "cano", "canus").unwrap();
+        assert_eq!((count(&db, "cano"), count(&db, "canus")), (1, 1));
+
+        assert!(editor
+            .set_override(&mut db, aeneid, 0, 2, Some("cano"))
+            .unwrap());
+        assert!(!editor
+            .set_override(&mut db, aeneid, 0, 2, Some("cano"))
+            .unwrap());
+        assert!(editor
+            .set_override(&mut db, aeneid, 0, 2, Some("arma"))
+            .is_err());
+        assert!(editor
+            .set_override(&mut db, aeneid, 5, 0, Some("cano"))
+            .is_err());
+        assert_eq!((count(&db, "cano"), count(&db, "canus")), (1, 0));
+
+        // The chosen lemma follows the source
+        editor.add_author(&mut db, "Ovidius", None).unwrap();
+        editor.move_source(&mut db, aeneid, "Ovidius").unwrap();
+        let mut db = driver_init(config.clone()).unwrap();
+        assert_eq!((count(&db, "cano"), count(&db, "canus")), (1, 0));
+
+        let aeneid = editor.find_source(&db, "Ovidius", "aeneid.txt").unwrap();
+        assert!(editor.set_override(&mut db, aeneid, 0, 2, None).unwrap());
+        assert_eq!((count(&db, "cano"), count(&db, "canus")), (1, 1));
+    }
+}
@@ -0,0 +1,579 @@
+//! A manifest listing the works of the corpus, as an alternative to the layout of the data directory
+//! Each work has its author, title, genre, date, language register and the files it is made of
+//! (relative to the data directory), each with an optional file of references. In TOML:
+//! ```toml
+//! [[work]]
+//! author = "Vergilius"
+//! title = "Aeneis"
+//! genre = "epic"
+//! register = "poetry"
+//! date = { from = -29, to = -19 }
+//! files = ["epic/aeneis_1.txt", { path = "epic/aeneis_2.txt", citations = "epic/aen_2.cit" }]
+//! ```
+//! The authors whose spelling needs more normalization than the corpus are listed by name:
+//! ```toml
+//! [normalization]
+//! "Gregorius Turonensis" = "medieval"
+//! ```
+//! The same structure is read from JSON, if the manifest ends in `.json`
+
+use super::MainDatabase;
+use crate::authors_chrono::{TimeSpan, Work};
+use crate::filesystem::citations_path;
+use crate::latin_utilities::Profile;
+use crate::query_system::ids::AuthorId;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// The ways reading a manifest can fail
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// A work is dated to a year out of the range of the calendar
+    InvalidYear(String, i32),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestError::Io(err) => write!(f, "{}", err),
+            ManifestError::Toml(err) => write!(f, "Invalid manifest: {}", err),
+            ManifestError::Json(err) => write!(f, "Invalid manifest: {}", err),
+            ManifestError::InvalidYear(title, year) => {
+                write!(
+                    f,
+                    "Invalid manifest: work {} is dated to year {}",
+                    title, year
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl From<io::Error> for ManifestError {
+    fn from(err: io::Error) -> Self {
+        ManifestError::Io(err)
+    }
+}
+
+/// When a work was written, in years (negative before Christ)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Dating {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// A file of a work, with the file of its references if it is not the usual sidecar
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WorkFile {
+    Path(PathBuf),
+    WithCitations { path: PathBuf, citations: PathBuf },
+}
+
+impl WorkFile {
+    pub fn path(&self) -> &Path {
+        match self {
+            WorkFile::Path(path) => path,
+            WorkFile::WithCitations { path, .. } => path,
+        }
+    }
+
+    pub fn citations(&self) -> Option<&Path> {
+        match self {
+            WorkFile::Path(_) => None,
+            WorkFile::WithCitations { citations, .. } => Some(citations),
+        }
+    }
+
+    /// Get the file with the references, either the given one or the usual sidecar
+    pub fn citations_path(&self, data_dir: &Path) -> PathBuf {
+        match self.citations() {
+            Some(citations) => data_dir.join(citations),
+            None => citations_path(&data_dir.join(self.path())),
+        }
+    }
+}
+
+/// A work, as described in the manifest
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WorkEntry {
+    author: String,
+    title: String,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    register: Option<String>,
+    #[serde(default)]
+    date: Option<Dating>,
+    #[serde(default)]
+    files: Vec<WorkFile>,
+}
+
+impl WorkEntry {
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    /// The language register (i.e. poetry, prose, letters)
+    pub fn register(&self) -> Option<&str> {
+        self.register.as_deref()
+    }
+
+    /// When the work was written, if it is known (and valid)
+    pub fn time_span(&self) -> Option<TimeSpan> {
+        let date = self.date?;
+        if date.from > date.to {
+            return None;
+        }
+
+        let year = |y| NaiveDate::from_ymd_opt(y, 1, 1);
+        Some(TimeSpan::new(year(date.from)?, year(date.to)?))
+    }
+
+    pub fn files(&self) -> &[WorkFile] {
+        &self.files
+    }
+
+    /// The work described by the entry, once its author is interned
+    pub fn work(&self, author: AuthorId) -> Work {
+        let work = Work::new(author, &self.title);
+        let work = match self.time_span() {
+            Some(span) => work.with_tspan(span),
+            None => work,
+        };
+        match &self.genre {
+            Some(genre) => work.with_genre(genre),
+            None => work,
+        }
+    }
+}
+
+/// A discrepancy between the manifest and the corpus
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Mismatch {
+    /// A file of a work is not there
+    MissingFile(PathBuf),
+    /// A file in the data directory is not part of any work
+    UnlistedFile(PathBuf),
+    /// A file is part of more than one work
+    SharedFile(PathBuf),
+    /// A work has no files
+    EmptyWork(String),
+    /// The date of a work has its end before its start
+    InvalidDate(String),
+    /// An author of the manifest is not in the authors file
+    UnknownAuthor(String),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::MissingFile(path) => write!(f, "{:?} is in the manifest but not found", path),
+            Mismatch::UnlistedFile(path) => write!(f, "{:?} is not in the manifest", path),
+            Mismatch::SharedFile(path) => write!(f, "{:?} is part of several works", path),
+            Mismatch::EmptyWork(title) => write!(f, "Work {} has no files", title),
+            Mismatch::InvalidDate(title) => write!(f, "Work {} ends before it starts", title),
+            Mismatch::UnknownAuthor(name) => {
+                write!(f, "Author {} is not in the authors file", name)
+            }
+        }
+    }
+}
+
+/// The works of the corpus
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default, rename = "work")]
+    works: Vec<WorkEntry>,
+    #[serde(default)]
+    normalization: BTreeMap<String, Profile>,
+}
+
+impl Manifest {
+    /// Read a manifest, in JSON if the file ends in `.json` and in TOML otherwise
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_toml(&content)
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ManifestError> {
+        let manifest: Self = toml::from_str(content).map_err(ManifestError::Toml)?;
+        manifest.check_years()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, ManifestError> {
+        let manifest: Self = serde_json::from_str(content).map_err(ManifestError::Json)?;
+        manifest.check_years()
+    }
+
+    // The years must be dates of the calendar, the order of the ends of a span is checked later
+    fn check_years(self) -> Result<Self, ManifestError> {
+        for work in &self.works {
+            let date = match work.date {
+                Some(date) => date,
+                None => continue,
+            };
+            for &year in &[date.from, date.to] {
+                if NaiveDate::from_ymd_opt(year, 1, 1).is_none() {
+                    return Err(ManifestError::InvalidYear(work.title.clone(), year));
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn works(&self) -> &[WorkEntry] {
+        &self.works
+    }
+
+    /// The normalization rules of the authors that have some, by name
+    pub fn author_profiles(&self) -> &BTreeMap<String, Profile> {
+        &self.normalization
+    }
+
+    /// The files of the works, with the work they belong to, in order
+    /// Note, a file shared by several works belongs to the first one
+    pub fn sources(&self) -> impl Iterator<Item = (&WorkEntry, &WorkFile)> {
+        let mut seen = HashSet::new();
+        self.works
+            .iter()
+            .flat_map(|work| work.files.iter().map(move |file| (work, file)))
+            .filter(move |(_, file)| seen.insert(file.path()))
+    }
+
+    /// Compare the manifest with the content of the data directory
+    /// The files that are not part of the corpus (i.e. the lemmatizer) can be excluded
+    pub fn check(&self, data_dir: &Path, excluded: &[PathBuf]) -> Vec<Mismatch> {
+        let mut res = Vec::new();
+        let mut known: HashSet<PathBuf> = excluded.iter().cloned().collect();
+
+        for work in &self.works {
+            if work.files.is_empty() {
+                res.push(Mismatch::EmptyWork(work.title.clone()));
+            }
+            if work.date.is_some() && work.time_span().is_none() {
+                res.push(Mismatch::InvalidDate(work.title.clone()));
+            }
+
+            for file in &work.files {
+                let path = data_dir.join(file.path());
+                if !path.is_file() {
+                    res.push(Mismatch::MissingFile(path.clone()));
+                }
+                // Note, an unused sidecar is still not reported
+                known.insert(citations_path(&path));
+                known.insert(file.citations_path(data_dir));
+                if !known.insert(path.clone()) {
+                    res.push(Mismatch::SharedFile(path));
+                }
+            }
+        }
+
+        for entry in WalkDir::new(data_dir)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            if entry.file_type().is_file() && !known.contains(entry.path()) {
+                res.push(Mismatch::UnlistedFile(entry.path().to_path_buf()));
+            }
+        }
+
+        res
+    }
+
+    /// The time span of each author, from the dates of their works
+    pub fn author_spans(&self) -> HashMap<&str, TimeSpan> {
+        let mut res: HashMap<&str, TimeSpan> = HashMap::new();
+        for work in &self.works {
+            let span = match work.time_span() {
+                Some(span) => span,
+                None => continue,
+            };
+
+            let span = match res.get(work.author()) {
+                Some(other) => TimeSpan::new(
+                    *span.start().min(other.start()),
+                    *span.end().max(other.end()),
+                ),
+                None => span,
+            };
+            res.insert(work.author(), span);
+        }
+        res
+    }
+}
+
+/// Record in the database which work each source belongs to, and where its references are
+pub(super) fn attach(db: &mut MainDatabase, manifest: &Manifest, data_dir: &Path) {
+    let mut entries = HashMap::new();
+    for work in &manifest.works {
+        // Note, the work is shared by its sources
+        let shared = Arc::new(work.clone());
+        for file in &work.files {
+            let id = match db.sources().get_by_left(&data_dir.join(file.path())) {
+                Some(&id) if !entries.contains_key(&id) => id,
+                _ => continue,
+            };
+
+            db.fs.set_citations_path(id, file.citations_path(data_dir));
+            entries.insert(id, shared.clone());
+        }
+    }
+
+    db.manifest_entries = Arc::new(entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+    use chrono::Datelike;
+
+    const MANIFEST: &str = r#"
+        [[work]]
+        author = "Vergilius"
+        title = "Aeneis"
+        genre = "epic"
+        register = "poetry"
+        date = { from = -29, to = -19 }
+        files = ["epic/aeneis_1.txt", { path = "epic/aeneis_2.txt", citations = "aen.cit" }]
+
+        [[work]]
+        author = "Vergilius"
+        title = "Georgica"
+        date = { from = -37, to = -29 }
+        files = ["georgica.txt"]
+
+        [[work]]
+        author = "Ennius"
+        title = "Annales"
+        date = { from = -169, to = -200 }
+
+        [normalization]
+        Ennius = "epigraphic"
+    "#;
+
+    #[test]
+    fn test_formats() {
+        let manifest = Manifest::from_toml(MANIFEST).unwrap();
+        assert_eq!(manifest.works().len(), 3);
+
+        let aeneid = &manifest.works()[0];
+        assert_eq!(aeneid.genre(), Some("epic"));
+        assert_eq!(aeneid.register(), Some("poetry"));
+        assert_eq!(aeneid.files()[1].citations(), Some(Path::new("aen.cit")));
+        assert_eq!(manifest.works()[2].time_span(), None);
+        assert_eq!(manifest.author_profiles()["Ennius"], Profile::epigraphic());
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+
+        let spans = manifest.author_spans();
+        let vergil = &spans["Vergilius"];
+        assert_eq!(vergil.start().year(), -37);
+        assert_eq!(vergil.end().year(), -19);
+        assert!(!spans.contains_key("Ennius"));
+
+        let invalid = MANIFEST.replace("to = -19", "to = 1000000");
+        match Manifest::from_toml(&invalid) {
+            Err(ManifestError::InvalidYear(title, 1000000)) => assert_eq!(title, "Aeneis"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let mut corpus = TestCorpus::new("manifest");
+        for file in &[
+            "epic/aeneis_1.txt",
+            "epic/aeneis_1.cit",
+            "aen.cit",
+            "georgica.txt",
+            "epic/bucolica.txt",
+        ] {
+            corpus = corpus.with_source(file, "");
+        }
+        let corpus = corpus.with_source("manifest.toml", MANIFEST);
+        let dir = corpus.dir().join("corpus");
+
+        let manifest = Manifest::load(&dir.join("manifest.toml")).unwrap();
+        let mismatches = manifest.check(&dir, &[dir.join("manifest.toml")]);
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch::MissingFile(dir.join("epic/aeneis_2.txt")),
+                Mismatch::EmptyWork("Annales".to_string()),
+                Mismatch::InvalidDate("Annales".to_string()),
+                Mismatch::UnlistedFile(dir.join("epic").join("bucolica.txt")),
+            ]
+        );
+
+        let sources: Vec<_> = manifest
+            .sources()
+            .map(|(w, f)| (w.title(), f.path()))
+            .collect();
+        assert_eq!(sources.len(), 3);
+        assert_eq!(sources[2], ("Georgica", Path::new("georgica.txt")));
+        assert_eq!(
+            manifest.works()[0].files()[1].citations_path(&dir),
+            dir.join("aen.cit")
+        );
+    }
+
+    #[test]
+    fn test_driver() {
+        use super::super::driver_init;
+        use crate::query_system::lit_subset::LitSubset;
+        use crate::query_system::traits::*;
+        use salsa::ParallelDatabase;
+
+        // Note, the works are nested deeper than the author folders would be
+        let corpus = TestCorpus::new("driver")
+            .with_lemmatizer("arma,arma,arma\ncano,cano,cano\n")
+            .with_source("epic/aeneis/1.txt", "arma cano\ncano")
+            .with_source("epic/aeneis/2.txt", "cano")
+            .with_source("aen.cit", "1\tAen. 2.1+\n")
+            .with_file(
+                "manifest.json",
+                r#"{"work": [{
+                "author": "Vergilius",
+                "title": "Aeneis",
+                "date": {"from": -29, "to": -19},
+                "files": ["epic/aeneis/1.txt", {"path": "epic/aeneis/2.txt", "citations": "aen.cit"}]
+            }]}"#,
+            );
+        let dir = corpus.dir();
+
+        let config = corpus.config().with_manifest(dir.join("manifest.json"));
+        let db = driver_init(config).unwrap();
+
+        let (author, &author_id) = db.authors().iter().next().unwrap();
+        assert_eq!(db.authors().len(), 1);
+        assert_eq!(author.name(), "Vergilius");
+        assert_eq!(author.tspan().unwrap().start().year(), -29);
+        assert_eq!(db.associated_sources(author_id).len(), 2);
+
+        let second = db
+            .sources()
+            .get_by_left(&dir.join("corpus/epic/aeneis/2.txt"));
+        let second = *second.unwrap();
+        assert_eq!(db.manifest_entry(second).unwrap().title(), "Aeneis");
+        assert_eq!(db.citation(second, 0), Some("Aen. 2.1".to_string()));
+
+        // Both of the files make up a single work
+        let (work, &work_id) = db.works().iter().next().unwrap();
+        assert_eq!(db.works().len(), 1);
+        assert_eq!(work.title(), "Aeneis");
+        assert_eq!(work.author(), author_id);
+        assert_eq!(work.tspan().unwrap().end().year(), -19);
+        assert_eq!(db.associated_work(second), work_id);
+        assert_eq!(db.work_sources(work_id).len(), 2);
+
+        let lit = LitSubset::from_works(&[work_id], &db.snapshot());
+        assert_eq!(lit.sources().len(), 2);
+        assert_eq!(db.works_count(lit)[&work_id], 4);
+    }
+
+    #[test]
+    fn test_normalization() {
+        use super::super::editor::CorpusEditor;
+        use super::super::{driver_init, MainDatabase};
+        use crate::query_system::traits::*;
+
+        let corpus = TestCorpus::new("profiles")
+            .with_lemmatizer("mihi,mihi,ego\npoenae,poenae,poena\nora,ora,os\nhora,hora,hora\n")
+            .with_source("aeneis.txt", "mihi poenae ora")
+            .with_source("historia.txt", "michi pene ora")
+            .with_file(
+                "manifest.toml",
+                r#"
+            [[work]]
+            author = "Vergilius"
+            title = "Aeneis"
+            files = ["aeneis.txt"]
+
+            [[work]]
+            author = "Gregorius"
+            title = "Historia"
+            files = ["historia.txt"]
+
+            [normalization]
+            Gregorius = "medieval"
+            "#,
+            );
+        let dir = corpus.dir();
+
+        let config = corpus.config().with_manifest(dir.join("manifest.toml"));
+        let mut db = driver_init(config.clone()).unwrap();
+
+        // Both spellings are read as the same lemmas, though only the medieval `ora` can be `hora`
+        let lemmas = |db: &MainDatabase, file: &str| {
+            let source = *db
+                .sources()
+                .get_by_left(&dir.join("corpus").join(file))
+                .unwrap();
+            let lemmatizer = db.source_lemmatizer(source);
+            let mut lemmas: Vec<_> = db
+                .source_forms(source)
+                .iter()
+                .flat_map(|fd| lemmatizer.get_possible_lemmas(fd.form().0))
+                .map(|lemma| db.lookup_word_str(lemma).to_string())
+                .collect();
+            lemmas.sort();
+            lemmas
+        };
+        assert_eq!(lemmas(&db, "aeneis.txt"), vec!["ego", "os", "poena"]);
+        assert_eq!(
+            lemmas(&db, "historia.txt"),
+            vec!["ego", "hora", "os", "poena"]
+        );
+
+        // While the classical spelling is kept for the others
+        let mii = db.lookup_interned_word("mii".into()).unwrap();
+        let vergil = db
+            .authors()
+            .get_by_left(&crate::authors_chrono::Author::new("Vergilius"));
+        let vergil_sources = db.associated_sources(*vergil.unwrap());
+        let source = *vergil_sources.iter().next().unwrap();
+        assert!(db.source_forms(source).iter().all(|fd| fd.form().0 != mii));
+        assert!(!db.lemmatizer().has_form(mii));
+
+        // The readings added later are known in both spellings too
+        let editor = CorpusEditor::new(config);
+        assert!(editor.add_lemma(&mut db, "poenae", "poenus").unwrap());
+        assert_eq!(
+            lemmas(&db, "historia.txt"),
+            vec!["ego", "hora", "os", "poena", "poenus"]
+        );
+        assert!(editor.remove_lemma(&mut db, "poenae", "poenus").unwrap());
+        assert_eq!(
+            lemmas(&db, "historia.txt"),
+            vec!["ego", "hora", "os", "poena"]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::query_system::sources::GetLineQuery;
+use crate::query_system::sources::{GetLineQuery, SourceFormsQuery, SourceTokensQuery};
 
 use super::MainDatabase;
 use crate::query_system::gc::GCollectable;
@@ -18,8 +18,11 @@ impl GCollectable for MainDatabase {
     }
 }
 
-pub(super) fn set_lru_sizes(db: &mut MainDatabase) {
-    const MANY: usize = 256;
+/// How many values the caches of the intermediate results of parsing keep
+pub(super) const MANY: usize = 256;
 
+pub(super) fn set_lru_sizes(db: &mut MainDatabase) {
     db.query_mut(GetLineQuery).set_lru_capacity(MANY);
+    db.query_mut(SourceTokensQuery).set_lru_capacity(MANY);
+    db.query_mut(SourceFormsQuery).set_lru_capacity(MANY);
 }
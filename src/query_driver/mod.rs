@@ -1,53 +1,94 @@
-use crate::authors_chrono::Author;
-use crate::filesystem::{FileSystem, GetFileSystem, InternerFileSystem};
+use self::manifest::{Manifest, ManifestError, Mismatch, WorkEntry};
+use crate::authors_chrono::{Author, Work};
+use crate::filesystem::{
+    source_format, FileSystem, InternerFileSystem, SourceFormat, CITATIONS_EXTENSION,
+};
 use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
 use crate::latin_lemmatizer::NaiveLemmatizer;
-use crate::latin_utilities::NormalizedLatinString;
+use crate::latin_utilities::{NormalizedLatinString, Profile};
 use crate::query_system::ids::*;
-use crate::query_system::middle::IntermediateDatabase;
-use crate::query_system::middle::IntermediateQueries;
-use crate::query_system::sources::SourcesDatabase;
+use crate::query_system::middle::{IntermediateDatabase, IntermediateQueries, ScopedLemmatizers};
+use crate::query_system::ngrams::NgramsQueryGroup;
+use crate::query_system::overrides::Overrides;
 use crate::query_system::sources::SourcesQueryGroup;
-use crate::query_system::traits::AuthorInternDatabase;
-use crate::query_system::types::InternersGroup;
+use crate::query_system::sources::{Normalization, PreparsedSources, SourcesDatabase};
+use crate::query_system::traits::{AuthorInternDatabase, WorkInternDatabase};
+use crate::query_system::types::{FormData, InternersGroup};
+use crate::query_system::weights::WeightsQueryGroup;
 use crate::query_system::MainQueries;
-use crate::word_db::{WordDatabase, WordDb};
+use crate::tei::TeiDocument;
+use crate::word_db::{WordDatabase, WordDb, WordPattern};
 
 use bimap::BiMap;
-use std::collections::HashMap;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use walkdir::WalkDir;
 
+pub mod editor;
+pub mod manifest;
 pub mod memory;
-
-#[salsa::database(MainQueries, SourcesQueryGroup, InternersGroup, IntermediateQueries)]
+pub mod overrides;
+pub mod snapshot;
+#[cfg(test)]
+pub(crate) mod test_corpus;
+pub mod warmup;
+pub mod watcher;
+
+#[salsa::database(
+    MainQueries,
+    SourcesQueryGroup,
+    InternersGroup,
+    IntermediateQueries,
+    NgramsQueryGroup,
+    WeightsQueryGroup
+)]
 #[derive(Default, Debug)]
 pub struct MainDatabase {
     runtime: salsa::Runtime<MainDatabase>,
     authors: BiMap<Author, AuthorId>,
+    works: BiMap<Work, WorkId>,
+    next_work: u32,
     fs: InternerFileSystem,
     word_db: WordDb,
+    preparsed: Arc<HashMap<SourceId, Arc<Vec<FormData>>>>,
+    manifest_entries: Arc<HashMap<SourceId, Arc<WorkEntry>>>,
 }
 
 impl MainDatabase {
     fn new() -> Self {
-        Self {
+        let mut res = Self {
             runtime: Default::default(),
             authors: BiMap::new(),
+            works: BiMap::new(),
+            next_work: 0,
             fs: InternerFileSystem::new(),
             word_db: WordDb::default(),
-        }
+            preparsed: Arc::new(HashMap::new()),
+            manifest_entries: Arc::new(HashMap::new()),
+        };
+        res.set_vocabulary_version(0);
+        res.set_normalization(Arc::new(Normalization::default()));
+        res.set_overrides(Arc::new(Overrides::new()));
+        res.set_scoped_lemmatizers(Arc::new(ScopedLemmatizers::default()));
+        res
     }
 
     pub fn authors(&self) -> &BiMap<Author, AuthorId> {
         &self.authors
     }
 
+    pub fn works(&self) -> &BiMap<Work, WorkId> {
+        &self.works
+    }
+
     pub fn sources(&self) -> &BiMap<PathBuf, SourceId> {
         &self.fs.sources()
     }
@@ -55,6 +96,11 @@ impl MainDatabase {
     pub fn word_db(&self) -> &WordDb {
         &self.word_db
     }
+
+    /// Get the work of a source, as described in the manifest (if one was given)
+    pub fn manifest_entry(&self, source: SourceId) -> Option<&WorkEntry> {
+        self.manifest_entries.get(&source).map(AsRef::as_ref)
+    }
 }
 
 impl AuthorInternDatabase for MainDatabase {
@@ -82,15 +128,49 @@ impl AuthorInternDatabase for MainDatabase {
     }
 }
 
-impl GetFileSystem for MainDatabase {
-    type Fs = InternerFileSystem;
+impl WorkInternDatabase for MainDatabase {
+    fn intern_work(&mut self, work: Work) -> WorkId {
+        if let Some(&id) = self.works.get_by_left(&work) {
+            return id;
+        }
+
+        // As for the sources, the ids of the works that were removed are not reused
+        let id = WorkId::from_integer(self.next_work);
+        self.next_work += 1;
+        self.works.insert(work, id);
+        id
+    }
+
+    fn lookup_intern_work(&self, id: WorkId) -> &Work {
+        // If invalid id, we panic
+        self.works().get_by_right(&id).unwrap()
+    }
+}
+
+impl FileSystem for MainDatabase {
+    type Source = PathBuf;
+
+    // Note, the file is read when it is first needed
+    fn intern_source(&mut self, path: Self::Source) -> SourceId {
+        let id = self.fs.intern_source(path);
+        self.set_source_contents(id, None);
+        id
+    }
+
+    fn watch(&self, id: SourceId) {
+        self.fs.watch(id)
+    }
 
-    fn filesystem(&self) -> &Self::Fs {
-        &self.fs
+    fn load(&self, id: SourceId) -> String {
+        self.fs.load(id)
     }
 
-    fn filesystem_mut(&mut self) -> &mut Self::Fs {
-        &mut self.fs
+    fn format(&self, id: SourceId) -> SourceFormat {
+        self.fs.format(id)
+    }
+
+    fn load_citations(&self, id: SourceId) -> Option<String> {
+        self.fs.load_citations(id)
     }
 }
 
@@ -106,6 +186,24 @@ impl WordDatabase for MainDatabase {
     fn lookup_interned_word(&self, s: NormalizedLatinString) -> Option<WordId> {
         self.word_db.lookup_interned_word(s)
     }
+
+    fn lookup_interned_str(&self, s: &str) -> Option<WordId> {
+        self.word_db.lookup_interned_str(s)
+    }
+
+    fn lookup_word_str(&self, id: WordId) -> &str {
+        self.word_db.lookup_word_str(id)
+    }
+
+    fn search_words(&self, pattern: &WordPattern) -> Vec<WordId> {
+        self.word_db.search_words(pattern)
+    }
+}
+
+impl PreparsedSources for MainDatabase {
+    fn preparsed(&self, source_id: SourceId) -> Option<Arc<Vec<FormData>>> {
+        self.preparsed.get(&source_id).cloned()
+    }
 }
 
 impl salsa::Database for MainDatabase {
@@ -123,22 +221,35 @@ impl salsa::ParallelDatabase for MainDatabase {
         salsa::Snapshot::new(MainDatabase {
             runtime: self.runtime.snapshot(self),
             authors: self.authors.clone(),
+            works: self.works.clone(),
+            next_work: self.next_work,
             fs: self.fs.clone(),
             word_db: self.word_db.clone(),
+            preparsed: self.preparsed.clone(),
+            manifest_entries: self.manifest_entries.clone(),
         })
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum LemmMode {
     CSVFormat,
     LemlatFormat,
+    /// A treebank, whose (form, lemma) pairs make up the lemmatizer
+    ConlluFormat,
 }
 
+#[derive(Clone)]
 pub struct Configuration {
     data_dir: PathBuf,
     lemmatizer_path: PathBuf,
     authors_path: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
     lemm_mode: LemmMode,
+    normalization: Profile,
+    snapshot_path: Option<PathBuf>,
+    overrides_path: Option<PathBuf>,
+    warm_up_threads: Option<usize>,
 }
 
 impl Configuration {
@@ -169,16 +280,101 @@ impl Configuration {
             data_dir,
             lemmatizer_path,
             authors_path,
+            manifest_path: None,
             lemm_mode,
+            normalization: Profile::classical(),
+            snapshot_path: None,
+            overrides_path: None,
+            warm_up_threads: None,
         })
     }
 
+    /// List the works of the corpus in a manifest, instead of taking the authors
+    /// from the folders of the data directory
+    pub fn with_manifest(self, manifest_path: impl Into<PathBuf>) -> Self {
+        Configuration {
+            manifest_path: Some(manifest_path.into()),
+            ..self
+        }
+    }
+
+    /// Normalize the spelling of the corpus (and of the lemmatizer) with a profile,
+    /// instead of the classical one
+    pub fn with_normalization(self, normalization: Profile) -> Self {
+        Configuration {
+            normalization,
+            ..self
+        }
+    }
+
+    /// Keep a snapshot of the database in a file, which is used to avoid
+    /// parsing the corpus again when the inputs did not change
+    pub fn with_snapshot(self, snapshot_path: impl Into<PathBuf>) -> Self {
+        Configuration {
+            snapshot_path: Some(snapshot_path.into()),
+            ..self
+        }
+    }
+
+    /// Keep the lemmas chosen by hand for single occurrences in a file,
+    /// which is created once the first lemma is chosen
+    pub fn with_overrides(self, overrides_path: impl Into<PathBuf>) -> Self {
+        Configuration {
+            overrides_path: Some(overrides_path.into()),
+            ..self
+        }
+    }
+
+    /// Parse the whole corpus while initializing, on some threads
+    pub fn with_warm_up(self, threads: usize) -> Self {
+        Configuration {
+            warm_up_threads: Some(threads),
+            ..self
+        }
+    }
+
+    /// Parse the whole corpus while initializing, on all cores unless specified otherwise
+    pub fn warm_up_by_default(self) -> Self {
+        let threads = self.warm_up_threads.unwrap_or_else(warmup::default_threads);
+        self.with_warm_up(threads)
+    }
+
+    pub(crate) fn manifest(&self) -> Result<Option<Manifest>, ManifestError> {
+        self.manifest_path
+            .as_ref()
+            .map(|path| Manifest::load(path))
+            .transpose()
+    }
+
+    // The files that are not part of the corpus, even if they are in the data directory
+    fn own_files(&self) -> Vec<PathBuf> {
+        let mut res = vec![self.lemmatizer_path.clone()];
+        res.extend(
+            vec![
+                &self.authors_path,
+                &self.manifest_path,
+                &self.snapshot_path,
+                &self.overrides_path,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned(),
+        );
+        res
+    }
+
     pub(crate) fn make_lemm(&self) -> Result<NaiveLemmatizer, Box<dyn Error>> {
         Ok(match self.lemm_mode {
             LemmMode::CSVFormat => crate::latin_lemmatizer::parsers::csv_format::new()
+                .with_profile(self.normalization.clone())
                 .read_all(File::open(&self.lemmatizer_path)?)?
                 .build(),
             LemmMode::LemlatFormat => crate::latin_lemmatizer::parsers::lemlat_format::new()
+                .with_profile(self.normalization.clone())
+                .read_all(File::open(&self.lemmatizer_path)?)?
+                .build(),
+            LemmMode::ConlluFormat => crate::latin_lemmatizer::parsers::conllu_format::new()
+                .with_profile(self.normalization.clone())
                 .read_all(File::open(&self.lemmatizer_path)?)?
                 .build(),
         })
@@ -186,40 +382,236 @@ impl Configuration {
 }
 
 pub fn driver_init(config: Configuration) -> Result<MainDatabase, Box<dyn Error>> {
-    let mut current_author_id = None;
-    let mut author_associations = HashMap::new();
+    // Note, the chosen lemmas are not part of the snapshot, since they do not change the parsing
+    let finish = |mut db: MainDatabase| -> Result<MainDatabase, Box<dyn Error>> {
+        if let Some(manifest) = config.manifest()? {
+            manifest::attach(&mut db, &manifest, &config.data_dir);
+        }
+        overrides::load(&mut db, &config)?;
+        if let Some(threads) = config.warm_up_threads {
+            warmup::warm_up(&db, threads);
+        }
+        Ok(db)
+    };
+
+    let snapshot_path = match &config.snapshot_path {
+        Some(path) => path.clone(),
+        None => return finish(build(&config)?),
+    };
+
+    match snapshot::load(&snapshot_path, &config) {
+        Ok(Some(db)) => {
+            info!("Loaded snapshot {:?}", snapshot_path);
+            return finish(db);
+        }
+        Ok(None) => info!("Snapshot {:?} missing or outdated", snapshot_path),
+        Err(err) => warn!("Invalid snapshot {:?}: {}", snapshot_path, err),
+    }
 
-    let mut db = MainDatabase::new();
-    memory::set_lru_sizes(&mut db);
+    // Note, the inputs are recorded before they are read
+    let inputs = snapshot::fingerprint(&config)?;
+    let db = finish(build(&config)?)?;
 
-    // First, load lemmatizer
-    let lemm = config.make_lemm()?;
-    let compressed = CompressedLemmatizer::new(lemm, &db);
+    info!("Writing snapshot {:?}", snapshot_path);
+    if let Err(err) = snapshot::save(&db, &config, inputs, &snapshot_path) {
+        warn!("Could not write snapshot {:?}: {}", snapshot_path, err);
+    }
+
+    Ok(db)
+}
+
+// Set which sources belong to which author
+fn set_associations(db: &mut MainDatabase, associations: HashMap<AuthorId, HashSet<SourceId>>) {
+    associations.into_iter().for_each(|(k, v)| {
+        v.iter().for_each(|&s| db.set_associated_author(s, k));
+        db.set_associated_sources(k, Arc::new(v));
+    });
+}
+
+// Set how the words are normalized, with the rules the manifest gives to some of the authors
+fn set_normalization(db: &mut MainDatabase, config: &Configuration, manifest: Option<&Manifest>) {
+    let mut normalization = Normalization::new(config.normalization.clone());
+    for (name, profile) in manifest.iter().flat_map(|m| m.author_profiles()) {
+        match db.authors.get_by_left(&Author::new(name)) {
+            Some(&id) => normalization = normalization.with_author(id, profile.clone()),
+            None => warn!("{}", Mismatch::UnknownAuthor(name.clone())),
+        }
+    }
+    db.set_normalization(Arc::new(normalization));
+}
+
+// Set which sources make up which work
+fn set_work_associations(db: &mut MainDatabase, associations: HashMap<WorkId, HashSet<SourceId>>) {
+    associations.into_iter().for_each(|(k, v)| {
+        v.iter().for_each(|&s| db.set_associated_work(s, k));
+        db.set_work_sources(k, Arc::new(v));
+    });
+}
+
+/// The work a source is taken to be on its own, when no manifest says otherwise
+/// (i.e. titled after the name of its file)
+pub fn default_work(author: AuthorId, path: &Path) -> Work {
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Work::new(author, title)
+}
 
-    for entry in WalkDir::new(config.data_dir).max_depth(2) {
+// Add a source to a work, which is interned if it is new
+fn add_to_work(db: &mut MainDatabase, source: SourceId, work: Work) -> WorkId {
+    let is_new = !db.works.contains_left(&work);
+    let work_id = db.intern_work(work);
+    let mut sources = if is_new {
+        HashSet::new()
+    } else {
+        (*db.work_sources(work_id)).clone()
+    };
+    sources.insert(source);
+    db.set_work_sources(work_id, Arc::new(sources));
+    db.set_associated_work(source, work_id);
+    work_id
+}
+
+// Take a source out of its work, returning the work
+fn remove_from_work(db: &mut MainDatabase, source: SourceId) -> Work {
+    let work_id = db.associated_work(source);
+    let work = db.lookup_intern_work(work_id).clone();
+    let mut sources = (*db.work_sources(work_id)).clone();
+    sources.remove(&source);
+
+    // Ensure no empty works arise
+    if sources.is_empty() {
+        db.works.remove_by_right(&work_id);
+    }
+    db.set_work_sources(work_id, Arc::new(sources));
+    work
+}
+
+// The sources in the data directory, each with its author (the folder it is in)
+fn walk_corpus(config: &Configuration) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let mut current_author = None;
+    let mut res = Vec::new();
+
+    for entry in WalkDir::new(&config.data_dir).max_depth(2) {
         let entry = entry?;
         let ft = entry.file_type();
         // Branch: Add a new author (maybe check for non folder on 2nd level?)
         if ft.is_dir() {
             // We create authors from file mapping
-            let file_name = entry.file_name().to_string_lossy().into_owned();
-
-            let new_id = db.intern_author(Author::new(file_name));
-            current_author_id = Some(new_id);
+            current_author = Some(entry.file_name().to_string_lossy().into_owned());
+        }
+        // Branch, skip references, which are loaded together with their source
+        else if entry.path().extension() == Some(OsStr::new(CITATIONS_EXTENSION)) {
+            continue;
         }
         // Branch, load into db (skip if no author appeared first)
-        else if ft.is_file() && current_author_id.is_some() {
-            let path = entry.path();
-
-            let new_id = db.intern_source(path.to_path_buf());
-            // Add the source to the author
-            author_associations
-                .entry(current_author_id.unwrap())
-                .or_insert_with(HashSet::new)
-                .insert(new_id);
+        else if ft.is_file() {
+            if let Some(author) = &current_author {
+                res.push((author.clone(), entry.path().to_path_buf()));
+            }
         }
     }
 
+    Ok(res)
+}
+
+// The sources listed in the manifest, reporting where it differs from the data directory
+fn manifest_corpus(config: &Configuration, manifest: &Manifest) -> Vec<(String, PathBuf)> {
+    for mismatch in manifest.check(&config.data_dir, &config.own_files()) {
+        warn!("{}", mismatch);
+    }
+
+    manifest
+        .sources()
+        .map(|(work, file)| (work.author(), config.data_dir.join(file.path())))
+        .filter(|(_, path)| path.is_file())
+        .map(|(author, path)| (author.to_string(), path))
+        .collect()
+}
+
+// Build the database from the inputs
+fn build(config: &Configuration) -> Result<MainDatabase, Box<dyn Error>> {
+    let mut author_associations = HashMap::new();
+    let mut work_associations = HashMap::new();
+    // The dates found in the headers of the TEI sources, the first one for each author
+    let mut tei_dates = HashMap::new();
+
+    let mut db = MainDatabase::new();
+    memory::set_lru_sizes(&mut db);
+
+    // First, load lemmatizer
+    let lemm = config.make_lemm()?;
+    // The (form, lemma) pairs of each of the treebanks, kept apart from the lemmatizer
+    let mut treebanks = Vec::new();
+
+    let manifest = config.manifest()?;
+    let corpus = match &manifest {
+        Some(manifest) => manifest_corpus(config, manifest),
+        None => walk_corpus(config)?,
+    };
+    // The work each of the files in the manifest is part of
+    let entries: HashMap<_, _> = manifest
+        .iter()
+        .flat_map(Manifest::sources)
+        .map(|(work, file)| (config.data_dir.join(file.path()), work))
+        .collect();
+
+    for (author, path) in corpus {
+        let document = if source_format(&path) == SourceFormat::Tei && config.authors_path.is_none()
+        {
+            match TeiDocument::parse(&std::fs::read_to_string(&path)?) {
+                Ok(document) => Some(document),
+                Err(err) => {
+                    warn!("Invalid TEI document {:?}: {}", path, err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Note, the header names the author of a TEI source in place of its folder,
+        // though not in place of the manifest
+        let author = match document.as_ref().and_then(TeiDocument::author) {
+            Some(name) if manifest.is_none() => name.to_string(),
+            _ => author,
+        };
+        let author_id = db.intern_author(Author::new(author));
+        if let Some(date) = document.as_ref().and_then(TeiDocument::date) {
+            tei_dates.entry(author_id).or_insert_with(|| date.clone());
+        }
+
+        let work = match entries.get(&path) {
+            Some(entry) => entry.work(author_id),
+            None => default_work(author_id, &path),
+        };
+
+        // The words of the treebanks are known in them, together with their lemmas
+        let treebank = if source_format(&path) == SourceFormat::Treebank {
+            Some(
+                crate::latin_lemmatizer::parsers::conllu_format::new()
+                    .with_profile(config.normalization.clone())
+                    .read_all(File::open(&path)?)?
+                    .build(),
+            )
+        } else {
+            None
+        };
+
+        let new_id = db.intern_source(path);
+        treebanks.extend(treebank.map(|treebank| (new_id, treebank)));
+        // Add the source to the author, and to its work
+        author_associations
+            .entry(author_id)
+            .or_insert_with(HashSet::new)
+            .insert(new_id);
+        work_associations
+            .entry(work)
+            .or_insert_with(HashSet::new)
+            .insert(new_id);
+    }
+
     // Ensure no childless authors arise
     db.authors = db
         .authors
@@ -227,11 +619,15 @@ pub fn driver_init(config: Configuration) -> Result<MainDatabase, Box<dyn Error>
         .filter(|(_, v)| author_associations.contains_key(v) && !author_associations[v].is_empty())
         .collect();
 
+    // The dates of single works, as given in the authors file
+    let mut work_spans = BTreeMap::new();
+
     // Update, so that we can get the authors with metadata
-    if let Some(authors_path) = config.authors_path {
+    if let Some(authors_path) = &config.authors_path {
         let mut authors_hist = crate::authors_chrono::parsers::WeirdParser::default();
         let authors_file = File::open(authors_path)?;
         authors_hist.read_all(authors_file)?;
+        work_spans = authors_hist.work_spans().clone();
         let authors_list = authors_hist.build();
         db.authors = db
             .authors
@@ -241,19 +637,78 @@ pub fn driver_init(config: Configuration) -> Result<MainDatabase, Box<dyn Error>
                 if let Some(author) = authors_list.get(&a).cloned() {
                     Some((author, k))
                 } else {
+                    if manifest.is_some() {
+                        warn!("{}", Mismatch::UnknownAuthor(a.name().to_string()));
+                    }
                     None
                 }
             })
             .collect();
     }
+    // Otherwise, the dates of the works in the manifest (or else the headers of the TEI sources)
+    // say when the authors lived
+    // Note, the names are kept, since they are how the sources of the authors are found
+    else {
+        let spans = manifest
+            .as_ref()
+            .map(Manifest::author_spans)
+            .unwrap_or_default();
+        db.authors = db
+            .authors
+            .into_iter()
+            .map(|(a, k)| {
+                match spans
+                    .get(a.name())
+                    .cloned()
+                    .or_else(|| tei_dates.remove(&k))
+                {
+                    Some(date) => (Author::new_with_tspan(a.name(), date), k),
+                    None => (a, k),
+                }
+            })
+            .collect();
+    }
 
     // Load the authors assoc
-    author_associations.into_iter().for_each(|(k, v)| {
-        db.set_associated_sources(k, Arc::new(v.clone()));
-        v.iter().for_each(|&s| db.set_associated_author(s, k))
-    });
+    set_associations(&mut db, author_associations);
+
+    // Then the works, of the authors that were kept
+    let mut work_ids = HashMap::new();
+    for (work, sources) in work_associations {
+        if db.authors.contains_right(&work.author()) {
+            work_ids.insert(db.intern_work(work), sources);
+        }
+    }
+    set_work_associations(&mut db, work_ids);
+
+    // Note, the date in the authors file takes the place of the one in the manifest
+    for ((author, title), span) in work_spans {
+        let author_id = match db.authors.get_by_left(&Author::new(&author)) {
+            Some(&id) => id,
+            None => continue,
+        };
+        match db.works.remove_by_left(&Work::new(author_id, &title)) {
+            Some((work, id)) => {
+                db.works.insert(work.with_tspan(span), id);
+            }
+            None => warn!("No work {:?} of {:?} to date", title, author),
+        }
+    }
+
+    // The authors with rules of their own are read with the forms in their spelling
+    set_normalization(&mut db, config, manifest.as_ref());
+    let profiles: HashSet<_> = db.normalization().authors().values().cloned().collect();
+    let mut scoped = ScopedLemmatizers::default();
+    for profile in profiles {
+        let variants = CompressedLemmatizer::new(lemm.normalize_forms(&profile), &db);
+        scoped = scoped.with_profile(profile, Arc::new(variants));
+    }
+    for (source, treebank) in treebanks {
+        scoped = scoped.with_treebank(source, Arc::new(CompressedLemmatizer::new(treebank, &db)));
+    }
 
-    db.set_lemmatizer(Arc::new(compressed));
+    db.set_lemmatizer(Arc::new(CompressedLemmatizer::new(lemm, &db)));
+    db.set_scoped_lemmatizers(Arc::new(scoped));
 
     Ok(db)
 }
@@ -0,0 +1,162 @@
+//! The file where the lemmas chosen by hand are kept
+//! Each line is made of the path of a source (relative to the data directory), the (zero based)
+//! line and index of the occurrence and its lemma, separated by tabs
+
+use super::{Configuration, MainDatabase};
+use crate::query_system::ids::SourceId;
+use crate::query_system::middle;
+use crate::query_system::overrides::Overrides;
+use crate::query_system::traits::*;
+use crate::query_system::types::Lemma;
+
+use log::warn;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+fn invalid(line_no: usize, msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_no + 1, msg),
+    )
+}
+
+/// Read the chosen lemmas into the database, if the file exists
+/// Note, the lemmas of sources that are not in the corpus are skipped
+pub fn load(db: &mut MainDatabase, config: &Configuration) -> io::Result<()> {
+    let path = match &config.overrides_path {
+        Some(path) if path.exists() => path,
+        _ => return Ok(()),
+    };
+
+    let converter = db.normalization().converter();
+    let mut overrides = Overrides::new();
+    for (line_no, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split('\t').collect();
+        if fields.len() != 4 {
+            return Err(invalid(line_no, "expected four fields"));
+        }
+        let number = |s: &str| s.parse().map_err(|_| invalid(line_no, "invalid number"));
+        let (occurrence_line, index) = (number(fields[1])?, number(fields[2])?);
+
+        let source = match db.sources().get_by_left(&config.data_dir.join(fields[0])) {
+            Some(&source) => source,
+            None => {
+                warn!("Skipping the lemma chosen in {:?}", fields[0]);
+                continue;
+            }
+        };
+
+        let lemma = match db.lookup_interned_word(converter.convert(fields[3])) {
+            Some(id) => Lemma(id),
+            None => {
+                warn!("Skipping the unknown lemma {:?}", fields[3]);
+                continue;
+            }
+        };
+        overrides.set(source, occurrence_line, index, Some(lemma));
+    }
+
+    let sources: Vec<_> = overrides.sources().collect();
+    db.set_overrides(Arc::new(overrides));
+    for source in sources {
+        prune(db, source);
+    }
+    Ok(())
+}
+
+/// Forget the lemmas chosen in a source that are not readings of the words at their places
+/// (i.e. once words were added before them), with a warning for each
+pub fn prune(db: &mut MainDatabase, source: SourceId) {
+    let chosen = db.overrides().source(source);
+    let valid = middle::valid_overrides(&*db, source);
+    if valid.len() == chosen.len() {
+        return;
+    }
+
+    let mut overrides = (*db.overrides()).clone();
+    for (&(line_no, index), lemma) in chosen.iter().filter(|(key, _)| !valid.contains_key(key)) {
+        warn!(
+            "Forgetting {:?} for word {} in line {} of {:?}, which it is not a reading of",
+            db.lookup_word_str(lemma.0),
+            index,
+            line_no,
+            source
+        );
+        overrides.set(source, line_no, index, None);
+    }
+    db.set_overrides(Arc::new(overrides));
+}
+
+/// Write all the chosen lemmas in the database to the file
+pub fn save(db: &MainDatabase, path: &Path, data_dir: &Path) -> io::Result<()> {
+    let overrides = db.overrides();
+    let mut lines = Vec::new();
+    for source in overrides.sources() {
+        let source_path = match db.sources().get_by_right(&source) {
+            Some(path) => path.strip_prefix(data_dir).unwrap_or(path),
+            None => continue,
+        };
+
+        for (&(line_no, index), lemma) in overrides.source(source).iter() {
+            lines.push((
+                source_path.to_path_buf(),
+                line_no,
+                index,
+                db.lookup_word_str(lemma.0).to_string(),
+            ));
+        }
+    }
+    lines.sort();
+
+    let content: String = lines
+        .into_iter()
+        .map(|(source, line_no, index, lemma)| {
+            format!("{}\t{}\t{}\t{}\n", source.display(), line_no, index, lemma)
+        })
+        .collect();
+
+    // Note, as for the other files, a half written file is never left behind
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::driver_init;
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let corpus = TestCorpus::new("overrides")
+            .with_lemmatizer("uenit,uenit,uenio\nuenit,uenit,ueneo\n")
+            .with_source("Vergilius/aeneid.txt", "uenit\nuenit uenit")
+            .with_file(
+                "overrides.tsv",
+                "Vergilius/aeneid.txt\t1\t1\tueneo\nMissing/text.txt\t0\t0\tueneo\n\
+                 Vergilius/aeneid.txt\t0\t0\tcano\nVergilius/aeneid.txt\t1\t2\tueneo\n",
+            );
+        let dir = corpus.dir();
+
+        let config = corpus.config().with_overrides(dir.join("overrides.tsv"));
+        let db = driver_init(config.clone()).unwrap();
+
+        let source = *db.sources().right_values().next().unwrap();
+        let ueneo = Lemma(db.lookup_interned_word("ueneo".into()).unwrap());
+        assert_eq!(db.overrides().get(source, 1, 1), Some(ueneo));
+        assert_eq!(db.overrides().source(source).len(), 1);
+
+        save(&db, &dir.join("overrides.tsv"), &config.data_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("overrides.tsv")).unwrap(),
+            "Vergilius/aeneid.txt\t1\t1\tueneo\n"
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Persistence of a built database on disk, so that the corpus is not parsed at every startup
+//! A snapshot records the inputs it was built from (with their modification time and hash),
+//! and it is only used as long as none of them changed
+
+use super::{
+    set_associations, set_normalization, set_work_associations, Configuration, LemmMode,
+    MainDatabase,
+};
+use crate::authors_chrono::{Author, TimeSpan, Work};
+use crate::filesystem::InternerFileSystem;
+use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
+use crate::latin_lemmatizer::morphology::Analysis;
+use crate::latin_utilities::{Enclitic, NormalizedLatinString, Profile};
+use crate::query_system::ids::*;
+use crate::query_system::middle::ScopedLemmatizers;
+use crate::query_system::traits::*;
+use crate::query_system::types::{Form, FormData};
+use crate::word_db::WordDb;
+
+use bimap::BiMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+const MAGIC: &[u8; 4] = b"LDDB";
+
+/// Bumped whenever the layout of the snapshot changes, older snapshots are then rebuilt
+pub const FORMAT_VERSION: u32 = 6;
+
+/// A file the database was built from
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InputFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredFormData {
+    line_no: usize,
+    index: usize,
+    offset: usize,
+    form: u32,
+    enclitic: Option<Enclitic>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSource {
+    path: PathBuf,
+    id: u32,
+    author: u32,
+    work: u32,
+    parsed: Vec<StoredFormData>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredWork {
+    id: u32,
+    author: u32,
+    title: String,
+    time_span: Option<TimeSpan>,
+    genre: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredLemmatizer {
+    mapping: Vec<(u32, Vec<u32>)>,
+    analyses: Vec<((u32, u32), Vec<Analysis>)>,
+}
+
+impl StoredLemmatizer {
+    fn new(lemm: &CompressedLemmatizer) -> Self {
+        StoredLemmatizer {
+            mapping: lemm
+                .mapping()
+                .iter()
+                .map(|(form, lemmas)| {
+                    (
+                        form.as_integer(),
+                        lemmas.iter().map(WordId::as_integer).collect(),
+                    )
+                })
+                .collect(),
+            analyses: lemm
+                .analyses()
+                .iter()
+                .map(|(&(form, lemma), analyses)| {
+                    (
+                        (form.as_integer(), lemma.as_integer()),
+                        analyses.iter().cloned().collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn restore(self) -> Arc<CompressedLemmatizer> {
+        let mapping = self
+            .mapping
+            .into_iter()
+            .map(|(form, lemmas)| {
+                (
+                    WordId::from_integer(form),
+                    lemmas.into_iter().map(WordId::from_integer).collect(),
+                )
+            })
+            .collect();
+        let analyses = self
+            .analyses
+            .into_iter()
+            .map(|((form, lemma), analyses)| {
+                (
+                    (WordId::from_integer(form), WordId::from_integer(lemma)),
+                    analyses.into_iter().collect(),
+                )
+            })
+            .collect();
+        Arc::new(CompressedLemmatizer::from_mapping_with_analyses(
+            mapping, analyses,
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    lemm_mode: LemmMode,
+    // Note, the rules of the authors are in the manifest, which is one of the inputs
+    normalization: Profile,
+    inputs: Vec<InputFile>,
+    words: Vec<(u32, String)>,
+    lemmatizer: StoredLemmatizer,
+    profiles: Vec<(Profile, StoredLemmatizer)>,
+    treebanks: Vec<(u32, StoredLemmatizer)>,
+    authors: Vec<(Author, u32)>,
+    works: Vec<StoredWork>,
+    sources: Vec<StoredSource>,
+}
+
+// FNV-1a, which unlike the std hasher is stable across releases
+fn hash_file(path: &Path) -> io::Result<u64> {
+    Ok(fs::read(path)?
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        }))
+}
+
+fn modified(path: &Path) -> io::Result<Option<SystemTime>> {
+    Ok(fs::metadata(path)?.modified().ok())
+}
+
+// All the files the database is built from, in a stable order
+fn input_paths(config: &Configuration) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut res = vec![config.lemmatizer_path.clone()];
+    res.extend(config.authors_path.iter().cloned());
+
+    // Note, only the files in the manifest are part of the corpus
+    if let Some(manifest) = config.manifest()? {
+        res.extend(config.manifest_path.iter().cloned());
+        for (_, file) in manifest.sources() {
+            let path = config.data_dir.join(file.path());
+            let citations = file.citations_path(&config.data_dir);
+            res.extend(vec![path, citations].into_iter().filter(|p| p.is_file()));
+        }
+        return Ok(res);
+    }
+
+    for entry in WalkDir::new(&config.data_dir)
+        .max_depth(2)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    {
+        let entry = entry?;
+        // Note, the snapshot itself might be kept with the corpus
+        if entry.file_type().is_file() && Some(entry.path()) != config.snapshot_path.as_deref() {
+            res.push(entry.path().to_path_buf());
+        }
+    }
+
+    Ok(res)
+}
+
+/// Record the current state of the inputs of a configuration
+pub fn fingerprint(config: &Configuration) -> Result<Vec<InputFile>, Box<dyn Error>> {
+    let mut res = Vec::new();
+    for path in input_paths(config)? {
+        res.push(InputFile {
+            modified: modified(&path)?,
+            hash: hash_file(&path)?,
+            path,
+        });
+    }
+    Ok(res)
+}
+
+// Are the inputs the same as the recorded ones? The (cheaper) times are checked first
+fn is_fresh(inputs: &[InputFile], config: &Configuration) -> Result<bool, Box<dyn Error>> {
+    let paths = input_paths(config)?;
+    if paths.len() != inputs.len() {
+        return Ok(false);
+    }
+
+    for (path, input) in paths.iter().zip(inputs) {
+        if *path != input.path || modified(path)? != input.modified {
+            return Ok(false);
+        }
+    }
+
+    for input in inputs {
+        if hash_file(&input.path)? != input.hash {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Write the database to disk, parsing all of the sources that were not yet
+pub fn save(
+    db: &MainDatabase,
+    config: &Configuration,
+    inputs: Vec<InputFile>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut sources = Vec::new();
+    for (source_path, &id) in db.sources() {
+        sources.push(StoredSource {
+            path: source_path.clone(),
+            id: id.as_integer(),
+            author: db.associated_author(id).as_integer(),
+            work: db.associated_work(id).as_integer(),
+            parsed: db
+                .source_forms(id)
+                .iter()
+                .map(|fd| StoredFormData {
+                    line_no: fd.line_no(),
+                    index: fd.index(),
+                    offset: fd.offset(),
+                    form: fd.form().0.as_integer(),
+                    enclitic: fd.enclitic(),
+                })
+                .collect(),
+        });
+    }
+
+    let scoped = db.scoped_lemmatizers();
+    let snapshot = Snapshot {
+        lemm_mode: config.lemm_mode,
+        normalization: config.normalization.clone(),
+        inputs,
+        words: db
+            .word_db()
+            .words()
+            .into_iter()
+            .map(|(id, s)| (id.as_integer(), s.inner().to_string()))
+            .collect(),
+        lemmatizer: StoredLemmatizer::new(&db.lemmatizer()),
+        profiles: scoped
+            .profiles()
+            .iter()
+            .map(|(profile, lemm)| (profile.clone(), StoredLemmatizer::new(lemm)))
+            .collect(),
+        treebanks: scoped
+            .treebanks()
+            .iter()
+            .map(|(source, lemm)| (source.as_integer(), StoredLemmatizer::new(lemm)))
+            .collect(),
+        authors: db
+            .authors()
+            .iter()
+            .map(|(author, id)| (author.clone(), id.as_integer()))
+            .collect(),
+        works: db
+            .works()
+            .iter()
+            .map(|(work, id)| StoredWork {
+                id: id.as_integer(),
+                author: work.author().as_integer(),
+                title: work.title().to_string(),
+                time_span: work.tspan().cloned(),
+                genre: work.genre().map(str::to_string),
+            })
+            .collect(),
+        sources,
+    };
+
+    // Write to the side, so that an interrupted write does not leave a corrupt snapshot
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &snapshot)?;
+        writer.flush()?;
+    }
+    fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Load the database from disk, if the snapshot exists and is up to date with the inputs
+pub fn load(path: &Path, config: &Configuration) -> Result<Option<MainDatabase>, Box<dyn Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut header = [0; 8];
+    reader.read_exact(&mut header)?;
+    if header[..4] != MAGIC[..] || header[4..] != FORMAT_VERSION.to_le_bytes()[..] {
+        return Ok(None);
+    }
+
+    let snapshot: Snapshot = bincode::deserialize_from(reader)?;
+    if snapshot.lemm_mode != config.lemm_mode
+        || snapshot.normalization != config.normalization
+        || !is_fresh(&snapshot.inputs, config)?
+    {
+        return Ok(None);
+    }
+
+    let mut db = restore(snapshot);
+    set_normalization(&mut db, config, config.manifest()?.as_ref());
+    Ok(Some(db))
+}
+
+fn restore(snapshot: Snapshot) -> MainDatabase {
+    let mut db = MainDatabase::new();
+    super::memory::set_lru_sizes(&mut db);
+
+    db.word_db = WordDb::from_words(snapshot.words.into_iter().map(|(id, s)| {
+        (
+            WordId::from_integer(id),
+            NormalizedLatinString::instantiate(s),
+        )
+    }));
+
+    db.authors = snapshot
+        .authors
+        .into_iter()
+        .map(|(author, id)| (author, AuthorId::from_integer(id)))
+        .collect();
+
+    db.works = snapshot
+        .works
+        .into_iter()
+        .map(|stored| {
+            let work = Work::new(AuthorId::from_integer(stored.author), stored.title);
+            let work = match stored.time_span {
+                Some(span) => work.with_tspan(span),
+                None => work,
+            };
+            let work = match stored.genre {
+                Some(genre) => work.with_genre(genre),
+                None => work,
+            };
+            (work, WorkId::from_integer(stored.id))
+        })
+        .collect();
+    db.next_work = db
+        .works
+        .right_values()
+        .map(|id| id.as_integer() + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut sources = BiMap::new();
+    let mut preparsed = HashMap::new();
+    let mut author_associations = HashMap::new();
+    let mut work_associations = HashMap::new();
+    for source in snapshot.sources {
+        let id = SourceId::from_integer(source.id);
+        let parsed = source
+            .parsed
+            .into_iter()
+            .map(|fd| {
+                let form = Form(WordId::from_integer(fd.form));
+                match fd.enclitic {
+                    Some(enclitic) => FormData::new_with_enclitic(
+                        id, fd.line_no, fd.index, fd.offset, form, enclitic,
+                    ),
+                    None => FormData::new(id, fd.line_no, fd.index, fd.offset, form),
+                }
+            })
+            .collect();
+
+        sources.insert(source.path, id);
+        preparsed.insert(id, Arc::new(parsed));
+        author_associations
+            .entry(AuthorId::from_integer(source.author))
+            .or_insert_with(HashSet::new)
+            .insert(id);
+        work_associations
+            .entry(WorkId::from_integer(source.work))
+            .or_insert_with(HashSet::new)
+            .insert(id);
+    }
+
+    // Note, the files are only read if they are needed
+    for &id in sources.right_values() {
+        db.set_source_contents(id, None);
+    }
+    db.fs = InternerFileSystem::from_sources(sources);
+    db.preparsed = Arc::new(preparsed);
+    set_associations(&mut db, author_associations);
+    set_work_associations(&mut db, work_associations);
+
+    db.set_lemmatizer(snapshot.lemmatizer.restore());
+    let mut scoped = ScopedLemmatizers::default();
+    for (profile, lemm) in snapshot.profiles {
+        scoped = scoped.with_profile(profile, lemm.restore());
+    }
+    for (source, lemm) in snapshot.treebanks {
+        scoped = scoped.with_treebank(SourceId::from_integer(source), lemm.restore());
+    }
+    db.set_scoped_lemmatizers(Arc::new(scoped));
+
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::driver_init;
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // A small corpus, kept in a snapshot
+    fn make_corpus(name: &str) -> TestCorpus {
+        TestCorpus::new(name)
+            .with_lemmatizer("arma,arma,arma\nuirum,uirum,uir\ncano,cano,cano\n")
+            .with_source("Vergilius/aeneid.txt", "Arma uirumque cano\ncano arma")
+    }
+
+    fn config(corpus: &TestCorpus) -> Configuration {
+        corpus
+            .config()
+            .with_snapshot(corpus.dir().join("corpus.snapshot"))
+    }
+
+    fn parsed_words(db: &MainDatabase) -> BTreeSet<(String, usize, usize, Option<Enclitic>)> {
+        db.sources()
+            .right_values()
+            .flat_map(|&s| db.parse_source(s).iter().cloned().collect::<Vec<_>>())
+            .map(|fd| db.lookup_intern_form_data(fd))
+            .map(|fd| {
+                (
+                    db.lookup_word_str(fd.form().0).to_string(),
+                    fd.line_no(),
+                    fd.index(),
+                    fd.enclitic(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let corpus = make_corpus("roundtrip");
+        let dir = corpus.dir();
+
+        let built = driver_init(config(&corpus)).unwrap();
+        assert!(built.preparsed.is_empty());
+        assert!(dir.join("corpus.snapshot").exists());
+
+        let loaded = driver_init(config(&corpus)).unwrap();
+        assert_eq!(loaded.preparsed.len(), 1);
+        assert_eq!(parsed_words(&built), parsed_words(&loaded));
+        assert_eq!(parsed_words(&loaded).len(), 5);
+        assert_eq!(
+            built.authors().left_values().collect::<Vec<_>>(),
+            loaded.authors().left_values().collect::<Vec<_>>()
+        );
+
+        // Note, with no manifest the source is a work of its own
+        let (work, &work_id) = loaded.works().iter().next().unwrap();
+        assert_eq!(work.title(), "aeneid");
+        let source = *loaded.sources().right_values().next().unwrap();
+        assert_eq!(loaded.associated_work(source), work_id);
+
+        let arma = loaded.lookup_interned_word("arma".into()).unwrap();
+        assert!(loaded.lemmatizer().has_lemma(arma));
+    }
+
+    #[test]
+    fn test_outdated() {
+        let corpus = make_corpus("outdated");
+        let dir = corpus.dir();
+        driver_init(config(&corpus)).unwrap();
+
+        fs::write(
+            dir.join("corpus").join("Vergilius").join("aeneid.txt"),
+            "arma uirum",
+        )
+        .unwrap();
+        assert!(load(&dir.join("corpus.snapshot"), &config(&corpus))
+            .unwrap()
+            .is_none());
+
+        let rebuilt = driver_init(config(&corpus)).unwrap();
+        assert!(rebuilt.preparsed.is_empty());
+        assert_eq!(parsed_words(&rebuilt).len(), 2);
+    }
+
+    #[test]
+    fn test_other_version() {
+        let corpus = make_corpus("version");
+        let dir = corpus.dir();
+        driver_init(config(&corpus)).unwrap();
+
+        let path = dir.join("corpus.snapshot");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4] = bytes[4].wrapping_add(1);
+        fs::write(&path, bytes).unwrap();
+        assert!(load(&path, &config(&corpus)).unwrap().is_none());
+    }
+}
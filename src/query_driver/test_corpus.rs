@@ -0,0 +1,77 @@
+//! A small corpus in a temporary directory, for the tests that go through the files
+//! The directory holds the data directory (`corpus`), the lemmatizer (`lemm.csv`) and
+//! optionally an authors file (`authors.txt`), and it is removed once the corpus is dropped
+
+use super::{Configuration, LemmMode};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) struct TestCorpus {
+    dir: PathBuf,
+    authors: bool,
+}
+
+impl TestCorpus {
+    /// A fresh directory, named after the test so that the tests can run side by side,
+    /// with an empty lemmatizer
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("latin_db_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("corpus")).unwrap();
+        fs::write(dir.join("lemm.csv"), "").unwrap();
+
+        TestCorpus {
+            dir,
+            authors: false,
+        }
+    }
+
+    /// Write a file, relative to the directory, along with the folders it is in
+    pub fn with_file(self, path: impl AsRef<Path>, content: &str) -> Self {
+        let path = self.dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        self
+    }
+
+    /// Write a source, relative to the data directory
+    pub fn with_source(self, path: impl AsRef<Path>, content: &str) -> Self {
+        let path = Path::new("corpus").join(path);
+        self.with_file(path, content)
+    }
+
+    /// Write the lemmatizer, in the CSV format
+    pub fn with_lemmatizer(self, content: &str) -> Self {
+        self.with_file("lemm.csv", content)
+    }
+
+    /// Write an authors file, which the configuration then uses
+    pub fn with_authors(self, content: &str) -> Self {
+        let mut corpus = self.with_file("authors.txt", content);
+        corpus.authors = true;
+        corpus
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The configuration of the corpus, which can still be completed (i.e. with a manifest)
+    pub fn config(&self) -> Configuration {
+        Configuration::new(
+            self.dir.join("corpus"),
+            self.dir.join("lemm.csv"),
+            Some(self.dir.join("authors.txt")).filter(|_| self.authors),
+            LemmMode::CSVFormat,
+        )
+        .unwrap()
+    }
+}
+
+impl Drop for TestCorpus {
+    fn drop(&mut self) {
+        // Note, a failing test still cleans up after itself
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,164 @@
+//! Parsing of the whole corpus ahead of time, spread on several threads (each with its own snapshot)
+//! The interning is done on a single thread, in the order of the sources,
+//! so that the ids do not depend on how the work was split
+
+use super::memory::MANY;
+use super::MainDatabase;
+use crate::query_system::ids::SourceId;
+use crate::query_system::traits::*;
+
+use log::info;
+use salsa::ParallelDatabase;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// The number of threads to use if none is specified
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// Run some work on every source, with the sources shared among the threads
+fn in_parallel(
+    db: &MainDatabase,
+    sources: &[SourceId],
+    threads: usize,
+    work: fn(&MainDatabase, SourceId),
+) {
+    let sources = Arc::new(sources.to_vec());
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|_| {
+            let snapshot = db.snapshot();
+            let (sources, next) = (sources.clone(), next.clone());
+            thread::spawn(move || {
+                while let Some(&source) = sources.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    work(&snapshot, source);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().expect("A worker panicked while warming up");
+    }
+}
+
+/// Parse all the sources of the authors in the database, and build their trees
+pub fn warm_up(db: &MainDatabase, threads: usize) {
+    let mut sources: Vec<_> = db
+        .authors()
+        .right_values()
+        .flat_map(|&author| {
+            db.associated_sources(author)
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    sources.sort();
+
+    info!(
+        "Warming up {} sources on {} threads",
+        sources.len(),
+        threads
+    );
+
+    // Note, the batches fit the caches, so that no resolved source is evicted before being interned
+    for batch in sources.chunks(MANY / 2) {
+        in_parallel(db, batch, threads, |db, source| {
+            db.source_forms(source);
+        });
+
+        for &source in batch {
+            db.parse_source(source);
+        }
+    }
+
+    in_parallel(db, &sources, threads, |db, source| {
+        db.source_tree(source);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::driver_init;
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+    use crate::query_system::lit_subset::LitSubset;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    // A corpus with several authors, and several sources for each
+    fn make_corpus() -> TestCorpus {
+        let mut corpus = TestCorpus::new("warmup").with_lemmatizer(
+            "arma,arma,arma\nuirum,uirum,uir\nuiros,uiros,uir\ncano,cano,cano\ncanit,canit,cano\n",
+        );
+
+        let texts = ["arma uirumque cano", "uiros canit", "cano arma\narma"];
+        for author in &["Vergilius", "Ovidius", "Lucanus"] {
+            for (i, text) in texts.iter().enumerate() {
+                corpus = corpus.with_source(format!("{}/{}.txt", author, i), text);
+            }
+        }
+
+        corpus
+    }
+
+    type Tree = BTreeMap<String, BTreeMap<String, BTreeSet<u32>>>;
+
+    // The parsing of the corpus, with the raw ids
+    // Note, the words are compared as strings, since the lemmatizer interns them in no set order
+    fn parsed(corpus: &TestCorpus, threads: usize) -> (BTreeMap<u32, BTreeSet<u32>>, Tree) {
+        let config = corpus.config().with_warm_up(threads);
+        let db = driver_init(config).unwrap();
+
+        let sources = db
+            .sources()
+            .right_values()
+            .map(|&s| {
+                let ids = db
+                    .parse_source(s)
+                    .iter()
+                    .map(|fd| fd.as_integer())
+                    .collect();
+                (s.as_integer(), ids)
+            })
+            .collect();
+
+        let all = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
+        let tree = db
+            .subset_tree(all)
+            .iter()
+            .map(|(&lemma, forms)| {
+                let forms = forms
+                    .iter()
+                    .map(|(&form, fds)| {
+                        (
+                            db.lookup_word_str(form.0).to_string(),
+                            fds.iter().map(|fd| fd.as_integer()).collect(),
+                        )
+                    })
+                    .collect();
+                (db.lookup_word_str(lemma.0).to_string(), forms)
+            })
+            .collect();
+
+        (sources, tree)
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let corpus = make_corpus();
+
+        let (sequential_sources, sequential_tree) = parsed(&corpus, 1);
+        assert_eq!(sequential_sources.len(), 9);
+        assert_eq!(sequential_tree.len(), 3);
+
+        for &threads in &[2, 4, 8] {
+            let (sources, tree) = parsed(&corpus, threads);
+            assert_eq!(sources, sequential_sources);
+            assert_eq!(tree, sequential_tree);
+        }
+    }
+}
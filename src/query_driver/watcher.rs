@@ -0,0 +1,439 @@
+//! Live reloading of the corpus: the changes to the data directory are applied to the database,
+//! so that the snapshots taken afterwards see them
+
+use super::overrides;
+use super::{add_to_work, default_work, remove_from_work, Configuration, MainDatabase};
+use crate::authors_chrono::Author;
+use crate::filesystem::{source_format, FileSystem, SourceFormat, CITATIONS_EXTENSION};
+use crate::query_system::ids::*;
+use crate::query_system::traits::*;
+use crate::tei::TeiDocument;
+
+use log::{info, warn};
+use notify::DebouncedEvent;
+use std::collections::HashSet;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+// What a path in the data directory stands for
+enum Entry {
+    Author(String),
+    Source(String, PathBuf),
+    Citations(PathBuf),
+}
+
+/// Keeps the database in sync with its data directory
+pub struct CorpusWatcher {
+    events: Receiver<DebouncedEvent>,
+    config: Configuration,
+    // Where the events are reported, which might not be how the corpus was walked
+    roots: Vec<PathBuf>,
+}
+
+impl CorpusWatcher {
+    /// Start watching the data directory of a database
+    pub fn new(db: &mut MainDatabase, config: &Configuration) -> Result<Self, Box<dyn Error>> {
+        let mut roots = vec![std::env::current_dir()?.join(&config.data_dir)];
+        roots.push(config.data_dir.canonicalize()?);
+
+        let events = db.fs.watch_directory(roots[0].clone())?;
+        info!("Watching {:?}", config.data_dir);
+
+        Ok(CorpusWatcher {
+            events,
+            config: config.clone(),
+            roots,
+        })
+    }
+
+    /// Wait for the next change, `None` once no more changes can be reported
+    pub fn next_event(&self) -> Option<DebouncedEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Update the database after a change
+    pub fn apply(&self, db: &mut MainDatabase, event: DebouncedEvent) {
+        match event {
+            DebouncedEvent::Create(path) => self.created(db, &path),
+            DebouncedEvent::Write(path) => self.written(db, &path),
+            DebouncedEvent::Remove(path) => self.removed(db, &path),
+            DebouncedEvent::Rename(from, to) => {
+                self.removed(db, &from);
+                self.created(db, &to);
+            }
+            DebouncedEvent::Error(err, path) => warn!("Error watching {:?}: {}", path, err),
+            _ => {}
+        }
+    }
+
+    // Map a path from an event to the corpus, following the layout of `driver_init`
+    fn entry(&self, path: &Path) -> Option<Entry> {
+        let relative = self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())?;
+        let names: Vec<_> = relative
+            .components()
+            .map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+
+        let corpus_path = self.config.data_dir.join(relative);
+        let own_files = [&self.config.snapshot_path, &self.config.overrides_path];
+        if own_files.iter().any(|p| p.as_ref() == Some(&corpus_path)) {
+            return None;
+        }
+
+        match names.as_slice() {
+            [author] => Some(Entry::Author(author.clone())),
+            [author, _] => {
+                if corpus_path.extension() == Some(OsStr::new(CITATIONS_EXTENSION)) {
+                    Some(Entry::Citations(corpus_path))
+                } else {
+                    Some(Entry::Source(author.clone(), corpus_path))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn created(&self, db: &mut MainDatabase, path: &Path) {
+        match self.entry(path) {
+            Some(Entry::Author(author)) => {
+                let dir = self.config.data_dir.join(&author);
+                for entry in WalkDir::new(dir).min_depth(1).max_depth(1) {
+                    match entry {
+                        Ok(entry) if entry.file_type().is_file() => self.created(db, entry.path()),
+                        Ok(_) => {}
+                        Err(err) => warn!("Error reading {:?}: {}", author, err),
+                    }
+                }
+            }
+            Some(Entry::Source(author, path)) if path.is_file() => {
+                match db.sources().get_by_left(&path).cloned() {
+                    Some(id) => touch(db, id),
+                    None => self.add_source(db, &author, path),
+                }
+            }
+            Some(Entry::Citations(path)) => self.citations_changed(db, &path),
+            _ => {}
+        }
+    }
+
+    fn written(&self, db: &mut MainDatabase, path: &Path) {
+        // Note, the sources outside of the directory are watched one by one
+        if let Some(id) = db.sources().get_by_left(path).cloned() {
+            return touch(db, id);
+        }
+
+        self.created(db, path)
+    }
+
+    fn removed(&self, db: &mut MainDatabase, path: &Path) {
+        match self.entry(path) {
+            Some(Entry::Author(author)) => {
+                let dir = self.config.data_dir.join(author);
+                let gone: Vec<_> = db
+                    .sources()
+                    .iter()
+                    .filter(|(path, _)| path.starts_with(&dir))
+                    .map(|(_, &id)| id)
+                    .collect();
+                gone.into_iter().for_each(|id| remove_source(db, id));
+            }
+            Some(Entry::Source(_, path)) => {
+                if let Some(id) = db.sources().get_by_left(&path).cloned() {
+                    remove_source(db, id);
+                }
+            }
+            Some(Entry::Citations(path)) => self.citations_changed(db, &path),
+            None => {
+                if let Some(id) = db.sources().get_by_left(path).cloned() {
+                    remove_source(db, id);
+                }
+            }
+        }
+    }
+
+    fn citations_changed(&self, db: &mut MainDatabase, path: &Path) {
+        let sources: Vec<_> = db
+            .sources()
+            .iter()
+            .map(|(_, &id)| id)
+            .filter(|&id| db.fs.citations_path_of(id) == path)
+            .collect();
+        sources.into_iter().for_each(|id| touch(db, id));
+    }
+
+    fn add_source(&self, db: &mut MainDatabase, author: &str, path: PathBuf) {
+        // Note, the manifest says which files are part of the corpus
+        if self.config.manifest_path.is_some() {
+            warn!("Source {:?} is not in the manifest, skipping", path);
+            return;
+        }
+
+        // As when building, a TEI source goes to the author named in its header
+        let header = match source_format(&path) {
+            SourceFormat::Tei if self.config.authors_path.is_none() => fs::read_to_string(&path)
+                .ok()
+                .and_then(|xml| TeiDocument::parse(&xml).ok())
+                .and_then(|document| document.author().map(String::from)),
+            _ => None,
+        };
+        let author = header.as_deref().unwrap_or(author);
+
+        let (author_id, mut sources) = match db.authors().get_by_left(&Author::new(author)) {
+            Some(&id) => (id, (*db.associated_sources(id)).clone()),
+            None => match self.new_author(db, author) {
+                Some(id) => (id, HashSet::new()),
+                None => return,
+            },
+        };
+
+        info!("New source {:?}", path);
+        let work = default_work(author_id, &path);
+        let id = db.intern_source(path);
+        sources.insert(id);
+        db.set_associated_author(id, author_id);
+        db.set_associated_sources(author_id, Arc::new(sources));
+        add_to_work(db, id, work);
+    }
+
+    // Add an author, with the metadata from the authors file (if any)
+    fn new_author(&self, db: &mut MainDatabase, name: &str) -> Option<AuthorId> {
+        let author = match &self.config.authors_path {
+            None => Author::new(name),
+            Some(authors_path) => {
+                let mut authors_hist = crate::authors_chrono::parsers::WeirdParser::default();
+                let read = File::open(authors_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|file| authors_hist.read_all(file).map_err(|err| err.to_string()));
+                if let Err(err) = read {
+                    warn!("Could not read {:?}: {}", authors_path, err);
+                    return None;
+                }
+
+                // As when building, the authors that are not described are skipped
+                authors_hist.build().get(&Author::new(name)).cloned()?
+            }
+        };
+
+        info!("New author {:?}", name);
+        Some(db.intern_author(author))
+    }
+}
+
+// Give the database the new contents of a source
+fn touch(db: &mut MainDatabase, id: SourceId) {
+    info!("Source {:?} changed", id);
+    let path = match db.sources().get_by_right(&id) {
+        Some(path) => path.clone(),
+        None => return warn!("Unknown source {:?}", id),
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) => return warn!("Could not read {:?}: {}", path, err),
+    };
+
+    // Note, the parsing loaded from a snapshot is now outdated
+    Arc::make_mut(&mut db.preparsed).remove(&id);
+    db.set_source_contents(id, Some(Arc::new(contents)));
+    // The words might have moved away from the lemmas chosen for them
+    overrides::prune(db, id);
+}
+
+fn remove_source(db: &mut MainDatabase, id: SourceId) {
+    info!("Source {:?} removed", id);
+    let author = db.associated_author(id);
+    let mut sources = (*db.associated_sources(author)).clone();
+    sources.remove(&id);
+
+    // Ensure no childless authors arise
+    if sources.is_empty() {
+        db.authors.remove_by_right(&author);
+    }
+
+    remove_from_work(db, id);
+    db.fs.remove_source(id);
+    Arc::make_mut(&mut db.preparsed).remove(&id);
+    db.set_associated_sources(author, Arc::new(sources));
+
+    // Note, the overrides of a removed source are not saved anymore
+    let mut overrides = (*db.overrides()).clone();
+    if overrides.remove_source(id) {
+        db.set_overrides(Arc::new(overrides));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::driver_init;
+    use super::super::test_corpus::TestCorpus;
+    use super::*;
+    use crate::query_system::lit_subset::LitSubset;
+    use crate::query_system::types::Lemma;
+    use salsa::ParallelDatabase;
+    use std::fs;
+
+    // A small corpus, with a single source
+    fn make_corpus(name: &str) -> TestCorpus {
+        TestCorpus::new(name)
+            .with_lemmatizer("arma,arma,arma\nuirum,uirum,uir\ncano,cano,cano\n")
+            .with_source("Vergilius/aeneid.txt", "arma uirumque cano")
+    }
+
+    fn count(db: &MainDatabase, word: &str) -> usize {
+        let lemma = Lemma(db.lookup_interned_word(word.into()).unwrap());
+        let all = LitSubset::from_authors(db.authors().right_values(), &db.snapshot());
+        db.lemma_occurrences_subset(lemma, all).len()
+    }
+
+    #[test]
+    fn test_edit_source() {
+        let corpus = make_corpus("watch_edit");
+        let dir = corpus.dir();
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+        assert_eq!(count(&db, "cano"), 1);
+
+        let path = dir.join("corpus").join("Vergilius").join("aeneid.txt");
+        fs::write(&path, "cano cano").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Write(path));
+        assert_eq!(count(&db, "cano"), 2);
+        assert_eq!(count(&db, "arma"), 0);
+    }
+
+    #[test]
+    fn test_edit_preparsed_source() {
+        let corpus = make_corpus("watch_preparsed");
+        let dir = corpus.dir();
+        let georgics = dir.join("corpus").join("Vergilius").join("georgics.txt");
+        fs::write(&georgics, "arma").unwrap();
+        let config = corpus.config().with_snapshot(dir.join("corpus.snapshot"));
+        driver_init(config.clone()).unwrap();
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+        assert_eq!(count(&db, "arma"), 2);
+
+        // Only the source that changed is read again, the others are not even looked at
+        fs::remove_file(&georgics).unwrap();
+        let path = dir.join("corpus").join("Vergilius").join("aeneid.txt");
+        fs::write(&path, "arma arma").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Write(path));
+        assert_eq!(count(&db, "arma"), 3);
+    }
+
+    #[test]
+    fn test_edit_overridden_source() {
+        let corpus = TestCorpus::new("watch_overrides")
+            .with_lemmatizer("uenit,uenit,uenio\nuenit,uenit,ueneo\nroma,roma,roma\n")
+            .with_source("Vergilius/aeneid.txt", "uenit")
+            .with_file("overrides.tsv", "Vergilius/aeneid.txt\t0\t0\tueneo\n");
+        let dir = corpus.dir();
+        let config = corpus.config().with_overrides(dir.join("overrides.tsv"));
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+        let source = *db.sources().right_values().next().unwrap();
+        assert_eq!(db.overrides().source(source).len(), 1);
+
+        // The lemma chosen for uenit is not given to the word now in its place
+        let path = dir.join("corpus").join("Vergilius").join("aeneid.txt");
+        fs::write(&path, "roma uenit").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Write(path));
+        assert!(db.overrides().source(source).is_empty());
+        assert_eq!(count(&db, "roma"), 1);
+        assert_eq!(count(&db, "uenio"), 1);
+    }
+
+    #[test]
+    fn test_new_and_removed_sources() {
+        let corpus = make_corpus("watch_new");
+        let dir = corpus.dir();
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+
+        // A new source for a known author
+        let georgics = dir.join("corpus").join("Vergilius").join("georgics.txt");
+        fs::write(&georgics, "arma").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(georgics.clone()));
+        assert_eq!(db.sources().len(), 2);
+        assert_eq!(count(&db, "arma"), 2);
+
+        // A new author
+        let ovid = dir.join("corpus").join("Ovidius");
+        fs::create_dir(&ovid).unwrap();
+        fs::write(ovid.join("amores.txt"), "arma cano").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(ovid.clone()));
+        assert_eq!(db.authors().len(), 2);
+        assert_eq!(count(&db, "arma"), 3);
+
+        fs::remove_file(&georgics).unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Remove(georgics));
+        assert_eq!(db.sources().len(), 2);
+        assert_eq!(count(&db, "arma"), 2);
+
+        fs::remove_dir_all(&ovid).unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Remove(ovid));
+        assert_eq!(db.authors().len(), 1);
+        assert_eq!(count(&db, "arma"), 1);
+    }
+
+    #[test]
+    fn test_new_source_after_removal() {
+        let corpus = make_corpus("watch_reuse");
+        let dir = corpus.dir();
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+
+        let georgics = dir.join("corpus").join("Vergilius").join("georgics.txt");
+        fs::write(&georgics, "arma").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(georgics.clone()));
+        let removed = *db.sources().get_by_left(&georgics).unwrap();
+        assert_eq!(db.source_format(removed), SourceFormat::Text);
+        fs::remove_file(&georgics).unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Remove(georgics));
+
+        // The new source is not taken for the one that was removed
+        let eclogues = dir.join("corpus").join("Vergilius").join("eclogues.conllu");
+        fs::write(&eclogues, "1\tarma\tarma\tNOUN\t_\t_\t0\troot\t_\t_\n").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(eclogues.clone()));
+        let added = *db.sources().get_by_left(&eclogues).unwrap();
+        assert_ne!(added, removed);
+        assert_eq!(db.source_format(added), SourceFormat::Treebank);
+        assert_eq!(count(&db, "arma"), 2);
+    }
+
+    #[test]
+    fn test_new_work_after_removal() {
+        let corpus = make_corpus("watch_reuse_work");
+        let dir = corpus.dir();
+        let config = corpus.config();
+        let mut db = driver_init(config.clone()).unwrap();
+        let watcher = CorpusWatcher::new(&mut db, &config).unwrap();
+
+        let georgics = dir.join("corpus").join("Vergilius").join("georgics.txt");
+        fs::write(&georgics, "arma").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(georgics.clone()));
+        let removed = db.associated_work(*db.sources().get_by_left(&georgics).unwrap());
+        fs::remove_file(&georgics).unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Remove(georgics));
+
+        // The new work is not taken for the one that was removed
+        let eclogues = dir.join("corpus").join("Vergilius").join("eclogues.txt");
+        fs::write(&eclogues, "arma").unwrap();
+        watcher.apply(&mut db, DebouncedEvent::Create(eclogues.clone()));
+        let added = db.associated_work(*db.sources().get_by_left(&eclogues).unwrap());
+        assert_ne!(added, removed);
+        assert_eq!(db.work_sources(added).len(), 1);
+    }
+}
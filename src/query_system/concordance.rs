@@ -0,0 +1,258 @@
+//! Keyword in context (KWIC) concordances
+//! The context of an occurrence is taken from the sequence of all the words in its source,
+//! so that it can extend over multiple lines
+
+use super::ids::{FormDataId, SourceId};
+use super::sources::SourcesDatabase;
+use crate::latin_utilities::tokenizer::Token;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// How much context to take around an occurrence
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Window {
+    /// A number of words on each side
+    Tokens(usize),
+    /// A number of lines on each side, in addition to the one of the occurrence
+    Lines(usize),
+}
+
+/// How to order the lines of a concordance
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ContextSort {
+    /// In order of appearance, by source
+    Textual,
+    /// By the words on the left, starting from the closest to the keyword
+    Left,
+    /// By the words on the right, starting from the closest to the keyword
+    Right,
+}
+
+/// An occurrence together with its context
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConcordanceLine {
+    occurrence: FormDataId,
+    left: Vec<String>,
+    keyword: String,
+    right: Vec<String>,
+}
+
+impl ConcordanceLine {
+    /// The occurrence this line is about
+    pub fn occurrence(&self) -> FormDataId {
+        self.occurrence
+    }
+
+    /// The words preceding the keyword
+    pub fn left(&self) -> String {
+        self.left.join(" ")
+    }
+
+    /// The occurrence as it appears in the text
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    /// The words following the keyword
+    pub fn right(&self) -> String {
+        self.right.join(" ")
+    }
+}
+
+// Index of the first token at or after a position
+fn lower_bound(tokens: &[Token], line_no: usize, index: usize) -> usize {
+    match tokens.binary_search_by_key(&(line_no, index), |t| (t.line_no(), t.index())) {
+        Ok(i) | Err(i) => i,
+    }
+}
+
+fn words(tokens: &[Token]) -> Vec<String> {
+    tokens.iter().map(|t| t.word().to_string()).collect()
+}
+
+// Compare contexts word by word, ignoring case
+fn compare_words<'a>(
+    a: impl Iterator<Item = &'a String>,
+    b: impl Iterator<Item = &'a String>,
+) -> Ordering {
+    a.map(|w| w.to_lowercase()).cmp(b.map(|w| w.to_lowercase()))
+}
+
+/// Build the concordance of some occurrences
+pub fn concordance(
+    db: &impl SourcesDatabase,
+    occurrences: impl IntoIterator<Item = FormDataId>,
+    window: Window,
+    sort: ContextSort,
+) -> Vec<ConcordanceLine> {
+    // Group by source, in textual order
+    let mut by_source: BTreeMap<SourceId, Vec<_>> = BTreeMap::new();
+    for id in occurrences {
+        let fd = db.lookup_intern_form_data(id);
+        by_source
+            .entry(fd.source())
+            .or_insert_with(Vec::new)
+            .push((fd.line_no(), fd.index(), id));
+    }
+
+    let mut res = Vec::new();
+    for (source, mut positions) in by_source {
+        positions.sort();
+        let tokens = db.source_tokens(source);
+
+        for (line_no, index, occurrence) in positions {
+            let pos = lower_bound(&tokens, line_no, index);
+            let keyword = match tokens.get(pos) {
+                Some(token) => token.word().to_string(),
+                None => continue,
+            };
+
+            let (start, end) = match window {
+                Window::Tokens(n) => (pos.saturating_sub(n), (pos + 1 + n).min(tokens.len())),
+                Window::Lines(n) => (
+                    lower_bound(&tokens, line_no.saturating_sub(n), 0),
+                    lower_bound(&tokens, line_no + n + 1, 0),
+                ),
+            };
+
+            res.push(ConcordanceLine {
+                occurrence,
+                left: words(&tokens[start..pos]),
+                keyword,
+                right: words(&tokens[pos + 1..end]),
+            });
+        }
+    }
+
+    // Note, the sort is stable, so ties stay in textual order
+    match sort {
+        ContextSort::Textual => {}
+        ContextSort::Left => {
+            res.sort_by(|a, b| compare_words(a.left.iter().rev(), b.left.iter().rev()))
+        }
+        ContextSort::Right => res.sort_by(|a, b| compare_words(a.right.iter(), b.right.iter())),
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::query_system::mock::{make_mock, MockDatabase};
+    use crate::query_system::types::InternDatabase;
+    use crate::word_db::WordDatabase;
+
+    const TEXT: &str = "Arma uirumque cano, Troiae qui primus ab oris\n\
+                        Italiam fato profugus Lauiniaque uenit\n\
+                        litora, multum ille et terris iactatus et alto";
+
+    fn occurrences_of(db: &MockDatabase, word: &str) -> Vec<FormDataId> {
+        let source = SourceId::from_integer(0);
+        db.parse_source(source)
+            .iter()
+            .cloned()
+            .filter(|&fd| db.lookup_word_str(db.lookup_intern_form_data(fd).form().0) == word)
+            .collect()
+    }
+
+    fn contexts(lines: &[ConcordanceLine]) -> Vec<(String, String, String)> {
+        lines
+            .iter()
+            .map(|l| (l.left(), l.keyword().to_string(), l.right()))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_window() {
+        let mut db = make_mock();
+        db.intern_source(TEXT.to_string());
+        let lines = concordance(
+            &db,
+            occurrences_of(&db, "italiam"),
+            Window::Tokens(2),
+            ContextSort::Textual,
+        );
+
+        assert_eq!(
+            contexts(&lines),
+            vec![("ab oris".into(), "Italiam".into(), "fato profugus".into())]
+        );
+    }
+
+    #[test]
+    fn tokens_window_bounds() {
+        let mut db = make_mock();
+        db.intern_source(TEXT.to_string());
+        let lines = concordance(
+            &db,
+            occurrences_of(&db, "arma"),
+            Window::Tokens(3),
+            ContextSort::Textual,
+        );
+
+        assert_eq!(
+            contexts(&lines),
+            vec![("".into(), "Arma".into(), "uirumque cano Troiae".into())]
+        );
+    }
+
+    #[test]
+    fn lines_window() {
+        let mut db = make_mock();
+        db.intern_source(TEXT.to_string());
+        let lines = concordance(
+            &db,
+            occurrences_of(&db, "uenit"),
+            Window::Lines(0),
+            ContextSort::Textual,
+        );
+
+        assert_eq!(
+            contexts(&lines),
+            vec![(
+                "Italiam fato profugus Lauiniaque".into(),
+                "uenit".into(),
+                "".into()
+            )]
+        );
+
+        let lines = concordance(
+            &db,
+            occurrences_of(&db, "uenit"),
+            Window::Lines(1),
+            ContextSort::Textual,
+        );
+        assert_eq!(lines[0].left().split(' ').count(), 12);
+        assert_eq!(lines[0].right().split(' ').count(), 8);
+    }
+
+    #[test]
+    fn sorting() {
+        let mut db = make_mock();
+        db.intern_source(TEXT.to_string());
+        let occurrences = occurrences_of(&db, "et");
+
+        let textual = concordance(
+            &db,
+            occurrences.clone(),
+            Window::Tokens(1),
+            ContextSort::Textual,
+        );
+        assert_eq!(textual[0].left(), "ille");
+        assert_eq!(textual[1].left(), "iactatus");
+
+        let left = concordance(
+            &db,
+            occurrences.clone(),
+            Window::Tokens(1),
+            ContextSort::Left,
+        );
+        assert_eq!(left[0].left(), "iactatus");
+
+        let right = concordance(&db, occurrences, Window::Tokens(1), ContextSort::Right);
+        assert_eq!(right[0].right(), "alto");
+    }
+}
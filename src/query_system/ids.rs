@@ -31,9 +31,13 @@ macro_rules! impl_intern_key {
             pub fn from_integer(v: u32) -> Self {
                 $name::from_intern_id(InternId::from(v))
             }
+
+            pub fn as_integer(&self) -> u32 {
+                self.0.as_u32()
+            }
         }
     };
 }
 
 // TODO: Move this somewhere global?
-create_ids!(AuthorId, FormDataId, SourceId, WordId);
+create_ids!(AuthorId, FormDataId, SourceId, WordId, WorkId);
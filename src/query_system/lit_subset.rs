@@ -1,6 +1,6 @@
-use super::ids::{AuthorId, SourceId};
+use super::ids::{AuthorId, SourceId, WorkId};
 use super::traits::*;
-use crate::authors_chrono::{Author, TimeSpan};
+use crate::authors_chrono::{Author, Certainty, TimeSpan};
 
 use std::collections::BTreeSet;
 use std::sync::Arc;
@@ -33,18 +33,50 @@ impl LitSubset {
         }
     }
 
+    pub fn from_works<'a>(
+        works: impl IntoIterator<Item = &'a WorkId>,
+        db: &salsa::Snapshot<impl MainDatabase>,
+    ) -> Self {
+        let mut sources = BTreeSet::new();
+
+        for src in works.into_iter().map(|w| db.work_sources(*w)) {
+            sources.extend(src.iter())
+        }
+
+        LitSubset {
+            sources: Arc::new(sources),
+        }
+    }
+
+    /// The sources of the authors that were written in a time span
+    /// Note, the sources of a work with a date of its own are dated by it, rather than by their author
     pub fn from_timespan<'a, 'b>(
         span: &TimeSpan,
         authors: impl IntoIterator<Item = (&'a Author, &'b AuthorId)>,
         db: &salsa::Snapshot<impl MainDatabase>,
     ) -> Self {
-        LitSubset::from_authors(
-            authors
-                .into_iter()
-                .filter(|(a, _)| a.in_timespan(span))
-                .map(|(_, i)| i),
-            db,
-        )
+        Self::from_timespan_with(span, Certainty::Terminus, authors, db)
+    }
+
+    /// The same as `from_timespan`, leaving out the sources whose dates are less certain
+    pub fn from_timespan_with<'a, 'b>(
+        span: &TimeSpan,
+        certainty: Certainty,
+        authors: impl IntoIterator<Item = (&'a Author, &'b AuthorId)>,
+        db: &salsa::Snapshot<impl MainDatabase>,
+    ) -> Self {
+        let mut sources = BTreeSet::new();
+
+        for (author, &id) in authors {
+            sources.extend(db.associated_sources(id).iter().filter(|&&source| {
+                let work = db.lookup_intern_work(db.associated_work(source));
+                work.in_timespan_with(author, span, certainty)
+            }));
+        }
+
+        LitSubset {
+            sources: Arc::new(sources),
+        }
     }
 
     pub fn sources(&self) -> &BTreeSet<SourceId> {
@@ -56,4 +88,21 @@ impl LitSubset {
             sources: Arc::new(self.sources.difference(&*other.sources).cloned().collect()),
         }
     }
+
+    pub fn intersection(self, other: &LitSubset) -> LitSubset {
+        LitSubset {
+            sources: Arc::new(
+                self.sources
+                    .intersection(&*other.sources)
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn union(self, other: &LitSubset) -> LitSubset {
+        LitSubset {
+            sources: Arc::new(self.sources.union(&*other.sources).cloned().collect()),
+        }
+    }
 }
@@ -3,11 +3,13 @@
 
 use super::ids::{FormDataId, SourceId};
 use super::lit_subset::LitSubset;
+use super::overrides::{Overrides, SourceOverrides};
 use super::sources::SourcesDatabase;
 use super::types::InternDatabase;
-use super::types::{Form, Lemma};
+use super::types::{Form, FormData, Lemma};
 
-use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
+use crate::latin_lemmatizer::compressed::{CompressedLemmatizer, LemmatizerView};
+use crate::latin_utilities::Profile;
 
 use log::info;
 use std::collections::{HashMap, HashSet};
@@ -22,6 +24,20 @@ pub trait IntermediateDatabase: SourcesDatabase + InternDatabase {
     #[salsa::input]
     fn lemmatizer(&self) -> Arc<CompressedLemmatizer>;
 
+    /// The readings that only hold in some of the sources
+    #[salsa::input]
+    fn scoped_lemmatizers(&self) -> Arc<ScopedLemmatizers>;
+
+    /// The lemmatizer the words of a source are read with
+    fn source_lemmatizer(&self, id: SourceId) -> LemmatizerView;
+
+    /// The lemmas that were chosen by hand for some occurrences
+    #[salsa::input]
+    fn overrides(&self) -> Arc<Overrides>;
+
+    /// The lemmas that were chosen by hand in a source, or that came with its words
+    fn source_overrides(&self, id: SourceId) -> Arc<SourceOverrides>;
+
     /// Parse multiple sources, and combine the result
     #[salsa::dependencies]
     fn parse_subset(&self, subset: LitSubset) -> Arc<HashSet<FormDataId>>;
@@ -66,6 +82,36 @@ pub trait IntermediateDatabase: SourcesDatabase + InternDatabase {
         -> Arc<HashSet<FormDataId>>;
 }
 
+/// The lemmatizers that only apply to some of the sources, rather than to the whole corpus
+#[derive(Debug, Default, Clone)]
+pub struct ScopedLemmatizers {
+    profiles: HashMap<Profile, Arc<CompressedLemmatizer>>,
+    treebanks: HashMap<SourceId, Arc<CompressedLemmatizer>>,
+}
+
+impl ScopedLemmatizers {
+    /// The lemmatizer in the spelling of the authors with some rules of their own
+    /// Note, it takes the place of the one of the corpus for their sources
+    pub fn with_profile(mut self, profile: Profile, lemm: Arc<CompressedLemmatizer>) -> Self {
+        self.profiles.insert(profile, lemm);
+        self
+    }
+
+    /// The (form, lemma) pairs of a treebank, which are known in its words only
+    pub fn with_treebank(mut self, source: SourceId, lemm: Arc<CompressedLemmatizer>) -> Self {
+        self.treebanks.insert(source, lemm);
+        self
+    }
+
+    pub fn profiles(&self) -> &HashMap<Profile, Arc<CompressedLemmatizer>> {
+        &self.profiles
+    }
+
+    pub fn treebanks(&self) -> &HashMap<SourceId, Arc<CompressedLemmatizer>> {
+        &self.treebanks
+    }
+}
+
 // Sum sets of sources together
 fn combine<'a, T: Hash + Eq + Clone + 'a>(
     sets: impl IntoIterator<Item = Arc<HashSet<T>>>,
@@ -80,46 +126,103 @@ fn combine<'a, T: Hash + Eq + Clone + 'a>(
     Arc::new(res)
 }
 
-// Lemmatizes a form, in an interface that works well with above
-fn lemmatize_form(db: &impl IntermediateDatabase, form: Form) -> HashSet<Lemma> {
-    let lemm = db.lemmatizer();
+fn source_lemmatizer(db: &impl IntermediateDatabase, id: SourceId) -> LemmatizerView {
+    let scoped = db.scoped_lemmatizers();
+    let normalization = db.normalization();
+    // Note, the author is only needed if some of them have rules of their own
+    let base = if normalization.authors().is_empty() {
+        None
+    } else {
+        normalization
+            .authors()
+            .get(&db.associated_author(id))
+            .and_then(|profile| scoped.profiles.get(profile).cloned())
+    };
+
+    LemmatizerView::new(
+        base.unwrap_or_else(|| db.lemmatizer()),
+        scoped.treebanks.get(&id).cloned(),
+    )
+}
 
-    lemm.get_possible_lemmas(form.0)
-        .cloned()
-        .unwrap_or_else(HashSet::new)
+// Lemmatizes a form, in an interface that works well with above
+fn lemmatize_form(db: &impl IntermediateDatabase, source: SourceId, form: Form) -> HashSet<Lemma> {
+    db.source_lemmatizer(source)
+        .get_possible_lemmas(form.0)
         .into_iter()
         .map(Lemma)
         .collect()
 }
 
-fn get_forms_lemma(db: &impl IntermediateDatabase, lemma: Lemma) -> HashSet<Form> {
-    let lemm = db.lemmatizer();
-
-    lemm.get_possible_forms(lemma.0)
-        .cloned()
-        .unwrap_or_else(HashSet::new)
-        .into_iter()
-        .map(Form)
-        .collect()
+/// The lemmas an occurrence is counted under: the one chosen by hand, or else those of its form
+pub fn lemmatize_occurrence(
+    db: &impl IntermediateDatabase,
+    overrides: &SourceOverrides,
+    fd: &FormData,
+) -> HashSet<Lemma> {
+    match overrides.get(&(fd.line_no(), fd.index())) {
+        Some(&lemma) => std::iter::once(lemma).collect(),
+        None => lemmatize_form(db, fd.source(), fd.form()),
+    }
 }
 
 fn parse_subset(db: &impl IntermediateDatabase, subset: LitSubset) -> Arc<HashSet<FormDataId>> {
     combine(subset.sources().iter().map(|s| db.parse_source(*s)))
 }
 
+/// The lemmas chosen by hand in a source that are readings of the words at their places
+/// Note, the others were chosen for words that have since moved (i.e. once a line was edited)
+pub fn valid_overrides(db: &impl IntermediateDatabase, id: SourceId) -> Arc<SourceOverrides> {
+    let chosen = db.overrides().source(id);
+    if chosen.is_empty() {
+        return chosen;
+    }
+
+    let forms: HashMap<_, _> = db
+        .source_forms(id)
+        .iter()
+        .map(|fd| ((fd.line_no(), fd.index()), fd.form()))
+        .collect();
+    Arc::new(
+        chosen
+            .iter()
+            .filter(|(key, lemma)| {
+                forms
+                    .get(key)
+                    .is_some_and(|&form| lemmatize_form(db, id, form).contains(*lemma))
+            })
+            .map(|(&key, &lemma)| (key, lemma))
+            .collect(),
+    )
+}
+
+fn source_overrides(db: &impl IntermediateDatabase, id: SourceId) -> Arc<SourceOverrides> {
+    let chosen = valid_overrides(db, id);
+    let gold = db.source_gold_lemmas(id);
+    if gold.is_empty() {
+        return chosen;
+    }
+
+    // Note, the lemmas chosen by hand take precedence
+    let mut res = (*gold).clone();
+    res.extend(chosen.iter().map(|(&key, &lemma)| (key, lemma)));
+    Arc::new(res)
+}
+
 fn source_tree(
     db: &impl IntermediateDatabase,
     id: SourceId,
 ) -> Arc<HashMap<Lemma, HashMap<Form, Vec<FormDataId>>>> {
     let data = db.parse_source(id);
+    let overrides = db.source_overrides(id);
     let mut res = HashMap::new();
     for fd_id in data.iter() {
-        let form = db.lookup_intern_form_data(*fd_id).form();
-        let lemmas = lemmatize_form(db, form);
+        let fd = db.lookup_intern_form_data(*fd_id);
+        let lemmas = lemmatize_occurrence(db, &overrides, &fd);
         for lemma in lemmas.iter() {
             res.entry(*lemma)
                 .or_insert_with(HashMap::new)
-                .entry(form)
+                .entry(fd.form())
                 .or_insert_with(Vec::new)
                 .push(*fd_id);
         }
@@ -163,12 +266,8 @@ fn forms_in_subset(db: &impl IntermediateDatabase, subset: LitSubset) -> Arc<Has
 }
 
 fn lemmas_in_source(db: &impl IntermediateDatabase, source: SourceId) -> Arc<HashSet<Lemma>> {
-    combine(
-        db.forms_in_source(source)
-            .iter()
-            .map(|&f| lemmatize_form(db, f))
-            .map(Arc::new),
-    )
+    // Note, the tree already accounts for the lemmas chosen by hand
+    Arc::new(db.source_tree(source).keys().cloned().collect())
 }
 
 fn lemmas_in_subset(db: &impl IntermediateDatabase, subset: LitSubset) -> Arc<HashSet<Lemma>> {
@@ -205,16 +304,12 @@ fn lemma_occurrences_subset(
         subset.sources().len()
     );
 
-    let forms = get_forms_lemma(db, id);
-
-    Arc::new(
-        db.parse_subset(subset)
-            .iter()
-            .filter(|&fd| {
-                let form = db.lookup_intern_form_data(*fd).form();
-                forms.contains(&form)
-            })
-            .cloned()
-            .collect(),
-    )
+    // Note, the trees account for the lemmatizers of the sources, and for the lemmas chosen by hand
+    let mut res = HashSet::new();
+    for source in subset.sources() {
+        if let Some(forms) = db.source_tree(*source).get(&id) {
+            res.extend(forms.values().flatten().cloned());
+        }
+    }
+    Arc::new(res)
 }
@@ -1,27 +1,42 @@
 //! Mocking facilities for testing
 
 use super::gc::GCollectable;
-use super::ids::AuthorId;
-use super::middle::IntermediateQueries;
-use super::sources::SourcesQueryGroup;
-use super::traits::{AuthorInternDatabase, IntermediateDatabase};
-use super::types::InternersGroup;
+use super::ids::{AuthorId, SourceId, WorkId};
+use super::middle::{IntermediateQueries, ScopedLemmatizers};
+use super::ngrams::NgramsQueryGroup;
+use super::overrides::Overrides;
+use super::sources::{Normalization, PreparsedSources, SourcesDatabase, SourcesQueryGroup};
+use super::traits::{AuthorInternDatabase, IntermediateDatabase, WorkInternDatabase};
+use super::types::{FormData, InternersGroup};
+use super::weights::WeightsQueryGroup;
 use super::MainQueries;
-use crate::authors_chrono::Author;
-use crate::filesystem::{GetFileSystem, MockFileSystem};
+use crate::authors_chrono::{Author, Work};
+use crate::filesystem::{FileSystem, MockFileSystem, SourceFormat};
 use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
+use crate::latin_lemmatizer::morphology::Analysis;
+use crate::latin_lemmatizer::NaiveLemmatizer;
 use crate::latin_utilities::NormalizedLatinString;
-use crate::word_db::{WordDatabase, WordDb, WordId};
+use crate::word_db::{WordDatabase, WordDb, WordId, WordPattern};
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
 /// A simplified database, which we use for testing
-#[salsa::database(SourcesQueryGroup, InternersGroup, MainQueries, IntermediateQueries)]
+#[salsa::database(
+    SourcesQueryGroup,
+    InternersGroup,
+    MainQueries,
+    IntermediateQueries,
+    NgramsQueryGroup,
+    WeightsQueryGroup
+)]
 pub struct MockDatabase {
     runtime: salsa::Runtime<MockDatabase>,
     mock: Author,
+    works: Vec<Work>,
     fs: MockFileSystem,
     word_db: WordDb,
+    closed_vocabulary: bool,
 }
 
 /// A mock database to be used for preliminary testing
@@ -32,14 +47,92 @@ pub fn make_mock() -> MockDatabase {
     res
 }
 
+/// A mock database that only knows about the specified words
+/// (by default, any word that is looked up is considered known)
+pub fn make_mock_with_vocabulary<'a>(words: impl IntoIterator<Item = &'a str>) -> MockDatabase {
+    let mut res = make_mock();
+    for word in words {
+        res.intern_word(word.into());
+    }
+    res.closed_vocabulary = true;
+    res
+}
+
+/// A mock database with a lemmatizer built from (form, lemma) pairs,
+/// that only knows about the words in it
+pub fn make_mock_with_lemmas<'a>(
+    pairs: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> MockDatabase {
+    let mut mapping = HashMap::new();
+    for (form, lemma) in pairs {
+        mapping
+            .entry(form.into())
+            .or_insert_with(HashSet::new)
+            .insert(lemma.into());
+    }
+
+    make_mock_with_lemmatizer(NaiveLemmatizer::new(mapping))
+}
+
+/// A mock database with the given lemmatizer, that only knows about the words in it
+pub fn make_mock_with_lemmatizer(lemm: NaiveLemmatizer) -> MockDatabase {
+    let mut res = MockDatabase::new();
+    let lemm = CompressedLemmatizer::new(lemm, &res);
+    res.set_lemmatizer(Arc::new(lemm));
+    res.closed_vocabulary = true;
+    res
+}
+
+/// A mock database with a lemmatizer built from (form, lemma, tag) triples,
+/// with the tags read as morphological analyses
+pub fn make_mock_with_analyses<'a>(
+    triples: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>,
+) -> MockDatabase {
+    let mut mapping = HashMap::new();
+    let mut analyses = HashMap::new();
+    for (form, lemma, tag) in triples {
+        mapping
+            .entry(form.into())
+            .or_insert_with(HashSet::new)
+            .insert(lemma.into());
+        analyses
+            .entry((form.into(), lemma.into()))
+            .or_insert_with(BTreeSet::new)
+            .insert(Analysis::from_tag(tag));
+    }
+
+    make_mock_with_lemmatizer(NaiveLemmatizer::new_with_analyses(mapping, analyses))
+}
+
 impl MockDatabase {
+    /// Intern a source in the CoNLL-U format
+    pub fn intern_treebank(&mut self, content: String) -> SourceId {
+        let id = self.fs.intern_treebank(content);
+        self.set_source_contents(id, None);
+        id
+    }
+
+    /// Intern a source in TEI XML
+    pub fn intern_tei(&mut self, content: String) -> SourceId {
+        let id = self.fs.intern_tei(content);
+        self.set_source_contents(id, None);
+        id
+    }
+
     pub fn new() -> Self {
-        MockDatabase {
+        let mut res = MockDatabase {
             runtime: salsa::Runtime::default(),
             mock: Author::new("Mock"),
+            works: Vec::new(),
             fs: MockFileSystem::default(),
             word_db: WordDb::default(),
-        }
+            closed_vocabulary: false,
+        };
+        res.set_vocabulary_version(0);
+        res.set_normalization(Arc::new(Normalization::default()));
+        res.set_overrides(Arc::new(Overrides::new()));
+        res.set_scoped_lemmatizers(Arc::new(ScopedLemmatizers::default()));
+        res
     }
 }
 
@@ -53,21 +146,58 @@ impl AuthorInternDatabase for MockDatabase {
     }
 }
 
+impl WorkInternDatabase for MockDatabase {
+    fn intern_work(&mut self, work: Work) -> WorkId {
+        let index = match self.works.iter().position(|w| *w == work) {
+            Some(index) => index,
+            None => {
+                self.works.push(work);
+                self.works.len() - 1
+            }
+        };
+        WorkId::from_integer(index as u32)
+    }
+
+    fn lookup_intern_work(&self, id: WorkId) -> &Work {
+        &self.works[id.as_integer() as usize]
+    }
+}
+
 impl Default for MockDatabase {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl GetFileSystem for MockDatabase {
-    type Fs = MockFileSystem;
+impl FileSystem for MockDatabase {
+    type Source = String;
+
+    fn intern_source(&mut self, path: Self::Source) -> SourceId {
+        let id = self.fs.intern_source(path);
+        self.set_source_contents(id, None);
+        id
+    }
+
+    fn watch(&self, id: SourceId) {
+        self.fs.watch(id)
+    }
+
+    fn load(&self, id: SourceId) -> String {
+        self.fs.load(id)
+    }
+
+    fn format(&self, id: SourceId) -> SourceFormat {
+        self.fs.format(id)
+    }
 
-    fn filesystem(&self) -> &Self::Fs {
-        &self.fs
+    fn load_citations(&self, id: SourceId) -> Option<String> {
+        self.fs.load_citations(id)
     }
+}
 
-    fn filesystem_mut(&mut self) -> &mut Self::Fs {
-        &mut self.fs
+impl PreparsedSources for MockDatabase {
+    fn preparsed(&self, _: SourceId) -> Option<Arc<Vec<FormData>>> {
+        None
     }
 }
 
@@ -85,8 +215,31 @@ impl WordDatabase for MockDatabase {
     }
 
     fn lookup_interned_word(&self, s: NormalizedLatinString) -> Option<WordId> {
+        if self.closed_vocabulary {
+            return self.word_db.lookup_interned_word(s);
+        }
+
         Some(self.word_db.intern_word(s))
     }
+
+    fn lookup_interned_str(&self, s: &str) -> Option<WordId> {
+        if self.closed_vocabulary {
+            return self.word_db.lookup_interned_str(s);
+        }
+
+        Some(
+            self.word_db
+                .intern_word(NormalizedLatinString::instantiate(s)),
+        )
+    }
+
+    fn lookup_word_str(&self, id: WordId) -> &str {
+        self.word_db.lookup_word_str(id)
+    }
+
+    fn search_words(&self, pattern: &WordPattern) -> Vec<WordId> {
+        self.word_db.search_words(pattern)
+    }
 }
 
 impl salsa::Database for MockDatabase {
@@ -104,8 +257,10 @@ impl salsa::ParallelDatabase for MockDatabase {
         salsa::Snapshot::new(MockDatabase {
             runtime: self.runtime.snapshot(self),
             mock: self.mock.clone(),
+            works: self.works.clone(),
             fs: self.fs.clone(),
             word_db: self.word_db.clone(),
+            closed_vocabulary: self.closed_vocabulary,
         })
     }
 }
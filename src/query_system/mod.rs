@@ -3,20 +3,29 @@
 //! The ids contains all the various id types that are used directly into salsa
 //! Types are all the expanded types those ids refer to
 
+pub mod concordance;
 pub mod gc;
 pub mod ids;
 pub mod lit_subset;
 pub mod middle;
 pub mod mock;
+pub mod morphology;
+pub mod ngrams;
+pub mod overrides;
+pub mod parallels;
 pub mod sources;
 pub mod traits;
 pub mod types;
+pub mod variants;
+pub mod weights;
 
 use ids::*;
 use lit_subset::LitSubset;
-use std::collections::{HashMap, HashSet};
+use morphology::Morphology;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use types::{Form, Lemma};
+use weights::{CountMode, Weight};
 
 /// The main trait, which any database should implement
 #[salsa::query_group(MainQueries)]
@@ -24,26 +33,76 @@ pub trait MainDatabase:
     sources::SourcesDatabase
     + types::InternDatabase
     + types::AuthorInternDatabase
+    + types::WorkInternDatabase
     + middle::IntermediateDatabase
+    + ngrams::NgramsDatabase
+    + weights::WeightsDatabase
     + gc::GCollectable
     + salsa::Database
     + salsa::ParallelDatabase
 {
     // TODO, this is really slow (I think? It is)
 
-    /// Count the number of occurrences of lemma in a subset of the literature
-    fn count_lemma_occurrences_subset(&self, id: Lemma, subset: LitSubset) -> usize;
+    /// Count the number of occurrences of lemma in a subset of the literature,
+    /// with the ambiguous ones counted according to the mode
+    fn count_lemma_occurrences_subset(
+        &self,
+        id: Lemma,
+        subset: LitSubset,
+        mode: CountMode,
+    ) -> Weight;
 
     /// Count the number of occurrences of a form in a subset of the literature
     fn count_form_occurrences_subset(&self, id: Form, subset: LitSubset) -> usize;
 
     fn intersect_sources(&self, sources: LitSubset, subset: LitSubset) -> Arc<HashSet<Lemma>>;
 
-    fn authors_count(&self, sub: LitSubset) -> Arc<HashMap<AuthorId, usize>>;
+    /// Count the occurrences of each author in a subset of the literature,
+    /// with the ambiguous ones counted according to the mode
+    fn authors_count(&self, sub: LitSubset, mode: CountMode) -> Arc<HashMap<AuthorId, Weight>>;
+
+    /// Count the occurrences of each work in a subset of the literature
+    fn works_count(&self, sub: LitSubset) -> Arc<HashMap<WorkId, usize>>;
+
+    /// Get the occurrences in a subset of the literature with the given morphology,
+    /// in order of appearance
+    fn morphology_occurrences(
+        &self,
+        morphology: Morphology,
+        subset: LitSubset,
+    ) -> Arc<Vec<FormDataId>>;
+
+    /// Count the occurrences of each lemma in a subset of the literature
+    fn lemma_frequencies(&self, sub: LitSubset) -> Arc<HashMap<Lemma, usize>>;
+
+    /// Get the lemmas that appear in each line of a source
+    fn line_lemmas(&self, source: SourceId) -> Arc<BTreeMap<usize, BTreeSet<Lemma>>>;
+
+    /// Group the forms of the lemmatizer in families of spelling variants, by their key
+    fn variant_families(&self) -> Arc<HashMap<String, BTreeSet<Form>>>;
+
+    /// Get the spelling variants of a form, itself included
+    fn form_variants(&self, form: Form) -> Arc<BTreeSet<Form>>;
 }
 
-fn count_lemma_occurrences_subset(db: &impl MainDatabase, id: Lemma, subset: LitSubset) -> usize {
-    db.lemma_occurrences_subset(id, subset).len()
+fn count_lemma_occurrences_subset(
+    db: &impl MainDatabase,
+    id: Lemma,
+    subset: LitSubset,
+    mode: CountMode,
+) -> Weight {
+    match mode {
+        CountMode::Every => Weight::new(db.lemma_occurrences_subset(id, subset).len() as f64),
+        CountMode::Weighted(weighting) => subset
+            .sources()
+            .iter()
+            .flat_map(|&s| {
+                db.weighted_source_counts(s, weighting.clone())
+                    .get(&id)
+                    .cloned()
+            })
+            .sum(),
+    }
 }
 
 fn count_form_occurrences_subset(db: &impl MainDatabase, id: Form, subset: LitSubset) -> usize {
@@ -100,16 +159,91 @@ fn intersect_sources(
     )
 }
 
-fn authors_count(db: &impl MainDatabase, sub: LitSubset) -> Arc<HashMap<AuthorId, usize>> {
+fn authors_count(
+    db: &impl MainDatabase,
+    sub: LitSubset,
+    mode: CountMode,
+) -> Arc<HashMap<AuthorId, Weight>> {
+    let weighting = match mode {
+        CountMode::Every => {
+            let tree = db.subset_tree(sub);
+            let mut res = HashMap::new();
+            for author in tree
+                .values()
+                .flat_map(|forms| forms.values().flatten())
+                .map(|fd_id| db.lookup_intern_form_data(*fd_id).author(db))
+            {
+                *res.entry(author).or_insert_with(Weight::default) += Weight::new(1.0);
+            }
+            return Arc::new(res);
+        }
+        CountMode::Weighted(weighting) => weighting,
+    };
+
+    // Note, each occurrence with some lemma weighs one in total
+    let mut res = HashMap::new();
+    for &source in sub.sources() {
+        let total = db
+            .weighted_source_counts(source, weighting.clone())
+            .values()
+            .cloned()
+            .sum();
+        *res.entry(db.associated_author(source))
+            .or_insert_with(Weight::default) += total;
+    }
+
+    Arc::new(res)
+}
+
+fn works_count(db: &impl MainDatabase, sub: LitSubset) -> Arc<HashMap<WorkId, usize>> {
     let tree = db.subset_tree(sub);
     let mut res = HashMap::new();
-    for author in tree
+    for work in tree
         .values()
         .flat_map(|forms| forms.values().flatten())
-        .map(|fd_id| db.lookup_intern_form_data(*fd_id).author(db))
+        .map(|fd_id| db.lookup_intern_form_data(*fd_id).work(db))
     {
-        *res.entry(author).or_insert(0) += 1;
+        *res.entry(work).or_insert(0) += 1;
     }
 
     Arc::new(res)
 }
+
+fn morphology_occurrences(
+    db: &impl MainDatabase,
+    morphology: Morphology,
+    subset: LitSubset,
+) -> Arc<Vec<FormDataId>> {
+    Arc::new(
+        subset
+            .sources()
+            .iter()
+            .flat_map(|&source| morphology::source_occurrences(db, source, &morphology))
+            .collect(),
+    )
+}
+
+fn lemma_frequencies(db: &impl MainDatabase, sub: LitSubset) -> Arc<HashMap<Lemma, usize>> {
+    let tree = db.subset_tree(sub);
+    Arc::new(
+        tree.iter()
+            .map(|(lemma, forms)| (*lemma, forms.values().map(|v| v.len()).sum()))
+            .collect(),
+    )
+}
+
+fn line_lemmas(db: &impl MainDatabase, source: SourceId) -> Arc<BTreeMap<usize, BTreeSet<Lemma>>> {
+    Arc::new(parallels::lemmas_by_line(db, source))
+}
+
+fn variant_families(db: &impl MainDatabase) -> Arc<HashMap<String, BTreeSet<Form>>> {
+    Arc::new(variants::variant_families(db))
+}
+
+fn form_variants(db: &impl MainDatabase, form: Form) -> Arc<BTreeSet<Form>> {
+    let key = crate::latin_utilities::variants::variant_key(db.lookup_word_str(form.0));
+    match db.variant_families().get(&key) {
+        Some(family) => Arc::new(family.clone()),
+        None => Arc::new(std::iter::once(form).collect()),
+    }
+}
@@ -0,0 +1,158 @@
+//! Searching the occurrences by their morphology (i.e. the perfect subjunctives of a lemma)
+//! An occurrence matches if one of the analyses of its form, as one of its lemmas, does.
+//! Note, the lemma chosen by hand for an occurrence is the only one its analyses are taken from
+
+use super::ids::{FormDataId, SourceId};
+use super::middle::IntermediateDatabase;
+use super::types::Lemma;
+use crate::latin_lemmatizer::morphology::{Feature, PartOfSpeech};
+
+use std::collections::BTreeSet;
+
+/// What to look for in the analyses of the occurrences
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
+pub struct Morphology {
+    lemma: Option<Lemma>,
+    pos: Option<PartOfSpeech>,
+    features: BTreeSet<Feature>,
+}
+
+impl Morphology {
+    /// Look for the analyses with all the features, of the part of speech if any
+    pub fn new(pos: Option<PartOfSpeech>, features: impl IntoIterator<Item = Feature>) -> Self {
+        Morphology {
+            lemma: None,
+            pos,
+            features: features.into_iter().collect(),
+        }
+    }
+
+    /// Only look among the occurrences of a lemma
+    pub fn with_lemma(mut self, lemma: Lemma) -> Self {
+        self.lemma = Some(lemma);
+        self
+    }
+}
+
+/// The occurrences of a source that match, in order of appearance
+pub fn source_occurrences(
+    db: &impl IntermediateDatabase,
+    source: SourceId,
+    morphology: &Morphology,
+) -> Vec<FormDataId> {
+    let lemm = db.source_lemmatizer(source);
+    let tree = db.source_tree(source);
+    let lemmas: Vec<_> = match morphology.lemma {
+        Some(lemma) => tree.get_key_value(&lemma).into_iter().collect(),
+        None => tree.iter().collect(),
+    };
+
+    let mut res = BTreeSet::new();
+    for (lemma, forms) in lemmas {
+        for (form, occurrences) in forms {
+            let matches = lemm
+                .get_analyses(form.0, lemma.0)
+                .iter()
+                .any(|analysis| analysis.matches(morphology.pos, &morphology.features));
+            if !matches {
+                continue;
+            }
+
+            for &id in occurrences {
+                let fd = db.lookup_intern_form_data(id);
+                res.insert((fd.line_no(), fd.index(), id));
+            }
+        }
+    }
+
+    res.into_iter().map(|(_, _, id)| id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lit_subset::LitSubset;
+    use super::super::mock::{make_mock_with_analyses, make_mock_with_lemmatizer, MockDatabase};
+    use super::super::traits::*;
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::latin_lemmatizer::parsers::csv_format;
+    use std::sync::Arc;
+
+    fn make_db() -> MockDatabase {
+        make_mock_with_analyses(vec![
+            ("amauit", "amo", "perf ind act 3 sg"),
+            ("amauerit", "amo", "perf subj act 3 sg"),
+            ("amauerit", "amo", "futperf ind act 3 sg"),
+            ("uiso", "uiso", "pres ind act 1 sg"),
+            ("uiso", "uisus", "part perf pass masc abl sg"),
+        ])
+    }
+
+    fn forms(db: &MockDatabase, source: SourceId, morphology: Morphology) -> Vec<String> {
+        let found = db.morphology_occurrences(morphology, LitSubset::from_sources(&[source]));
+        found
+            .iter()
+            .map(|&id| {
+                let form = db.lookup_intern_form_data(id).form();
+                db.lookup_word_str(form.0).to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_features() {
+        let mut db = make_db();
+        let source = db.intern_source("amauerit uiso\namauit amauerit".to_string());
+
+        let perfect_subjunctives =
+            Morphology::new(None, vec![Feature::Perfect, Feature::Subjunctive]);
+        assert_eq!(
+            forms(&db, source, perfect_subjunctives),
+            vec!["amauerit", "amauerit"]
+        );
+
+        let perfects = Morphology::new(None, vec![Feature::Perfect]);
+        assert_eq!(
+            forms(&db, source, perfects.clone()),
+            vec!["amauerit", "uiso", "amauit", "amauerit"]
+        );
+
+        let lemma = Lemma(db.lookup_interned_word("uisus".into()).unwrap());
+        let ablatives = Morphology::new(None, vec![Feature::Participle, Feature::Ablative]);
+        assert_eq!(
+            forms(&db, source, ablatives.with_lemma(lemma)),
+            vec!["uiso"]
+        );
+
+        // Once the lemma is chosen, only its analyses are considered
+        let mut overrides = (*db.overrides()).clone();
+        let uiso = Lemma(db.lookup_interned_word("uiso".into()).unwrap());
+        overrides.set(source, 0, 1, Some(uiso));
+        db.set_overrides(Arc::new(overrides));
+        assert_eq!(
+            forms(&db, source, perfects),
+            vec!["amauerit", "amauit", "amauerit"]
+        );
+    }
+
+    #[test]
+    fn test_positional_tags() {
+        let csv = "amauit,amauit,amo,V1,i1,,Vmis3s-a\n\
+                   amauerit,amauerit,amo,V1,i1,,Vmss3s-a\n\
+                   amauerit,amauerit,amo,V1,i1,,Vmit3s-a\n\
+                   amata,amata,amo,V1,i1,,Vmps-sfpb";
+        let lemm = csv_format::new().read_all(csv.as_bytes()).unwrap().build();
+        let mut db = make_mock_with_lemmatizer(lemm);
+        let source = db.intern_source("amauit amauerit\namata amauerit".to_string());
+
+        let perfect_subjunctives =
+            Morphology::new(None, vec![Feature::Perfect, Feature::Subjunctive]);
+        assert_eq!(
+            forms(&db, source, perfect_subjunctives),
+            vec!["amauerit", "amauerit"]
+        );
+
+        let ablatives = Morphology::new(Some(PartOfSpeech::Verb), vec![Feature::Ablative]);
+        assert_eq!(forms(&db, source, ablatives), vec!["amata"]);
+    }
+}
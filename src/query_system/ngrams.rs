@@ -0,0 +1,354 @@
+//! Indices of the sequences of consecutive words in the sources, used to search for phrases
+//! Two words are consecutive if nothing but whitespace and punctuation separates them,
+//! so that a word that is not known to the database breaks a sequence
+
+use super::ids::{FormDataId, SourceId};
+use super::lit_subset::LitSubset;
+use super::middle::{self, IntermediateDatabase};
+use super::types::{Form, Lemma};
+
+use log::info;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The longest n-grams that are indexed, longer phrases are checked word by word
+pub const MAX_NGRAM: usize = 5;
+
+/// A phrase to search for, either as written or by its lemmas
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Phrase {
+    Forms(Vec<Form>),
+    Lemmas(Vec<Lemma>),
+}
+
+impl Phrase {
+    /// The number of words in the phrase
+    pub fn len(&self) -> usize {
+        match self {
+            Phrase::Forms(forms) => forms.len(),
+            Phrase::Lemmas(lemmas) => lemmas.len(),
+        }
+    }
+
+    /// Is the phrase made of no words?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[salsa::query_group(NgramsQueryGroup)]
+pub trait NgramsDatabase: IntermediateDatabase {
+    /// The occurrences in a source, aligned with its tokens (`None` for the unknown words)
+    fn source_sequence(&self, source: SourceId) -> Arc<Vec<Option<FormDataId>>>;
+
+    /// Index the n-grams of forms in a source, by their start in the sequence of the source
+    fn form_ngrams(&self, source: SourceId, n: usize) -> Arc<HashMap<Vec<Form>, Vec<usize>>>;
+
+    /// Index the lemmas in a source, by their position in the sequence of the source
+    /// Note, an ambiguous form is indexed under all of its readings, one word at a time, as the
+    /// readings of a sequence of them would multiply
+    fn lemma_positions(&self, source: SourceId) -> Arc<HashMap<Lemma, Vec<usize>>>;
+
+    /// Find all the occurrences of a phrase in a subset of the literature, in textual order
+    fn phrase_occurrences(&self, phrase: Phrase, subset: LitSubset) -> Arc<Vec<Vec<FormDataId>>>;
+}
+
+fn source_sequence(db: &impl NgramsDatabase, source: SourceId) -> Arc<Vec<Option<FormDataId>>> {
+    let positions: HashMap<_, _> = db
+        .parse_source(source)
+        .iter()
+        .map(|&fd_id| {
+            let fd = db.lookup_intern_form_data(fd_id);
+            ((fd.line_no(), fd.index()), fd_id)
+        })
+        .collect();
+
+    Arc::new(
+        db.source_tokens(source)
+            .iter()
+            .map(|t| positions.get(&(t.line_no(), t.index())).cloned())
+            .collect(),
+    )
+}
+
+// All the windows of n consecutive known words, with their start
+fn windows(
+    sequence: &[Option<FormDataId>],
+    n: usize,
+) -> impl Iterator<Item = (usize, Vec<FormDataId>)> + '_ {
+    // Note, there are no 0-grams
+    let windows = match n {
+        0 => [].windows(1),
+        n => sequence.windows(n),
+    };
+    windows.enumerate().filter_map(|(start, window)| {
+        window
+            .iter()
+            .cloned()
+            .collect::<Option<Vec<_>>>()
+            .map(|w| (start, w))
+    })
+}
+
+// The readings of an occurrence, respecting the lemmas chosen by hand
+fn lemmatize_occurrence(db: &impl NgramsDatabase, fd: FormDataId) -> Vec<Lemma> {
+    let fd = db.lookup_intern_form_data(fd);
+    let overrides = db.source_overrides(fd.source());
+    let mut res: Vec<_> = middle::lemmatize_occurrence(db, &overrides, &fd)
+        .into_iter()
+        .collect();
+    res.sort();
+    res
+}
+
+fn form_ngrams(
+    db: &impl NgramsDatabase,
+    source: SourceId,
+    n: usize,
+) -> Arc<HashMap<Vec<Form>, Vec<usize>>> {
+    info!("Indexing {}-grams of forms in {:?}", n, source);
+    let sequence = db.source_sequence(source);
+    let mut res = HashMap::new();
+    for (start, window) in windows(&sequence, n) {
+        let forms = window
+            .iter()
+            .map(|&fd| db.lookup_intern_form_data(fd).form())
+            .collect();
+        res.entry(forms).or_insert_with(Vec::new).push(start);
+    }
+
+    Arc::new(res)
+}
+
+fn lemma_positions(db: &impl NgramsDatabase, source: SourceId) -> Arc<HashMap<Lemma, Vec<usize>>> {
+    info!("Indexing lemmas in {:?}", source);
+    let sequence = db.source_sequence(source);
+    let mut res = HashMap::new();
+    for (position, fd) in sequence.iter().enumerate() {
+        for lemma in fd
+            .map(|fd| lemmatize_occurrence(db, fd))
+            .unwrap_or_default()
+        {
+            res.entry(lemma).or_insert_with(Vec::new).push(position);
+        }
+    }
+
+    Arc::new(res)
+}
+
+// Check that a phrase matches the sequence starting from a position
+fn matches_at(
+    db: &impl NgramsDatabase,
+    phrase: &Phrase,
+    sequence: &[Option<FormDataId>],
+    start: usize,
+) -> Option<Vec<FormDataId>> {
+    let window = sequence.get(start..start + phrase.len())?;
+    let window: Vec<_> = window.iter().cloned().collect::<Option<_>>()?;
+
+    let all_match = window.iter().enumerate().all(|(i, &fd)| match phrase {
+        Phrase::Forms(forms) => forms[i] == db.lookup_intern_form_data(fd).form(),
+        Phrase::Lemmas(lemmas) => lemmatize_occurrence(db, fd).contains(&lemmas[i]),
+    });
+
+    if all_match {
+        Some(window)
+    } else {
+        None
+    }
+}
+
+fn phrase_occurrences(
+    db: &impl NgramsDatabase,
+    phrase: Phrase,
+    subset: LitSubset,
+) -> Arc<Vec<Vec<FormDataId>>> {
+    info!(
+        "Looking for phrase: {:?} in {} sources",
+        phrase,
+        subset.sources().len()
+    );
+
+    let mut res = Vec::new();
+    for &source in subset.sources() {
+        let starts = match &phrase {
+            // Only the first words are looked up in the index
+            Phrase::Forms(forms) => {
+                let n = forms.len().min(MAX_NGRAM);
+                db.form_ngrams(source, n).get(&forms[..n]).cloned()
+            }
+            // The rarest lemma gives the fewest starts to check
+            Phrase::Lemmas(lemmas) => {
+                let index = db.lemma_positions(source);
+                lemmas
+                    .iter()
+                    .enumerate()
+                    .map(|(i, lemma)| {
+                        index
+                            .get(lemma)
+                            .map(|positions| {
+                                positions.iter().filter_map(|p| p.checked_sub(i)).collect()
+                            })
+                            .unwrap_or_default()
+                    })
+                    .min_by_key(Vec::len)
+            }
+        };
+
+        let sequence = db.source_sequence(source);
+        res.extend(
+            starts
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|start| matches_at(db, &phrase, &sequence, start)),
+        );
+    }
+
+    Arc::new(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::query_system::mock::{make_mock_with_lemmas, MockDatabase};
+    use crate::query_system::types::InternDatabase;
+    use crate::word_db::WordDatabase;
+
+    const TEXT: &str = "Arma uirumque cano, Troiae qui primus ab oris\n\
+                        arma cano. Arma uirosque canebat\n\
+                        arma uirum";
+
+    fn make_db() -> MockDatabase {
+        make_mock_with_lemmas(vec![
+            ("arma", "arma"),
+            ("uirumque", "uir"),
+            ("uirosque", "uir"),
+            ("uirum", "uir"),
+            ("cano", "cano"),
+            ("canebat", "cano"),
+            ("primus", "primus"),
+            ("primus", "primo"),
+            ("troiae", "troia"),
+            ("qui", "qui"),
+            ("ab", "ab"),
+            ("oris", "ora"),
+        ])
+    }
+
+    fn forms(db: &MockDatabase, words: &str) -> Phrase {
+        Phrase::Forms(
+            words
+                .split_whitespace()
+                .map(|w| Form(db.intern_word(w.into())))
+                .collect(),
+        )
+    }
+
+    fn lemmas(db: &MockDatabase, words: &str) -> Phrase {
+        Phrase::Lemmas(
+            words
+                .split_whitespace()
+                .map(|w| Lemma(db.intern_word(w.into())))
+                .collect(),
+        )
+    }
+
+    // The positions of the matches, as (line_no, index) of the first word
+    fn search(db: &MockDatabase, phrase: Phrase, source: SourceId) -> Vec<(usize, usize)> {
+        db.phrase_occurrences(phrase, LitSubset::from_sources(&[source]))
+            .iter()
+            .map(|m| db.lookup_intern_form_data(m[0]))
+            .map(|fd| (fd.line_no(), fd.index()))
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+        let sequence = db.source_sequence(source);
+
+        assert_eq!(sequence.len(), 15);
+        assert!(sequence.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn test_form_ngrams() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+        let index = db.form_ngrams(source, 2);
+
+        assert_eq!(index.values().map(Vec::len).sum::<usize>(), 14);
+        let key: Vec<_> = vec!["arma", "cano"]
+            .into_iter()
+            .map(|w| Form(db.intern_word(w.into())))
+            .collect();
+        assert_eq!(index.get(&key), Some(&vec![8]));
+    }
+
+    #[test]
+    fn test_form_phrase() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+
+        assert_eq!(search(&db, forms(&db, "arma uirum"), source), vec![(2, 0)]);
+        assert_eq!(search(&db, forms(&db, "cano troiae"), source), vec![(0, 2)]);
+        assert!(search(&db, forms(&db, "uirum arma"), source).is_empty());
+    }
+
+    #[test]
+    fn test_lemma_phrase() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+
+        assert_eq!(
+            search(&db, lemmas(&db, "arma uir cano"), source),
+            vec![(0, 0), (1, 2)]
+        );
+        assert_eq!(
+            search(&db, lemmas(&db, "arma uir"), source),
+            vec![(0, 0), (1, 2), (2, 0)]
+        );
+        // Ambiguous forms match any of their lemmas
+        assert_eq!(search(&db, lemmas(&db, "primo"), source), vec![(0, 5)]);
+        assert_eq!(search(&db, lemmas(&db, "primus"), source), vec![(0, 5)]);
+        assert_eq!(
+            search(&db, lemmas(&db, "qui primo ab ora"), source),
+            vec![(0, 4)]
+        );
+        assert!(search(&db, lemmas(&db, ""), source).is_empty());
+    }
+
+    #[test]
+    fn test_lemma_positions() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+        let index = db.lemma_positions(source);
+
+        // Each word once, and primus once more for its second reading
+        assert_eq!(index.values().map(Vec::len).sum::<usize>(), 16);
+        let arma = Lemma(db.intern_word("arma".into()));
+        assert_eq!(index.get(&arma), Some(&vec![0, 8, 10, 13]));
+    }
+
+    #[test]
+    fn test_long_phrase() {
+        let mut db = make_db();
+        let source = db.intern_source(TEXT.to_string());
+        let phrase = forms(&db, "arma uirumque cano troiae qui primus ab");
+
+        let res = db.phrase_occurrences(phrase, LitSubset::from_sources(&[source]));
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].len(), 7);
+    }
+
+    #[test]
+    fn test_unknown_words_break_phrases() {
+        let mut db = make_db();
+        let source = db.intern_source("arma nescio cano\narma, cano".to_string());
+
+        assert_eq!(search(&db, forms(&db, "arma cano"), source), vec![(1, 0)]);
+        assert!(search(&db, forms(&db, ""), source).is_empty());
+    }
+}
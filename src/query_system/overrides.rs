@@ -0,0 +1,98 @@
+//! Lemmas chosen by hand for single occurrences of ambiguous forms
+//! An occurrence is identified by its source, its line and its index among the words of the line
+
+use super::ids::SourceId;
+use super::types::Lemma;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// The chosen lemmas of a source, keyed by line and index in the line
+pub type SourceOverrides = BTreeMap<(usize, usize), Lemma>;
+
+/// The lemmas chosen for the occurrences of the whole corpus
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    sources: HashMap<SourceId, Arc<SourceOverrides>>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lemma chosen for an occurrence, if any
+    pub fn get(&self, source: SourceId, line_no: usize, index: usize) -> Option<Lemma> {
+        self.sources
+            .get(&source)
+            .and_then(|overrides| overrides.get(&(line_no, index)))
+            .cloned()
+    }
+
+    /// Choose (or forget, if None) the lemma of an occurrence, returning whether anything changed
+    pub fn set(
+        &mut self,
+        source: SourceId,
+        line_no: usize,
+        index: usize,
+        lemma: Option<Lemma>,
+    ) -> bool {
+        let overrides = Arc::make_mut(self.sources.entry(source).or_default());
+        let changed = match lemma {
+            Some(lemma) => overrides.insert((line_no, index), lemma) != Some(lemma),
+            None => overrides.remove(&(line_no, index)).is_some(),
+        };
+
+        if overrides.is_empty() {
+            self.sources.remove(&source);
+        }
+
+        changed
+    }
+
+    /// All the chosen lemmas of a source
+    pub fn source(&self, source: SourceId) -> Arc<SourceOverrides> {
+        self.sources.get(&source).cloned().unwrap_or_default()
+    }
+
+    /// Forget about a source (i.e. once it is removed)
+    pub fn remove_source(&mut self, source: SourceId) -> bool {
+        self.sources.remove(&source).is_some()
+    }
+
+    /// The sources that have some chosen lemmas
+    pub fn sources(&self) -> impl Iterator<Item = SourceId> + '_ {
+        self.sources.keys().cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word_db::WordId;
+
+    #[test]
+    fn test_set_and_clear() {
+        let source = SourceId::from_integer(0);
+        let (lemma, other) = (
+            Lemma(WordId::from_integer(1)),
+            Lemma(WordId::from_integer(2)),
+        );
+        let mut overrides = Overrides::new();
+
+        assert!(overrides.set(source, 3, 1, Some(lemma)));
+        assert!(!overrides.set(source, 3, 1, Some(lemma)));
+        assert!(overrides.set(source, 3, 1, Some(other)));
+        assert_eq!(overrides.get(source, 3, 1), Some(other));
+        assert_eq!(overrides.get(source, 3, 2), None);
+        assert_eq!(overrides.source(source).len(), 1);
+
+        assert!(overrides.set(source, 3, 1, None));
+        assert!(!overrides.set(source, 3, 1, None));
+        assert!(overrides.is_empty());
+    }
+}
@@ -0,0 +1,451 @@
+//! Detection of intertextual parallels, in the style of Tesserae
+//! A parallel is a pair of lines (or of windows of a few words), one from each of two subsets
+//! of the literature, which share at least a certain number of lemmas. Parallels are scored by
+//! the rarity of the shared lemmas in the whole corpus, so that reuses of uncommon vocabulary
+//! come first
+
+use super::ids::SourceId;
+use super::lit_subset::LitSubset;
+use super::middle::lemmatize_occurrence;
+use super::types::Lemma;
+use super::MainDatabase;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// A line of a source, or a window of words running over one or more lines
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct LineRef {
+    source: SourceId,
+    line_no: usize,
+    // The index of the first word of a window in the source, as several of them can share lines
+    window: usize,
+    last_line_no: usize,
+}
+
+impl LineRef {
+    /// The source the line is in
+    pub fn source(&self) -> SourceId {
+        self.source
+    }
+
+    /// The (zero based) number of the line, the first one for a window of words
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    /// The (zero based) number of the last line of a window of words
+    pub fn last_line_no(&self) -> usize {
+        self.last_line_no
+    }
+
+    /// The canonical reference of the line, if the source has any
+    pub fn citation(&self, db: &impl MainDatabase) -> Option<String> {
+        db.citation(self.source, self.line_no)
+    }
+}
+
+/// Where the shared lemmas are looked for
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Unit {
+    /// A line
+    Line,
+    /// A number of consecutive words, regardless of the lines
+    /// Note, a window starts at each word, and a parallel is given by the words that share lemmas
+    Words(usize),
+}
+
+/// The parameters of the detection
+#[derive(Debug, Clone)]
+pub struct ParallelsConfig {
+    /// How many lemmas two lines need to share
+    pub min_shared: usize,
+    /// How many of the most frequent lemmas of the corpus are ignored
+    pub stop_words: usize,
+    /// Whether the lemmas are shared by lines or by windows of words
+    pub unit: Unit,
+}
+
+impl Default for ParallelsConfig {
+    fn default() -> Self {
+        ParallelsConfig {
+            min_shared: 2,
+            stop_words: 10,
+            unit: Unit::Line,
+        }
+    }
+}
+
+/// A pair of lines sharing some lemmas
+#[derive(Debug, Clone)]
+pub struct Parallel {
+    source: LineRef,
+    target: LineRef,
+    shared: Vec<Lemma>,
+    score: f64,
+}
+
+impl Parallel {
+    /// The line in the first subset
+    pub fn source(&self) -> LineRef {
+        self.source
+    }
+
+    /// The line in the second subset
+    pub fn target(&self) -> LineRef {
+        self.target
+    }
+
+    /// The lemmas the lines have in common
+    pub fn shared(&self) -> &[Lemma] {
+        &self.shared
+    }
+
+    /// How significant the parallel is, the higher the rarer the shared lemmas
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+// The line and the lemmas of each word of the sources, in order
+type Words = HashMap<SourceId, Vec<(usize, Vec<Lemma>)>>;
+
+// The lemmas of each line (or window of words) in a subset
+fn lines_in_subset(
+    db: &impl MainDatabase,
+    subset: &LitSubset,
+    unit: Unit,
+    words: &mut Words,
+) -> Vec<(LineRef, Vec<Lemma>)> {
+    let mut res = Vec::new();
+    for &source in subset.sources() {
+        match unit {
+            Unit::Line => {
+                for (&line_no, lemmas) in db.line_lemmas(source).iter() {
+                    let line = LineRef {
+                        source,
+                        line_no,
+                        window: 0,
+                        last_line_no: line_no,
+                    };
+                    res.push((line, lemmas.iter().cloned().collect()));
+                }
+            }
+            Unit::Words(n) => {
+                let occurrences = words
+                    .entry(source)
+                    .or_insert_with(|| occurrence_lemmas(db, source));
+                // Note, a source shorter than a window makes a single one
+                let n = n.clamp(1, occurrences.len().max(1));
+                for (window, words) in occurrences.windows(n).enumerate() {
+                    let lemmas: BTreeSet<_> = words.iter().flat_map(|(_, l)| l).cloned().collect();
+                    let line = LineRef {
+                        source,
+                        line_no: words[0].0,
+                        window,
+                        last_line_no: words[words.len() - 1].0,
+                    };
+                    res.push((line, lemmas.into_iter().collect()));
+                }
+            }
+        }
+    }
+    res
+}
+
+// The part of a window of words from the first to the last word with some of the shared lemmas
+fn narrow(line: LineRef, n: usize, words: &Words, shared: &[Lemma]) -> LineRef {
+    let words = &words[&line.source];
+    let end = (line.window + n).min(words.len());
+    let matches = |&i: &usize| words[i].1.iter().any(|l| shared.contains(l));
+    let first = (line.window..end).find(matches).unwrap();
+    let last = (line.window..end).rfind(matches).unwrap();
+
+    LineRef {
+        source: line.source,
+        line_no: words[first].0,
+        window: first,
+        last_line_no: words[last].0,
+    }
+}
+
+/// Find the parallels between two subsets, using a third one (usually the whole literature)
+/// to compute the rarity of the lemmas. The results are sorted, best first
+pub fn find_parallels(
+    db: &impl MainDatabase,
+    source: &LitSubset,
+    target: &LitSubset,
+    corpus: &LitSubset,
+    config: &ParallelsConfig,
+) -> Vec<Parallel> {
+    let frequencies = db.lemma_frequencies(corpus.clone());
+    let total: usize = frequencies.values().sum();
+
+    // The most frequent lemmas would make almost any two lines a parallel
+    let mut by_frequency: Vec<_> = frequencies.iter().collect();
+    by_frequency.sort_by(|(la, fa), (lb, fb)| fb.cmp(fa).then(la.cmp(lb)));
+    let stop_words: HashSet<Lemma> = by_frequency
+        .into_iter()
+        .take(config.stop_words)
+        .map(|(l, _)| *l)
+        .collect();
+
+    // Inverted index of the target
+    let mut words = HashMap::new();
+    let mut index: HashMap<Lemma, Vec<LineRef>> = HashMap::new();
+    for (line, lemmas) in lines_in_subset(db, target, config.unit, &mut words) {
+        for lemma in lemmas.into_iter().filter(|l| !stop_words.contains(l)) {
+            index.entry(lemma).or_default().push(line);
+        }
+    }
+
+    let mut res = Vec::new();
+    for (line, lemmas) in lines_in_subset(db, source, config.unit, &mut words) {
+        let mut candidates: BTreeMap<LineRef, Vec<Lemma>> = BTreeMap::new();
+        for lemma in lemmas.into_iter().filter(|l| !stop_words.contains(l)) {
+            for &other in index.get(&lemma).into_iter().flatten() {
+                // Note, the subsets might overlap
+                if other != line {
+                    candidates.entry(other).or_default().push(lemma);
+                }
+            }
+        }
+
+        for (other, shared) in candidates {
+            if shared.len() < config.min_shared.max(1) {
+                continue;
+            }
+
+            // Inverse document frequency of the shared lemmas
+            let score = shared
+                .iter()
+                .map(|l| {
+                    let freq = *frequencies.get(l).unwrap_or(&0);
+                    (total.max(1) as f64 / freq.max(1) as f64).ln()
+                })
+                .sum();
+
+            let (source, target) = match config.unit {
+                Unit::Line => (line, other),
+                Unit::Words(n) => (
+                    narrow(line, n, &words, &shared),
+                    narrow(other, n, &words, &shared),
+                ),
+            };
+            res.push(Parallel {
+                source,
+                target,
+                shared,
+                score,
+            });
+        }
+    }
+
+    res.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then((a.source, a.target).cmp(&(b.source, b.target)))
+    });
+
+    // Note, the overlapping windows find the same words more than once, and when the subsets
+    // overlap a pair of lines is found both ways, so only the best of each pair is kept
+    let mut seen = HashSet::new();
+    res.retain(|p| seen.insert((p.source.min(p.target), p.source.max(p.target))));
+
+    res
+}
+
+// The line and the lemmas of each word of a source, in order
+fn occurrence_lemmas(db: &impl MainDatabase, source: SourceId) -> Vec<(usize, Vec<Lemma>)> {
+    let overrides = db.source_overrides(source);
+    let mut occurrences: Vec<_> = db
+        .parse_source(source)
+        .iter()
+        .map(|&fd| db.lookup_intern_form_data(fd))
+        .collect();
+    // Note, the occurrences are not parsed in the order of the text
+    occurrences.sort_by_key(|fd| (fd.line_no(), fd.index()));
+
+    occurrences
+        .iter()
+        .map(|fd| {
+            let lemmas = lemmatize_occurrence(db, &overrides, fd);
+            (fd.line_no(), lemmas.into_iter().collect())
+        })
+        .collect()
+}
+
+// Used by the queries in the parent module
+pub(super) fn lemmas_by_line(
+    db: &impl MainDatabase,
+    source: SourceId,
+) -> BTreeMap<usize, BTreeSet<Lemma>> {
+    let mut res: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for (line_no, lemmas) in occurrence_lemmas(db, source) {
+        if !lemmas.is_empty() {
+            res.entry(line_no).or_default().extend(lemmas);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::query_system::mock::{make_mock_with_lemmas, MockDatabase};
+
+    fn make_db() -> MockDatabase {
+        make_mock_with_lemmas(vec![
+            ("arma", "arma"),
+            ("uirumque", "uir"),
+            ("uiros", "uir"),
+            ("cano", "cano"),
+            ("canit", "cano"),
+            ("et", "et"),
+            ("rosa", "rosa"),
+            ("rosae", "rosa"),
+            ("puella", "puella"),
+        ])
+    }
+
+    fn lines(parallels: &[Parallel]) -> Vec<(usize, usize)> {
+        parallels
+            .iter()
+            .map(|p| (p.source().line_no(), p.target().line_no()))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_parallels() {
+        let mut db = make_db();
+        let aeneid = db.intern_source("arma uirumque cano\npuella et rosa".to_string());
+        let other = db.intern_source("et rosae\nuiros et arma canit\npuella rosa".to_string());
+        let (source, target) = (
+            LitSubset::from_sources(&[aeneid]),
+            LitSubset::from_sources(&[other]),
+        );
+        let corpus = LitSubset::from_sources(&[aeneid, other]);
+        let config = ParallelsConfig {
+            min_shared: 2,
+            stop_words: 0,
+            unit: Unit::Line,
+        };
+
+        let res = find_parallels(&db, &source, &target, &corpus, &config);
+        // Three shared lemmas, then puella is rarer than et
+        assert_eq!(lines(&res), vec![(0, 1), (1, 2), (1, 0)]);
+        assert_eq!(res[0].shared().len(), 3);
+        assert!(res[0].score() > res[1].score());
+        assert!(res.iter().all(|p| p.source().source() == aeneid));
+    }
+
+    #[test]
+    fn test_stop_words() {
+        let mut db = make_db();
+        let aeneid = db.intern_source("arma et cano\npuella et rosa".to_string());
+        let other = db.intern_source("et et et rosa".to_string());
+        let corpus = LitSubset::from_sources(&[aeneid, other]);
+        let config = ParallelsConfig {
+            min_shared: 2,
+            stop_words: 1,
+            unit: Unit::Line,
+        };
+
+        let res = find_parallels(
+            &db,
+            &LitSubset::from_sources(&[aeneid]),
+            &LitSubset::from_sources(&[other]),
+            &corpus,
+            &config,
+        );
+        assert!(res.is_empty());
+
+        let res = find_parallels(
+            &db,
+            &LitSubset::from_sources(&[aeneid]),
+            &LitSubset::from_sources(&[other]),
+            &corpus,
+            &ParallelsConfig {
+                stop_words: 0,
+                ..config
+            },
+        );
+        assert_eq!(lines(&res), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_same_subset() {
+        let mut db = make_db();
+        let aeneid = db.intern_source("arma uirumque cano\narma uiros canit".to_string());
+        let subset = LitSubset::from_sources(&[aeneid]);
+        let config = ParallelsConfig {
+            min_shared: 2,
+            stop_words: 0,
+            unit: Unit::Line,
+        };
+
+        let res = find_parallels(&db, &subset, &subset, &subset, &config);
+        assert_eq!(lines(&res), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_words() {
+        let mut db = make_db();
+        let aeneid = db.intern_source("puella\narma\nrosa".to_string());
+        let other = db.intern_source("rosae puella".to_string());
+        let corpus = LitSubset::from_sources(&[aeneid, other]);
+        let parallels = |unit| {
+            let config = ParallelsConfig {
+                min_shared: 2,
+                stop_words: 0,
+                unit,
+            };
+            find_parallels(
+                &db,
+                &LitSubset::from_sources(&[aeneid]),
+                &LitSubset::from_sources(&[other]),
+                &corpus,
+                &config,
+            )
+        };
+
+        assert!(parallels(Unit::Line).is_empty());
+        assert!(parallels(Unit::Words(2)).is_empty());
+
+        // The window runs over the three lines
+        let res = parallels(Unit::Words(3));
+        assert_eq!(lines(&res), vec![(0, 0)]);
+        assert_eq!(res[0].source().last_line_no(), 2);
+        assert_eq!(res[0].shared().len(), 2);
+    }
+
+    #[test]
+    fn test_sliding_words() {
+        let mut db = make_db();
+        let aeneid = db.intern_source("et arma cano et puella".to_string());
+        let other = db.intern_source("arma canit\npuella cano".to_string());
+        let corpus = LitSubset::from_sources(&[aeneid, other]);
+        let config = ParallelsConfig {
+            min_shared: 2,
+            stop_words: 0,
+            unit: Unit::Words(3),
+        };
+
+        let res = find_parallels(
+            &db,
+            &LitSubset::from_sources(&[aeneid]),
+            &LitSubset::from_sources(&[other]),
+            &corpus,
+            &config,
+        );
+        // The two windows over arma cano make a single parallel, and the one across both lines
+        // of the target is found too
+        let mut last_lines: Vec<_> = res.iter().map(|p| p.target().last_line_no()).collect();
+        last_lines.sort();
+        assert_eq!(lines(&res), vec![(0, 0), (0, 0)]);
+        assert_eq!(last_lines, vec![0, 1]);
+        assert!(res.iter().all(|p| p.shared().len() == 2));
+    }
+}
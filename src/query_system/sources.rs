@@ -1,19 +1,101 @@
 //! The lowest level interfacing with source files directly
 
-use super::ids::{AuthorId, FormDataId, SourceId};
-use super::types::{Form, FormData, InternDatabase};
-use crate::filesystem::FileSystem;
-use crate::latin_utilities::StandardLatinConverter;
+use super::ids::{AuthorId, FormDataId, SourceId, WorkId};
+use super::overrides::SourceOverrides;
+use super::types::{Form, FormData, InternDatabase, Lemma};
+use crate::citations::parsers::{self, SidecarParser};
+use crate::citations::CitationScheme;
+use crate::filesystem::{FileSystem, SourceFormat};
+use crate::latin_lemmatizer::parsers::conllu_format::Treebank;
+use crate::latin_utilities::tokenizer::Token;
+use crate::latin_utilities::{
+    Enclitic, LatinTokenizer, NormalizedLatinString, Profile, StandardLatinConverter,
+};
+use crate::tei::TeiDocument;
 
-use log::info;
-use std::collections::HashSet;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+/// Sources whose parsing was done ahead of time (i.e. loaded from a snapshot on disk)
+pub trait PreparsedSources {
+    /// Get the result of parsing a source, if it is available
+    fn preparsed(&self, source_id: SourceId) -> Option<Arc<Vec<FormData>>>;
+}
+
+/// How the words of the sources are normalized: the profile of the corpus, together with
+/// the rules that some of the authors add to it
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Normalization {
+    corpus: Profile,
+    authors: HashMap<AuthorId, Profile>,
+}
+
+impl Normalization {
+    pub fn new(corpus: Profile) -> Self {
+        Normalization {
+            corpus,
+            authors: HashMap::new(),
+        }
+    }
+
+    /// Normalize the words of an author with some more rules, after the ones of the corpus
+    pub fn with_author(mut self, author: AuthorId, profile: Profile) -> Self {
+        self.authors.insert(author, profile);
+        self
+    }
+
+    pub fn corpus(&self) -> &Profile {
+        &self.corpus
+    }
+
+    /// The rules added by each of the authors that have some
+    pub fn authors(&self) -> &HashMap<AuthorId, Profile> {
+        &self.authors
+    }
+
+    /// The converter for the lemmas, and the words of the authors without rules of their own
+    pub fn converter(&self) -> StandardLatinConverter {
+        StandardLatinConverter::default().with_profile(self.corpus.clone())
+    }
+
+    /// The converter for the words of an author
+    pub fn author_converter(&self, author: AuthorId) -> StandardLatinConverter {
+        match self.authors.get(&author) {
+            Some(profile) => {
+                StandardLatinConverter::default().with_profile(self.corpus.clone().then(profile))
+            }
+            None => self.converter(),
+        }
+    }
+}
+
 /// The trait that is used to parse sources
 /// Usage: set the source text, define the relation between sources and authors
 #[salsa::query_group(SourcesQueryGroup)]
-pub trait SourcesDatabase: InternDatabase + FileSystem + salsa::Database {
+pub trait SourcesDatabase:
+    InternDatabase + FileSystem + PreparsedSources + salsa::Database
+{
+    /// Get the content of the file of a source
+    fn source_file(&self, source_id: SourceId) -> Arc<String>;
+
+    /// The contents of the file of a source, once the file changed
+    /// Note, until then (i.e. None) the file is read when it is first needed
+    #[salsa::input]
+    fn source_contents(&self, source_id: SourceId) -> Option<Arc<String>>;
+
+    /// Get how the file of a source is to be read
+    fn source_format(&self, source_id: SourceId) -> SourceFormat;
+
+    /// Read the file of a source as a treebank
+    fn source_treebank(&self, source_id: SourceId) -> Arc<Treebank>;
+
+    /// Read the file of a source as a TEI document
+    fn source_tei(&self, source_id: SourceId) -> Arc<TeiDocument>;
+
     /// Get the source text for a specified source
+    /// Note, the text of a treebank has one sentence per line, the one of a TEI document
+    /// a verse or a paragraph per line
     fn source_text(&self, source_id: SourceId) -> Arc<String>;
 
     /// Get the sources for an author
@@ -24,58 +106,226 @@ pub trait SourcesDatabase: InternDatabase + FileSystem + salsa::Database {
     #[salsa::input]
     fn associated_author(&self, source_id: SourceId) -> AuthorId;
 
+    /// Get the sources a work is made of
+    #[salsa::input]
+    fn work_sources(&self, work_id: WorkId) -> Arc<HashSet<SourceId>>;
+
+    /// Get the work a source is part of
+    #[salsa::input]
+    fn associated_work(&self, source_id: SourceId) -> WorkId;
+
+    /// Get how the words of the sources (and of the lemmatizer) are normalized
+    #[salsa::input]
+    fn normalization(&self) -> Arc<Normalization>;
+
+    /// Bumped whenever words are added to the vocabulary, so that the sources are parsed again
+    /// (the words themselves are not tracked by salsa)
+    #[salsa::input]
+    fn vocabulary_version(&self) -> usize;
+
     // Low level
     /// Get a determined line in a source, if possible
     #[salsa::dependencies]
     fn get_line(&self, source_id: SourceId, line: usize) -> Option<Arc<String>>;
 
+    /// Get the references of a source, from its sidecar file or else from its inline markers
+    /// (or the structure of the edition, for a TEI document)
+    fn source_citations(&self, source_id: SourceId) -> Arc<CitationScheme>;
+
+    /// Get the citation of a determined line in a source, if it has any
+    #[salsa::dependencies]
+    fn citation(&self, source_id: SourceId, line: usize) -> Option<String>;
+
+    /// Split a source in its words, in the order they appear
+    fn source_tokens(&self, source_id: SourceId) -> Arc<Vec<Token>>;
+
+    /// Resolve the words of a source to the known forms, in the order they appear
+    /// Note, nothing is interned, so that sources can be resolved in parallel
+    fn source_forms(&self, source_id: SourceId) -> Arc<Vec<FormData>>;
+
     // TODO, benchmark and see if hashset actually worth it
     /// Parse a source, returning the FormData that it generates
     fn parse_source(&self, source_id: SourceId) -> Arc<HashSet<FormDataId>>;
+
+    /// Get the lemmas that come with the words of a source (i.e. the ones of a treebank),
+    /// keyed by line and index
+    fn source_gold_lemmas(&self, source_id: SourceId) -> Arc<SourceOverrides>;
 }
 
-fn source_text(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<String> {
+fn source_file(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<String> {
+    if let Some(contents) = db.source_contents(source_id) {
+        return contents;
+    }
+
     info!("Loading source {:?}", source_id);
-    db.salsa_runtime()
-        .report_synthetic_read(salsa::Durability::LOW);
     db.watch(source_id);
     Arc::new(db.load(source_id))
 }
 
+fn source_format(db: &impl SourcesDatabase, source_id: SourceId) -> SourceFormat {
+    // Note, a source keeps the name of its file (and so its format) even when it is moved
+    db.format(source_id)
+}
+
+fn source_treebank(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<Treebank> {
+    let converter = db.normalization().converter();
+    Arc::new(Treebank::parse_with(&db.source_file(source_id), &converter))
+}
+
+fn source_tei(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<TeiDocument> {
+    Arc::new(
+        TeiDocument::parse(&db.source_file(source_id)).unwrap_or_else(|err| {
+            warn!("Invalid TEI document for source {:?}: {}", source_id, err);
+            TeiDocument::default()
+        }),
+    )
+}
+
+fn source_text(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<String> {
+    match db.source_format(source_id) {
+        SourceFormat::Text => db.source_file(source_id),
+        SourceFormat::Treebank => Arc::new(db.source_treebank(source_id).text().to_string()),
+        SourceFormat::Tei => Arc::new(db.source_tei(source_id).text().to_string()),
+    }
+}
+
 // Note, this function is O(line), so it should be used scarcely
 fn get_line(db: &impl SourcesDatabase, source_id: SourceId, line: usize) -> Option<Arc<String>> {
     let text = db.source_text(source_id);
     text.lines().nth(line).map(|l| Arc::new(l.to_string()))
 }
 
-fn parse_source(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<HashSet<FormDataId>> {
-    info!("Parsing source {:?}", source_id);
-    let converter = StandardLatinConverter::default();
-    let mut form_data_ids = HashSet::new();
+fn source_citations(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<CitationScheme> {
+    // Note, the contents of a source are set again when its sidecar changes too
+    db.source_contents(source_id);
+
+    let scheme = match db.load_citations(source_id) {
+        Some(sidecar) => {
+            let mut parser = SidecarParser::default();
+            parser.read_all(sidecar.as_bytes()).map(|_| parser.build())
+        }
+        None if db.source_format(source_id) == SourceFormat::Tei => {
+            Ok(db.source_tei(source_id).citations().clone())
+        }
+        None => parsers::from_markers(&db.source_text(source_id)),
+    };
+
+    Arc::new(scheme.unwrap_or_else(|err| {
+        warn!("Invalid references for source {:?}: {}", source_id, err);
+        CitationScheme::new()
+    }))
+}
+
+fn citation(db: &impl SourcesDatabase, source_id: SourceId, line: usize) -> Option<String> {
+    db.source_citations(source_id).cite(line)
+}
+
+fn source_tokens(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<Vec<Token>> {
+    if db.source_format(source_id) == SourceFormat::Treebank {
+        return Arc::new(db.source_treebank(source_id).tokens().to_vec());
+    }
 
     let text = db.source_text(source_id);
+    // Note, references are not part of the text
+    let text = parsers::blank_markers(&text);
+    Arc::new(LatinTokenizer.tokenize(text.as_ref()))
+}
 
-    for (i, line) in text.lines().enumerate() {
-        for word in line.split(' ') {
-            let lw = converter.convert(word);
-            // Note, this does NOT intern new words, which should reduce allocs
-            db.lookup_interned_word(lw).map(|id| {
-                let form = Form(id);
-                let form_data = FormData::new(source_id, i, form);
-                let form_data_id = db.intern_form_data(form_data);
-                form_data_ids.insert(form_data_id);
-            });
-        }
+fn source_forms(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<Vec<FormData>> {
+    db.vocabulary_version();
+    if let Some(parsed) = db.preparsed(source_id) {
+        // Note, the contents are still looked at, so that an edited source gets parsed again
+        db.source_contents(source_id);
+        return parsed;
+    }
+
+    info!("Parsing source {:?}", source_id);
+    let normalization = db.normalization();
+    // Note, the author is only needed if some of them have rules of their own
+    let converter = if normalization.authors().is_empty() {
+        normalization.converter()
+    } else {
+        normalization.author_converter(db.associated_author(source_id))
+    };
+    let mut forms = Vec::new();
+    // Note, the words of a treebank are already split, so the enclitics are not looked for
+    let split_enclitics = db.source_format(source_id) != SourceFormat::Treebank;
+
+    for token in db.source_tokens(source_id).iter() {
+        let lw = converter.convert(token.word());
+        let (line_no, index, offset) = (token.line_no(), token.index(), token.offset());
+        let found = if split_enclitics {
+            lookup_form(db, lw)
+        } else {
+            db.lookup_interned_word(lw).map(|id| (Form(id), None))
+        };
+        let form_data = match found {
+            Some((form, None)) => FormData::new(source_id, line_no, index, offset, form),
+            Some((form, Some(enclitic))) => {
+                FormData::new_with_enclitic(source_id, line_no, index, offset, form, enclitic)
+            }
+            None => continue,
+        };
+        forms.push(form_data);
+    }
+
+    Arc::new(forms)
+}
+
+fn parse_source(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<HashSet<FormDataId>> {
+    Arc::new(
+        db.source_forms(source_id)
+            .iter()
+            .map(|fd| db.intern_form_data(fd.clone()))
+            .collect(),
+    )
+}
+
+fn source_gold_lemmas(db: &impl SourcesDatabase, source_id: SourceId) -> Arc<SourceOverrides> {
+    if db.source_format(source_id) != SourceFormat::Treebank {
+        return Arc::new(SourceOverrides::new());
+    }
+
+    // Note, the lemmas were added to the vocabulary together with the lemmatizer
+    db.vocabulary_version();
+    let treebank = db.source_treebank(source_id);
+    Arc::new(
+        treebank
+            .tokens()
+            .iter()
+            .zip(treebank.lemmas())
+            .filter_map(|(token, lemma)| {
+                let lemma = Lemma(db.lookup_interned_str(lemma.inner())?);
+                Some(((token.line_no(), token.index()), lemma))
+            })
+            .collect(),
+    )
+}
+
+// Resolve a word to a known form, splitting enclitics if the word as a whole is unknown
+fn lookup_form(
+    db: &impl SourcesDatabase,
+    word: NormalizedLatinString,
+) -> Option<(Form, Option<Enclitic>)> {
+    // Note, this does NOT intern new words, which should reduce allocs
+    if let Some(id) = db.lookup_interned_str(word.inner()) {
+        return Some((Form(id), None));
     }
 
-    Arc::new(form_data_ids)
+    Enclitic::split(&word)
+        .into_iter()
+        .find_map(|(host, enclitic)| {
+            db.lookup_interned_word(host)
+                .map(|id| (Form(id), Some(enclitic)))
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::filesystem::FileSystem;
-    use crate::query_system::mock::make_mock;
+    use crate::query_system::mock::{make_mock, make_mock_with_lemmas, make_mock_with_vocabulary};
+    use crate::word_db::WordDatabase;
     use proptest::prelude::*;
     use std::iter;
 
@@ -181,6 +431,180 @@ perlaturus ad te primum liber iste festinet, apud te"#;
         assert_debug_snapshot!("notum_est_omnibus", form_data)
     }
 
+    fn parse_words(text: &str) -> BTreeSet<(usize, String)> {
+        let mut db = make_mock();
+        let source = db.intern_source(text.to_string());
+        let parse_res = db.parse_source(source);
+        parse_res
+            .iter()
+            .map(|&fd| db.lookup_intern_form_data(fd))
+            .map(|fd| (fd.line_no(), db.lookup_word_str(fd.form().0).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_whitespace_punctuation() {
+        let text = "Quo usque tandem\tabutere,  Catilina,\u{a0}patientia nostra?\r\n\
+                    quam diu etiam furor iste tuus nos eludet?quem ad finem sese\n\
+                    \teffrenata iactabit audacia?  Nihilne te nocturnum praesidium Palati,";
+
+        assert_debug_snapshot!("quo_usque_tandem", parse_words(text))
+    }
+
+    #[test]
+    fn parse_hyphenation() {
+        let text = "Arma uirum-\nque cano, Troiae qui primus ab oris\n\
+                    Italiam fato profugus Lauiniaque ue-  \n\
+                    nit litora, multum ille et terris iactatus et alto -\n\
+                    ui superum saeuae memorem Iunonis ob iram";
+
+        assert_debug_snapshot!("arma_uirumque", parse_words(text))
+    }
+
+    #[test]
+    fn parse_repeated_forms() {
+        let mut db = make_mock();
+        let source = db.intern_source("et arma et uirum et\ncano et".to_string());
+        let parse_res = db.parse_source(source);
+        let positions: BTreeSet<_> = parse_res
+            .iter()
+            .map(|&fd| db.lookup_intern_form_data(fd))
+            .filter(|fd| db.lookup_word_str(fd.form().0) == "et")
+            .map(|fd| (fd.line_no(), fd.index(), fd.offset()))
+            .collect();
+
+        let expected: BTreeSet<_> = vec![(0, 0, 0), (0, 2, 8), (0, 4, 17), (1, 1, 5)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(parse_res.len(), 7);
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn parse_inline_citations() {
+        let mut db = make_mock();
+        let source =
+            db.intern_source("§Cat. 1.1§ quo usque\ntandem\n§Cat. 1.2§ o tempora".to_string());
+        let parse_res = db.parse_source(source);
+        let form_data: BTreeSet<_> = parse_res
+            .iter()
+            .map(|&fd| db.lookup_intern_form_data(fd))
+            .map(|fd| {
+                (
+                    db.lookup_word_str(fd.form().0).to_string(),
+                    db.citation(fd.source(), fd.line_no()).unwrap(),
+                )
+            })
+            .collect();
+
+        let expected: BTreeSet<_> = vec![
+            ("quo", "Cat. 1.1"),
+            ("usque", "Cat. 1.1"),
+            ("tandem", "Cat. 1.1"),
+            ("o", "Cat. 1.2"),
+            ("tempora", "Cat. 1.2"),
+        ]
+        .into_iter()
+        .map(|(f, c)| (f.to_string(), c.to_string()))
+        .collect();
+
+        assert_eq!(form_data, expected);
+    }
+
+    #[test]
+    fn parse_enclitics() {
+        let mut db = make_mock_with_vocabulary(vec!["arma", "uirum", "cano", "me", "est"]);
+        let source = db.intern_source("Arma uirumque cano mecum estne".to_string());
+        let parse_res = db.parse_source(source);
+        let form_data: BTreeSet<_> = parse_res
+            .iter()
+            .map(|&fd| db.lookup_intern_form_data(fd))
+            .map(|fd| (db.lookup_word_str(fd.form().0).to_string(), fd.enclitic()))
+            .collect();
+
+        let expected: BTreeSet<_> = vec![
+            ("arma".to_string(), None),
+            ("uirum".to_string(), Some(Enclitic::Que)),
+            ("cano".to_string(), None),
+            ("me".to_string(), Some(Enclitic::Cum)),
+            ("est".to_string(), Some(Enclitic::Ne)),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(form_data, expected);
+    }
+
+    #[test]
+    fn parse_unknown_words() {
+        let mut db = make_mock_with_vocabulary(vec!["arma"]);
+        let source = db.intern_source("arma uirumque que".to_string());
+        let parse_res = db.parse_source(source);
+
+        assert_eq!(parse_res.len(), 1);
+    }
+
+    #[test]
+    fn parse_treebank() {
+        use crate::latin_lemmatizer::compressed::CompressedLemmatizer;
+        use crate::latin_lemmatizer::NaiveLemmatizer;
+        use crate::query_system::middle::ScopedLemmatizers;
+        use crate::query_system::traits::IntermediateDatabase;
+
+        let mut db =
+            make_mock_with_lemmas(vec![("arma", "arma"), ("arma", "armo"), ("cano", "cano")]);
+        let source = db.intern_treebank(
+            "# text = Arma cano.\n\
+             1\tArma\tarma\tNOUN\t_\t_\n\
+             2\tcano\tcano\tVERB\t_\t_\t0\troot\t_\tSpaceAfter=No\n\
+             3\t.\t.\tPUNCT\t_\t_\n\n"
+                .to_string(),
+        );
+
+        assert_eq!(*db.source_text(source), "Arma cano.\n");
+        assert_eq!(db.parse_source(source).len(), 2);
+
+        // The lemmas of the treebank are the only readings of its words
+        let arma = Lemma(db.lookup_interned_word("arma".into()).unwrap());
+        assert_eq!(db.source_gold_lemmas(source).get(&(0, 0)), Some(&arma));
+        assert_eq!(db.lemmas_in_source(source).len(), 2);
+
+        // While its (form, lemma) pairs are not known in the other sources
+        let mut pairs = HashMap::new();
+        pairs.insert("cano".into(), iter::once("canus".into()).collect());
+        let pairs = CompressedLemmatizer::new(NaiveLemmatizer::new(pairs), &db);
+        db.set_scoped_lemmatizers(Arc::new(
+            ScopedLemmatizers::default().with_treebank(source, Arc::new(pairs)),
+        ));
+        let text = db.intern_source("arma cano".to_string());
+
+        let cano = db.lookup_interned_word("cano".into()).unwrap();
+        let canus = db.lookup_interned_word("canus".into()).unwrap();
+        assert!(db
+            .source_lemmatizer(source)
+            .get_possible_lemmas(cano)
+            .contains(&canus));
+        assert!(!db
+            .source_lemmatizer(text)
+            .get_possible_lemmas(cano)
+            .contains(&canus));
+    }
+
+    #[test]
+    fn parse_tei() {
+        let mut db = make_mock_with_vocabulary(vec!["arma", "uirum", "cano"]);
+        let source = db.intern_tei(
+            r#"<TEI><text><body><div n="1"><head>Liber I</head>
+            <l n="1">Arma uirumque cano<note>arma cano</note></l></div></body></text></TEI>"#
+                .to_string(),
+        );
+
+        assert_eq!(*db.source_text(source), "Arma uirumque cano");
+        assert_eq!(db.parse_source(source).len(), 3);
+        assert_eq!(db.citation(source, 0), Some("1.1".to_string()));
+    }
+
     proptest! {
         #[test]
         fn doesnt_crash(s in "\\PC*") {
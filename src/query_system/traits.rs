@@ -1,9 +1,12 @@
 //! Module that re-exports all the traits used in the query_system, for convenience
 
 pub use super::middle::IntermediateDatabase;
+pub use super::ngrams::NgramsDatabase;
 pub use super::sources::SourcesDatabase;
 pub use super::types::AuthorInternDatabase;
 pub use super::types::InternDatabase;
+pub use super::types::WorkInternDatabase;
+pub use super::weights::WeightsDatabase;
 pub use super::MainDatabase;
 pub use crate::word_db::WordDatabase;
 pub use salsa::Database;
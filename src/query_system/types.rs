@@ -2,8 +2,9 @@
 //! In most cases, the types are created by direct computation on
 //! the sources, and are then interned in order to speed up computation
 
-use super::ids::{AuthorId, FormDataId, SourceId};
+use super::ids::{AuthorId, FormDataId, SourceId, WorkId};
 use super::traits::MainDatabase;
+use crate::latin_utilities::Enclitic;
 use crate::word_db::{WordDatabase, WordId};
 
 #[salsa::query_group(InternersGroup)]
@@ -17,11 +18,19 @@ pub trait AuthorInternDatabase {
     fn lookup_intern_author(&self, id: AuthorId) -> &Author;
 }
 
+pub trait WorkInternDatabase {
+    fn intern_work(&mut self, work: Work) -> WorkId;
+    fn lookup_intern_work(&self, id: WorkId) -> &Work;
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct FormData {
     source: SourceId,
     line_no: usize,
+    index: usize,
+    offset: usize,
     form: Form,
+    enclitic: Option<Enclitic>,
 }
 
 #[derive(shrinkwraprs::Shrinkwrap, Debug, Hash, Eq, PartialEq, Clone, Copy, Ord, PartialOrd)]
@@ -30,14 +39,36 @@ pub struct Lemma(pub WordId);
 #[derive(shrinkwraprs::Shrinkwrap, Debug, Hash, Eq, PartialEq, Clone, Copy, Ord, PartialOrd)]
 pub struct Form(pub WordId);
 
-pub use crate::authors_chrono::Author;
+pub use crate::authors_chrono::{Author, Work};
 
 impl FormData {
-    pub fn new(source: SourceId, line_no: usize, form: Form) -> Self {
+    pub fn new(source: SourceId, line_no: usize, index: usize, offset: usize, form: Form) -> Self {
         Self {
             source,
             line_no,
+            index,
+            offset,
             form,
+            enclitic: None,
+        }
+    }
+
+    /// A form data whose form was found by splitting off an enclitic
+    pub fn new_with_enclitic(
+        source: SourceId,
+        line_no: usize,
+        index: usize,
+        offset: usize,
+        form: Form,
+        enclitic: Enclitic,
+    ) -> Self {
+        Self {
+            source,
+            line_no,
+            index,
+            offset,
+            form,
+            enclitic: Some(enclitic),
         }
     }
 
@@ -49,11 +80,41 @@ impl FormData {
         self.line_no
     }
 
+    /// The position of the word among the ones in its line
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The offset, in chars, of the word in its line
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     pub fn form(&self) -> Form {
         self.form
     }
 
+    pub fn enclitic(&self) -> Option<Enclitic> {
+        self.enclitic
+    }
+
     pub fn author(&self, db: &impl MainDatabase) -> AuthorId {
         db.associated_author(self.source())
     }
+
+    pub fn work(&self, db: &impl MainDatabase) -> WorkId {
+        db.associated_work(self.source())
+    }
+
+    /// The canonical reference of the line, if the source has any
+    pub fn citation(&self, db: &impl MainDatabase) -> Option<String> {
+        db.citation(self.source(), self.line_no())
+    }
+
+    /// The lemma that was chosen by hand for this occurrence, if any
+    pub fn chosen_lemma(&self, db: &impl MainDatabase) -> Option<Lemma> {
+        db.source_overrides(self.source())
+            .get(&(self.line_no(), self.index()))
+            .cloned()
+    }
 }
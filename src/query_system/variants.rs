@@ -0,0 +1,129 @@
+//! Families of spelling variants among the forms of the lemmatizer
+//! (i.e. `adfero` and `affero`), grouped by [`variant_key`](../../latin_utilities/variants/fn.variant_key.html)
+
+use super::ids::{AuthorId, WorkId};
+use super::lit_subset::LitSubset;
+use super::types::Form;
+use super::MainDatabase;
+use crate::authors_chrono::split_spans_by_century;
+use crate::latin_utilities::variants::variant_key;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Group the forms of the lemmatizer by the key of their variants
+/// (and in the spelling of the authors with rules of their own)
+/// Note, only the families with more than one form are kept
+pub fn variant_families(db: &impl MainDatabase) -> HashMap<String, BTreeSet<Form>> {
+    let scoped = db.scoped_lemmatizers();
+    let lemmatizers = std::iter::once(db.lemmatizer()).chain(scoped.profiles().values().cloned());
+
+    let mut res: HashMap<String, BTreeSet<Form>> = HashMap::new();
+    for lemm in lemmatizers {
+        for &form in lemm.mapping().keys() {
+            let key = variant_key(db.lookup_word_str(form));
+            res.entry(key).or_default().insert(Form(form));
+        }
+    }
+
+    res.retain(|_, forms| forms.len() > 1);
+    res
+}
+
+/// How often a variant occurs in a subset of the literature, by author and by century
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VariantCounts {
+    form: Form,
+    count: usize,
+    authors: BTreeMap<AuthorId, usize>,
+    centuries: BTreeMap<i32, usize>,
+}
+
+impl VariantCounts {
+    pub fn new(db: &impl MainDatabase, form: Form, subset: LitSubset) -> Self {
+        let occurrences = db.form_occurrences_subset(form, subset);
+        let mut authors = BTreeMap::new();
+        let mut works: HashMap<WorkId, usize> = HashMap::new();
+        for &fd in occurrences.iter() {
+            let fd = db.lookup_intern_form_data(fd);
+            *authors.entry(fd.author(db)).or_insert(0) += 1;
+            *works.entry(fd.work(db)).or_insert(0) += 1;
+        }
+
+        // Note, each work is dated by its own date, or else by the one of its author
+        let buckets = split_spans_by_century(works.iter().map(|(&id, &count)| {
+            let work = db.lookup_intern_work(id);
+            (count, work.dating(db.lookup_intern_author(work.author())))
+        }));
+
+        VariantCounts {
+            form,
+            count: occurrences.len(),
+            authors,
+            centuries: buckets
+                .into_iter()
+                .map(|(century, counts)| (century, counts.iter().sum()))
+                .collect(),
+        }
+    }
+
+    pub fn form(&self) -> Form {
+        self.form
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn authors(&self) -> &BTreeMap<AuthorId, usize> {
+        &self.authors
+    }
+
+    /// The occurrences in each century, counted in all of the centuries their work spans
+    pub fn centuries(&self) -> &BTreeMap<i32, usize> {
+        &self.centuries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authors_chrono::{TimeSpan, Work};
+    use crate::filesystem::FileSystem;
+    use crate::query_system::mock::make_mock_with_lemmas;
+    use crate::query_system::traits::*;
+
+    #[test]
+    fn test_variants() {
+        let mut db = make_mock_with_lemmas(vec![
+            ("adfero", "adfero"),
+            ("affero", "adfero"),
+            ("quom", "cum"),
+            ("cum", "cum"),
+            ("rosa", "rosa"),
+        ]);
+        let source = db.intern_source("adfero affero rosa\naffero cum".to_string());
+        let author = AuthorId::from_integer(0);
+        let work =
+            db.intern_work(Work::new(author, "Annales").with_tspan(TimeSpan::new_cent(-2, -1)));
+        db.set_associated_author(source, author);
+        db.set_associated_work(source, work);
+
+        let form = |s: &str| Form(db.lookup_interned_word(s.into()).unwrap());
+        let (adfero, affero, rosa) = (form("adfero"), form("affero"), form("rosa"));
+        assert_eq!(db.variant_families().len(), 2);
+        assert_eq!(
+            *db.form_variants(affero),
+            vec![adfero, affero].into_iter().collect()
+        );
+        assert_eq!(db.form_variants(rosa).len(), 1);
+
+        let counts = VariantCounts::new(&db, affero, LitSubset::from_sources(&[source]));
+        assert_eq!(counts.count(), 2);
+        assert_eq!(counts.authors()[&author], 2);
+        assert_eq!(
+            counts.centuries().keys().cloned().collect::<Vec<_>>(),
+            vec![-2, -1]
+        );
+        assert_eq!(counts.centuries()[&-1], 2);
+    }
+}
@@ -0,0 +1,439 @@
+//! Fractional counting of the occurrences of ambiguous forms
+//! Instead of being counted once under each of its lemmas, an ambiguous occurrence is split among
+//! them, in proportion to how often each lemma appears unambiguously in a corpus. Optionally, the
+//! unambiguous lemmas around the occurrence refine the split (as in a naive Bayes classifier)
+
+use super::ids::{FormDataId, SourceId};
+use super::lit_subset::LitSubset;
+use super::middle::{self, IntermediateDatabase};
+use super::types::Lemma;
+
+use log::info;
+use std::collections::HashMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::sync::Arc;
+
+/// A fractional count of occurrences
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Weight(f64);
+
+// Note, weights are never NaN
+impl Eq for Weight {}
+
+impl Weight {
+    /// A weight, which cannot be NaN
+    pub fn new(value: f64) -> Self {
+        assert!(!value.is_nan(), "A weight cannot be NaN");
+        Weight(value)
+    }
+
+    pub fn value(self) -> f64 {
+        self.0
+    }
+}
+
+impl Add for Weight {
+    type Output = Weight;
+
+    fn add(self, other: Weight) -> Weight {
+        Weight::new(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Weight {
+    fn add_assign(&mut self, other: Weight) {
+        *self = *self + other
+    }
+}
+
+impl Sum for Weight {
+    fn sum<I: Iterator<Item = Weight>>(iter: I) -> Weight {
+        iter.fold(Weight::default(), Add::add)
+    }
+}
+
+/// How the occurrences of ambiguous forms are split
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Weighting {
+    corpus: LitSubset,
+    context: usize,
+}
+
+impl Weighting {
+    /// Split by the frequencies of the lemmas in a corpus (usually the whole literature)
+    pub fn new(corpus: LitSubset) -> Self {
+        Weighting { corpus, context: 0 }
+    }
+
+    /// Also consider the unambiguous lemmas at most this many words away, in the same line
+    pub fn with_context(self, window: usize) -> Self {
+        Weighting {
+            context: window,
+            ..self
+        }
+    }
+
+    pub fn corpus(&self) -> &LitSubset {
+        &self.corpus
+    }
+
+    pub fn context(&self) -> usize {
+        self.context
+    }
+}
+
+/// How the occurrences of ambiguous forms are counted
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum CountMode {
+    /// Once under each of the lemmas
+    Every,
+    /// Split among the lemmas
+    Weighted(Weighting),
+}
+
+/// The possible lemmas of an occurrence
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Reading {
+    pub occurrence: FormDataId,
+    pub line_no: usize,
+    pub index: usize,
+    pub lemmas: Vec<Lemma>,
+}
+
+impl Reading {
+    /// Is there a single lemma for the occurrence?
+    pub fn is_certain(&self) -> bool {
+        self.lemmas.len() == 1
+    }
+}
+
+/// How often the lemmas appear unambiguously, alone and near each other
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Statistics {
+    lemmas: HashMap<Lemma, usize>,
+    pairs: HashMap<(Lemma, Lemma), usize>,
+}
+
+impl Statistics {
+    /// The unambiguous occurrences of a lemma
+    pub fn frequency(&self, lemma: Lemma) -> usize {
+        self.lemmas.get(&lemma).cloned().unwrap_or(0)
+    }
+
+    /// How many times two lemmas appear unambiguously near each other
+    pub fn cooccurrences(&self, a: Lemma, b: Lemma) -> usize {
+        self.pairs.get(&(a.min(b), a.max(b))).cloned().unwrap_or(0)
+    }
+
+    fn extend(&mut self, other: &Statistics) {
+        for (&lemma, &count) in &other.lemmas {
+            *self.lemmas.entry(lemma).or_insert(0) += count;
+        }
+        for (&pair, &count) in &other.pairs {
+            *self.pairs.entry(pair).or_insert(0) += count;
+        }
+    }
+}
+
+#[salsa::query_group(WeightsQueryGroup)]
+pub trait WeightsDatabase: IntermediateDatabase {
+    /// The possible lemmas of the occurrences of a source, in textual order
+    fn source_readings(&self, source: SourceId) -> Arc<Vec<Reading>>;
+
+    /// How often the lemmas appear unambiguously in a source, the pairs at most context words apart
+    fn source_statistics(&self, source: SourceId, context: usize) -> Arc<Statistics>;
+
+    /// How often the lemmas appear unambiguously in a subset of the literature
+    #[salsa::dependencies]
+    fn subset_statistics(&self, sub: LitSubset, context: usize) -> Arc<Statistics>;
+
+    /// Split the occurrences of a source among their lemmas, the weights of each summing to one
+    fn occurrence_weights(
+        &self,
+        source: SourceId,
+        weighting: Weighting,
+    ) -> Arc<HashMap<FormDataId, Vec<(Lemma, Weight)>>>;
+
+    /// The expected number of occurrences of each lemma in a source
+    fn weighted_source_counts(
+        &self,
+        source: SourceId,
+        weighting: Weighting,
+    ) -> Arc<HashMap<Lemma, Weight>>;
+
+    /// The expected number of occurrences of each lemma in a subset of the literature
+    #[salsa::dependencies]
+    fn weighted_lemma_frequencies(
+        &self,
+        sub: LitSubset,
+        weighting: Weighting,
+    ) -> Arc<HashMap<Lemma, Weight>>;
+}
+
+fn source_readings(db: &impl WeightsDatabase, source: SourceId) -> Arc<Vec<Reading>> {
+    let overrides = db.source_overrides(source);
+    let mut res: Vec<_> = db
+        .parse_source(source)
+        .iter()
+        .map(|&occurrence| {
+            let fd = db.lookup_intern_form_data(occurrence);
+            let mut lemmas: Vec<_> = middle::lemmatize_occurrence(db, &overrides, &fd)
+                .into_iter()
+                .collect();
+            lemmas.sort();
+            Reading {
+                occurrence,
+                line_no: fd.line_no(),
+                index: fd.index(),
+                lemmas,
+            }
+        })
+        .filter(|r| !r.lemmas.is_empty())
+        .collect();
+    res.sort_by_key(|r| (r.line_no, r.index));
+
+    Arc::new(res)
+}
+
+// The unambiguous readings near one, in the same line
+fn neighbours(
+    readings: &[Reading],
+    at: usize,
+    context: usize,
+) -> impl Iterator<Item = &Reading> + '_ {
+    let (line_no, index) = (readings[at].line_no, readings[at].index);
+    let near = move |r: &&Reading| {
+        r.line_no == line_no && index.max(r.index) - index.min(r.index) <= context
+    };
+
+    let before = readings[..at].iter().rev().take_while(near);
+    let after = readings[at + 1..].iter().take_while(near);
+    before.chain(after).filter(|r| r.is_certain())
+}
+
+fn source_statistics(
+    db: &impl WeightsDatabase,
+    source: SourceId,
+    context: usize,
+) -> Arc<Statistics> {
+    let readings = db.source_readings(source);
+    let mut res = Statistics::default();
+    for (i, reading) in readings.iter().enumerate() {
+        if !reading.is_certain() {
+            continue;
+        }
+
+        let lemma = reading.lemmas[0];
+        *res.lemmas.entry(lemma).or_insert(0) += 1;
+
+        // Note, each pair is counted from its first word
+        for other in neighbours(&readings, i, context).filter(|r| r.index > reading.index) {
+            let other = other.lemmas[0];
+            *res.pairs
+                .entry((lemma.min(other), lemma.max(other)))
+                .or_insert(0) += 1;
+        }
+    }
+
+    Arc::new(res)
+}
+
+fn subset_statistics(db: &impl WeightsDatabase, sub: LitSubset, context: usize) -> Arc<Statistics> {
+    let mut res = Statistics::default();
+    for &source in sub.sources() {
+        res.extend(&db.source_statistics(source, context));
+    }
+
+    Arc::new(res)
+}
+
+fn occurrence_weights(
+    db: &impl WeightsDatabase,
+    source: SourceId,
+    weighting: Weighting,
+) -> Arc<HashMap<FormDataId, Vec<(Lemma, Weight)>>> {
+    info!("Weighting the lemmas of {:?}", source);
+    let stats = db.subset_statistics(weighting.corpus.clone(), weighting.context);
+    let readings = db.source_readings(source);
+    // Note, this smooths the probabilities of the lemmas near one another
+    let vocabulary = stats.lemmas.len().max(1) as f64;
+
+    let mut res = HashMap::with_capacity(readings.len());
+    for (i, reading) in readings.iter().enumerate() {
+        if reading.is_certain() {
+            res.insert(
+                reading.occurrence,
+                vec![(reading.lemmas[0], Weight::new(1.0))],
+            );
+            continue;
+        }
+
+        // The log likelihood of each lemma, with add one smoothing
+        let scores: Vec<_> = reading
+            .lemmas
+            .iter()
+            .map(|&lemma| {
+                let frequency = stats.frequency(lemma) as f64;
+                let mut score = (frequency + 1.0).ln();
+                if weighting.context > 0 {
+                    for other in neighbours(&readings, i, weighting.context) {
+                        let together = stats.cooccurrences(lemma, other.lemmas[0]) as f64;
+                        score += ((together + 1.0) / (frequency + vocabulary)).ln();
+                    }
+                }
+                score
+            })
+            .collect();
+
+        let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exps: Vec<_> = scores.iter().map(|s| (s - max).exp()).collect();
+        let total: f64 = exps.iter().sum();
+
+        res.insert(
+            reading.occurrence,
+            reading
+                .lemmas
+                .iter()
+                .zip(exps)
+                .map(|(&lemma, e)| (lemma, Weight::new(e / total)))
+                .collect(),
+        );
+    }
+
+    Arc::new(res)
+}
+
+fn weighted_source_counts(
+    db: &impl WeightsDatabase,
+    source: SourceId,
+    weighting: Weighting,
+) -> Arc<HashMap<Lemma, Weight>> {
+    let mut res = HashMap::new();
+    for &(lemma, weight) in db.occurrence_weights(source, weighting).values().flatten() {
+        *res.entry(lemma).or_insert_with(Weight::default) += weight;
+    }
+
+    Arc::new(res)
+}
+
+fn weighted_lemma_frequencies(
+    db: &impl WeightsDatabase,
+    sub: LitSubset,
+    weighting: Weighting,
+) -> Arc<HashMap<Lemma, Weight>> {
+    let mut res = HashMap::new();
+    for &source in sub.sources() {
+        for (&lemma, &weight) in db.weighted_source_counts(source, weighting.clone()).iter() {
+            *res.entry(lemma).or_insert_with(Weight::default) += weight;
+        }
+    }
+
+    Arc::new(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::FileSystem;
+    use crate::query_system::ids::AuthorId;
+    use crate::query_system::mock::{make_mock_with_lemmas, MockDatabase};
+    use crate::query_system::traits::*;
+
+    fn make_db() -> MockDatabase {
+        make_mock_with_lemmas(vec![
+            ("uenio", "uenio"),
+            ("uenimus", "uenio"),
+            ("uenit", "uenio"),
+            ("uenit", "ueneo"),
+            ("uenibat", "ueneo"),
+            ("pretio", "pretium"),
+            ("roma", "roma"),
+        ])
+    }
+
+    fn lemma(db: &MockDatabase, word: &str) -> Lemma {
+        Lemma(db.lookup_interned_word(word.into()).unwrap())
+    }
+
+    fn weight(db: &MockDatabase, source: SourceId, weighting: Weighting, word: &str) -> f64 {
+        db.weighted_source_counts(source, weighting)
+            .get(&lemma(db, word))
+            .cloned()
+            .unwrap_or_default()
+            .value()
+    }
+
+    #[test]
+    fn test_frequencies() {
+        let mut db = make_db();
+        let source = db.intern_source("uenio uenimus uenit".to_string());
+        let weighting = Weighting::new(LitSubset::from_sources(&[source]));
+
+        let weights = db.occurrence_weights(source, weighting.clone());
+        assert_eq!(weights.len(), 3);
+        for lemmas in weights.values() {
+            let total: Weight = lemmas.iter().map(|(_, w)| *w).sum();
+            assert!((total.value() - 1.0).abs() < 1e-9);
+        }
+
+        // Two unambiguous uenio against none for ueneo
+        assert!((weight(&db, source, weighting.clone(), "uenio") - 2.75).abs() < 1e-9);
+        assert!((weight(&db, source, weighting, "ueneo") - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_context() {
+        let mut db = make_db();
+        let source =
+            db.intern_source("uenio roma\nuenimus roma\nuenibat pretio\npretio uenit".to_string());
+        let weighting = Weighting::new(LitSubset::from_sources(&[source]));
+
+        // By frequency alone, uenio is more likely
+        assert!((weight(&db, source, weighting.clone(), "ueneo") - 1.4).abs() < 1e-9);
+
+        // But ueneo was found near pretium
+        let with_context = weight(&db, source, weighting.with_context(1), "ueneo");
+        assert!((with_context - (1.0 + 0.8 / 1.3)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overrides_are_certain() {
+        let mut db = make_db();
+        let source = db.intern_source("uenit uenit".to_string());
+        let mut overrides = (*db.overrides()).clone();
+        overrides.set(source, 0, 1, Some(lemma(&db, "ueneo")));
+        db.set_overrides(Arc::new(overrides));
+
+        let weighting = Weighting::new(LitSubset::from_sources(&[source]));
+        let ueneo = weight(&db, source, weighting.clone(), "ueneo");
+        assert!((ueneo - (1.0 + 2.0 / 3.0)).abs() < 1e-9);
+        assert!((weight(&db, source, weighting, "uenio") - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_count_modes() {
+        let mut db = make_db();
+        let source = db.intern_source("uenio uenimus uenit".to_string());
+        let lit = LitSubset::from_sources(&[source]);
+        let weighted = CountMode::Weighted(Weighting::new(lit.clone()));
+
+        let uenio = lemma(&db, "uenio");
+        let count = |mode| db.count_lemma_occurrences_subset(uenio, lit.clone(), mode);
+        assert_eq!(count(CountMode::Every), Weight::new(3.0));
+        assert!((count(weighted.clone()).value() - 2.75).abs() < 1e-9);
+
+        // Each occurrence weighs one in total, while it counts under both lemmas otherwise
+        let author = AuthorId::from_integer(0);
+        db.set_associated_author(source, author);
+        let authors = |mode| db.authors_count(lit.clone(), mode)[&author];
+        assert_eq!(authors(CountMode::Every), Weight::new(4.0));
+        assert!((authors(weighted).value() - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_nan() {
+        Weight::new(f64::NAN);
+    }
+}
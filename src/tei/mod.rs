@@ -0,0 +1,323 @@
+//! Sources encoded in TEI XML (i.e. the Perseus or the DigilibLT editions)
+//! The text is taken from the `<body>`, with a line for each verse (`<l>`) and each paragraph,
+//! leaving out the notes, the headings and the variant readings of the apparatus.
+//! The structure of the edition (`<div n>`, `<l n>`, `<milestone n>`) makes up the citations,
+//! and the `<teiHeader>` gives the author, the title and the date of the work
+
+use crate::authors_chrono::TimeSpan;
+use crate::citations::{CitationScheme, Reference};
+
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+
+/// The extension of the sources in TEI XML
+pub const EXTENSION: &str = "xml";
+
+// The elements whose content is not part of the text
+const SKIPPED: [&str; 7] = ["note", "head", "rdg", "rdgGrp", "wit", "fw", "teiHeader"];
+
+// The elements that make up a line of their own
+const BLOCKS: [&str; 4] = ["p", "ab", "lg", "sp"];
+
+/// The content of a TEI file
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TeiDocument {
+    text: String,
+    citations: CitationScheme,
+    author: Option<String>,
+    title: Option<String>,
+    date: Option<TimeSpan>,
+}
+
+impl TeiDocument {
+    /// Read a TEI file
+    pub fn parse(xml: &str) -> Result<Self, roxmltree::Error> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+
+        let mut res = TeiDocument::default();
+        if let Some(header) = find(root, "teiHeader") {
+            let title_stmt = find(header, "titleStmt");
+            res.author = title_stmt.and_then(|t| find(t, "author")).and_then(text_of);
+            res.title = title_stmt.and_then(|t| find(t, "title")).and_then(text_of);
+            res.date = header
+                .descendants()
+                .filter(|n| n.has_tag_name("date") || n.has_tag_name("origDate"))
+                .find_map(date_of);
+        }
+
+        // Note, a file with no body (i.e. a bare fragment) is read as a whole
+        let body = find(root, "body").unwrap_or(root);
+        let mut builder = Builder::default();
+        builder.read(body);
+        builder.end_line();
+
+        res.text = builder.lines.join("\n");
+        res.citations = builder.citations;
+        Ok(res)
+    }
+
+    /// The text, with a line for each verse or paragraph
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The references given by the structure of the edition
+    pub fn citations(&self) -> &CitationScheme {
+        &self.citations
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// When the work was written, if the header says so
+    pub fn date(&self) -> Option<&TimeSpan> {
+        self.date.as_ref()
+    }
+}
+
+// The first element with a name, among the descendants
+fn find<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+// All the text in an element, with the whitespace collapsed
+fn text_of(node: Node) -> Option<String> {
+    let text: Vec<_> = node
+        .descendants()
+        .filter(|n| n.is_text())
+        .flat_map(|n| n.text().unwrap_or("").split_whitespace())
+        .collect();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.join(" "))
+    }
+}
+
+// Read a year in the TEI format (i.e. `-0019` or `0079-08-24`)
+fn year(s: &str) -> Option<i32> {
+    let (sign, digits) = match s.trim().strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s.trim()),
+    };
+    let digits = digits.split('-').next()?;
+    digits.parse::<i32>().ok().map(|y| sign * y)
+}
+
+fn date_of(node: Node) -> Option<TimeSpan> {
+    let attribute = |names: &[&str]| names.iter().find_map(|&name| node.attribute(name));
+    let (start, end) = match attribute(&["when"]).and_then(year) {
+        Some(when) => (when, when),
+        None => (
+            attribute(&["notBefore", "from"]).and_then(year)?,
+            attribute(&["notAfter", "to"]).and_then(year)?,
+        ),
+    };
+
+    if start > end {
+        return None;
+    }
+    // Note, a year out of the calendar makes no date
+    Some(TimeSpan::new(
+        NaiveDate::from_ymd_opt(start, 1, 1)?,
+        NaiveDate::from_ymd_opt(end, 1, 1)?,
+    ))
+}
+
+#[derive(Debug, Default)]
+struct Builder {
+    lines: Vec<String>,
+    current: String,
+    citations: CitationScheme,
+    // The `n` of the enclosing divisions
+    divisions: Vec<String>,
+}
+
+impl Builder {
+    fn end_line(&mut self) {
+        let line = self
+            .current
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !line.is_empty() {
+            self.lines.push(line);
+        }
+        self.current.clear();
+    }
+
+    // The line that the next text is going to be in
+    fn line_no(&self) -> usize {
+        let pending = !self.current.trim().is_empty();
+        self.lines.len() + pending as usize
+    }
+
+    // The citation prefix given by the enclosing divisions
+    fn prefix(&self) -> String {
+        self.divisions.join(".")
+    }
+
+    fn cite(&mut self, n: &str, line_no: usize) {
+        let prefix = self.prefix();
+        let prefix = if prefix.is_empty() {
+            prefix
+        } else {
+            prefix + "."
+        };
+
+        // Note, the verses that are not numbered are counted from the last one that is
+        let reference = match n.parse::<usize>() {
+            Ok(start) => Reference::new_counting(prefix, start),
+            Err(_) => Reference::new(prefix + n),
+        };
+        self.citations.insert(line_no, reference);
+    }
+
+    fn read(&mut self, node: Node) {
+        for child in node.children() {
+            if child.is_text() {
+                self.current.push_str(child.text().unwrap_or(""));
+                self.current.push(' ');
+                continue;
+            }
+            if !child.is_element() {
+                continue;
+            }
+
+            let name = child.tag_name().name();
+            match name {
+                _ if SKIPPED.contains(&name) => {}
+                // Note, of the apparatus only the reading of the edition is kept
+                "app" => {
+                    if let Some(lemma) = child.children().find(|n| n.has_tag_name("lem")) {
+                        self.read(lemma);
+                    }
+                }
+                "lb" => self.end_line(),
+                "milestone" => {
+                    if let Some(n) = child.attribute("n") {
+                        self.cite(n, self.line_no());
+                    }
+                }
+                "div" | "div1" | "div2" | "div3" => {
+                    self.end_line();
+                    let n = child.attribute("n");
+                    if let Some(n) = n {
+                        self.divisions.push(n.to_string());
+                        let reference = Reference::new(self.prefix());
+                        self.citations.insert(self.line_no(), reference);
+                    }
+                    self.read(child);
+                    self.end_line();
+                    if n.is_some() {
+                        self.divisions.pop();
+                    }
+                }
+                "l" => {
+                    self.end_line();
+                    if let Some(n) = child.attribute("n") {
+                        self.cite(n, self.line_no());
+                    }
+                    self.read(child);
+                    self.end_line();
+                }
+                _ if BLOCKS.contains(&name) => {
+                    self.end_line();
+                    self.read(child);
+                    self.end_line();
+                }
+                _ => self.read(child),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AENEID: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader>
+    <fileDesc>
+      <titleStmt>
+        <title>Aeneis</title>
+        <author>P. Vergilius Maro</author>
+      </titleStmt>
+      <sourceDesc><bibl><date notBefore="-0029" notAfter="-0019"/></bibl></sourceDesc>
+    </fileDesc>
+  </teiHeader>
+  <text>
+    <body>
+      <div type="textpart" subtype="book" n="1">
+        <head>Liber I</head>
+        <l n="1">Arma uirumque cano, Troiae qui primus ab oris</l>
+        <l>Italiam, fato profugus, <app><lem>Lauiniaque</lem><rdg>Lauinaque</rdg></app> uenit</l>
+        <l n="3">litora<note>multum ille et terris</note></l>
+      </div>
+      <div type="textpart" subtype="book" n="2">
+        <l n="1">Conticuere omnes</l>
+      </div>
+    </body>
+  </text>
+</TEI>"#;
+
+    #[test]
+    fn test_text() {
+        let document = TeiDocument::parse(AENEID).unwrap();
+        assert_eq!(
+            document.text(),
+            "Arma uirumque cano, Troiae qui primus ab oris\n\
+             Italiam, fato profugus, Lauiniaque uenit\n\
+             litora\n\
+             Conticuere omnes"
+        );
+    }
+
+    #[test]
+    fn test_citations() {
+        let document = TeiDocument::parse(AENEID).unwrap();
+        let citations = document.citations();
+        assert_eq!(citations.cite(0), Some("1.1".to_string()));
+        assert_eq!(citations.cite(1), Some("1.2".to_string()));
+        assert_eq!(citations.cite(2), Some("1.3".to_string()));
+        assert_eq!(citations.cite(3), Some("2.1".to_string()));
+    }
+
+    #[test]
+    fn test_header() {
+        let document = TeiDocument::parse(AENEID).unwrap();
+        assert_eq!(document.author(), Some("P. Vergilius Maro"));
+        assert_eq!(document.title(), Some("Aeneis"));
+
+        let date = document.date().unwrap();
+        assert_eq!(*date.start(), NaiveDate::from_ymd(-29, 1, 1));
+        assert_eq!(*date.end(), NaiveDate::from_ymd(-19, 1, 1));
+
+        let document = TeiDocument::parse(
+            r#"<TEI><teiHeader><date notBefore="-0029" notAfter="999999999"/></teiHeader></TEI>"#,
+        )
+        .unwrap();
+        assert_eq!(document.date(), None);
+    }
+
+    #[test]
+    fn test_milestones() {
+        let document = TeiDocument::parse(
+            r#"<TEI><text><body><div n="1"><p><milestone unit="section" n="1"/>Quo usque tandem
+            <milestone unit="section" n="2"/><lb/>Nihilne te nocturnum</p></div></body></text></TEI>"#,
+        )
+        .unwrap();
+
+        assert_eq!(document.text(), "Quo usque tandem\nNihilne te nocturnum");
+        assert_eq!(document.citations().cite(0), Some("1.1".to_string()));
+        assert_eq!(document.citations().cite(1), Some("1.2".to_string()));
+    }
+}
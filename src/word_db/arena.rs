@@ -0,0 +1,369 @@
+//! An append-only interner, where the readers never lock nor allocate.
+//! The bytes of the words are copied in chunks that never move, their positions are kept
+//! in segments (by id) that never move either, and the ids are found by their words
+//! with an open addressing hash table. Only the writers take a lock, and nothing is freed
+//! before the arena is dropped (the tables that were outgrown included), so a reader
+//! can always finish with what it loaded.
+
+use std::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{ptr, slice, str};
+
+// The size of a chunk of bytes, unless a word is longer than that
+const CHUNK_SIZE: usize = 1 << 16;
+// The size of the first segment, each one is twice as big as the one before
+const FIRST_SEGMENT: usize = 1 << 10;
+// Enough segments for every u32 id
+const SEGMENTS: usize = 23;
+// The size of the first hash table, which is kept at most half full
+const FIRST_TABLE: usize = 1 << 11;
+
+// Where the bytes of a word are, a null pointer if no word has the id
+#[derive(Clone, Copy)]
+struct Entry {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Entry {
+            ptr: ptr::null(),
+            len: 0,
+        }
+    }
+}
+
+// The segment of an id, and the position of the id in it
+fn locate(index: usize) -> (usize, usize) {
+    let bucket = index / FIRST_SEGMENT + 1;
+    let segment = (usize::BITS - 1 - bucket.leading_zeros()) as usize;
+    (segment, index - FIRST_SEGMENT * ((1 << segment) - 1))
+}
+
+fn segment_size(segment: usize) -> usize {
+    FIRST_SEGMENT << segment
+}
+
+// FNV-1a, which needs no state and is fast enough on short words
+fn hash(s: &str) -> usize {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    }) as usize
+}
+
+// The ids of the words by their hash, each slot holding either 0 or an id plus one
+struct Table {
+    slots: Box<[AtomicU32]>,
+}
+
+impl Table {
+    fn new(size: usize) -> Self {
+        Table {
+            slots: (0..size).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    // Note, only the writer calls this, so the slot it finds empty stays so
+    fn insert(&self, s: &str, id: u32) {
+        let mask = self.slots.len() - 1;
+        let mut i = hash(s) & mask;
+        while self.slots[i].load(Ordering::Relaxed) != 0 {
+            i = (i + 1) & mask;
+        }
+        self.slots[i].store(id + 1, Ordering::Release);
+    }
+}
+
+// What only the writer touches
+struct Writer {
+    chunks: Vec<(*mut u8, usize)>,
+    used: usize,
+    tables: Vec<*mut Table>,
+}
+
+impl Writer {
+    // Copy some bytes in the current chunk (or in a new one, if they do not fit)
+    fn store(&mut self, bytes: &[u8]) -> *const u8 {
+        let fits = match self.chunks.last() {
+            Some(&(_, size)) => self.used + bytes.len() <= size,
+            None => false,
+        };
+        if !fits {
+            let size = CHUNK_SIZE.max(bytes.len());
+            let chunk = Box::into_raw(vec![0u8; size].into_boxed_slice()) as *mut u8;
+            self.chunks.push((chunk, size));
+            self.used = 0;
+        }
+
+        let (chunk, _) = *self.chunks.last().unwrap();
+        // SAFETY: the bytes fit in the chunk after `used`, where no reader looks yet
+        unsafe {
+            let dest = chunk.add(self.used);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+            self.used += bytes.len();
+            dest
+        }
+    }
+}
+
+/// The words and their ids, where the ids are given in order
+pub struct Arena {
+    // One past the highest id, the entries below it are never written again
+    len: AtomicUsize,
+    count: AtomicUsize,
+    segments: [AtomicPtr<Entry>; SEGMENTS],
+    table: AtomicPtr<Table>,
+    writer: Mutex<Writer>,
+}
+
+// SAFETY: the raw pointers are only written by the writer, under its lock, and what they point
+// to is published (with `len` and the slots of the table) only once it is written
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
+impl Default for Arena {
+    fn default() -> Self {
+        let table = Box::into_raw(Box::new(Table::new(FIRST_TABLE)));
+        Arena {
+            len: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+            segments: Default::default(),
+            table: AtomicPtr::new(table),
+            writer: Mutex::new(Writer {
+                chunks: Vec::new(),
+                used: 0,
+                tables: vec![table],
+            }),
+        }
+    }
+}
+
+impl Arena {
+    /// The number of words
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// One past the highest id that was given
+    pub fn end(&self) -> u32 {
+        self.len.load(Ordering::Acquire) as u32
+    }
+
+    /// Get the word with an id, if there is one
+    pub fn get(&self, id: u32) -> Option<&str> {
+        let index = id as usize;
+        if index >= self.len.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let (segment, offset) = locate(index);
+        let base = self.segments[segment].load(Ordering::Acquire);
+        // Note, the ids that were skipped might have no segment
+        if base.is_null() {
+            return None;
+        }
+
+        // SAFETY: the segment was allocated before `len` went past the id, and the entry
+        // (with the bytes it points to) was written before that too, and is never written again
+        let entry = unsafe { *base.add(offset) };
+        if entry.ptr.is_null() {
+            return None;
+        }
+        Some(unsafe { str::from_utf8_unchecked(slice::from_raw_parts(entry.ptr, entry.len)) })
+    }
+
+    /// Get the id of a word, if it was interned
+    pub fn find(&self, s: &str) -> Option<u32> {
+        // SAFETY: a table is never freed before the arena
+        let table = unsafe { &*self.table.load(Ordering::Acquire) };
+        let mask = table.slots.len() - 1;
+        let mut i = hash(s) & mask;
+        loop {
+            match table.slots[i].load(Ordering::Acquire) {
+                0 => return None,
+                slot if self.get(slot - 1) == Some(s) => return Some(slot - 1),
+                _ => i = (i + 1) & mask,
+            }
+        }
+    }
+
+    /// Get the id of a word, interning it if needed, and whether it was new
+    pub fn intern(&self, s: &str) -> (u32, bool) {
+        if let Some(id) = self.find(s) {
+            return (id, false);
+        }
+
+        let mut writer = self.writer.lock().unwrap();
+        // Note, another writer could have interned the word while this one waited
+        if let Some(id) = self.find(s) {
+            return (id, false);
+        }
+
+        let id = self.end();
+        self.push(&mut writer, id, s);
+        (id, true)
+    }
+
+    /// Intern a word with a given id, which must be past the ones that were given already
+    /// (i.e. to read back the words of another arena, in the order of their ids)
+    pub fn insert(&self, id: u32, s: &str) {
+        let mut writer = self.writer.lock().unwrap();
+        assert!(id >= self.end(), "The ids of an arena are given in order");
+        self.push(&mut writer, id, s);
+    }
+
+    fn push(&self, writer: &mut Writer, id: u32, s: &str) {
+        assert!(id < u32::MAX, "Too many words for an arena");
+        let index = id as usize;
+        let entry = Entry {
+            ptr: writer.store(s.as_bytes()),
+            len: s.len(),
+        };
+
+        let (segment, offset) = locate(index);
+        let mut base = self.segments[segment].load(Ordering::Acquire);
+        if base.is_null() {
+            let entries = vec![Entry::default(); segment_size(segment)].into_boxed_slice();
+            base = Box::into_raw(entries) as *mut Entry;
+            self.segments[segment].store(base, Ordering::Release);
+        }
+        // SAFETY: the id is past `len`, so no reader looks at its entry yet
+        unsafe { base.add(offset).write(entry) };
+        self.len.store(index + 1, Ordering::Release);
+        let count = self.count.fetch_add(1, Ordering::AcqRel) + 1;
+
+        // SAFETY: the tables are only freed with the arena
+        let table = unsafe { &**writer.tables.last().unwrap() };
+        if count * 2 <= table.slots.len() {
+            table.insert(s, id);
+            return;
+        }
+
+        // Outgrown, the table is built again twice as big (with the new word, past `len` now)
+        let table = Table::new(table.slots.len() * 2);
+        for (id, word) in self.iter() {
+            table.insert(word, id);
+        }
+        let table = Box::into_raw(Box::new(table));
+        self.table.store(table, Ordering::Release);
+        writer.tables.push(table);
+    }
+
+    /// The words, with their ids
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        (0..self.end()).filter_map(move |id| self.get(id).map(|s| (id, s)))
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let writer = self.writer.get_mut().unwrap();
+        for &(chunk, size) in &writer.chunks {
+            // SAFETY: the chunks were boxed slices of this size, and no reader is left
+            unsafe { drop(Box::from_raw(ptr::slice_from_raw_parts_mut(chunk, size))) };
+        }
+
+        for &table in &writer.tables {
+            // SAFETY: as above, for the tables
+            unsafe { drop(Box::from_raw(table)) };
+        }
+
+        for (segment, base) in self.segments.iter_mut().enumerate() {
+            let base = *base.get_mut();
+            if !base.is_null() {
+                let size = segment_size(segment);
+                // SAFETY: as above, for the segments
+                unsafe { drop(Box::from_raw(ptr::slice_from_raw_parts_mut(base, size))) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(FIRST_SEGMENT - 1), (0, FIRST_SEGMENT - 1));
+        assert_eq!(locate(FIRST_SEGMENT), (1, 0));
+        assert_eq!(locate(3 * FIRST_SEGMENT), (2, 0));
+        let (segment, offset) = locate(u32::MAX as usize);
+        assert!(segment < SEGMENTS && offset < segment_size(segment));
+    }
+
+    #[test]
+    fn test_intern() {
+        let arena = Arena::default();
+        assert_eq!(arena.intern("arma"), (0, true));
+        assert_eq!(arena.intern("uirumque"), (1, true));
+        assert_eq!(arena.intern("arma"), (0, false));
+        assert_eq!(arena.intern(""), (2, true));
+        assert_eq!(arena.get(1), Some("uirumque"));
+        assert_eq!(arena.get(2), Some(""));
+        assert_eq!(arena.get(3), None);
+        assert_eq!(arena.find("cano"), None);
+
+        // Past the first chunk, segment and table
+        let words: Vec<_> = (0..10_000).map(|i| format!("uerbum{}", i)).collect();
+        for word in &words {
+            arena.intern(word);
+        }
+        let long = "a".repeat(CHUNK_SIZE + 1);
+        arena.intern(&long);
+        assert_eq!(arena.len(), words.len() + 4);
+        assert!(words
+            .iter()
+            .all(|word| arena.get(arena.find(word).unwrap()) == Some(word.as_str())));
+        assert_eq!(arena.find(&long), Some(arena.end() - 1));
+    }
+
+    #[test]
+    fn test_gaps() {
+        let arena = Arena::default();
+        arena.insert(2, "arma");
+        arena.insert(5, "cano");
+        assert_eq!(arena.get(0), None);
+        assert_eq!(arena.find("cano"), Some(5));
+        assert_eq!(arena.intern("troiae"), (6, true));
+        assert_eq!(
+            arena.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![2, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_threads() {
+        let arena = Arc::new(Arena::default());
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let arena = Arc::clone(&arena);
+                thread::spawn(move || {
+                    // Note, half of the words are shared among the threads
+                    for i in 0..5_000 {
+                        let word = match i % 2 {
+                            0 => format!("uerbum{}", i),
+                            _ => format!("uerbum{}_{}", i, t),
+                        };
+                        let (id, _) = arena.intern(&word);
+                        assert_eq!(arena.get(id), Some(word.as_str()));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(arena.len(), 2_500 + 4 * 2_500);
+        assert_eq!(arena.iter().count(), arena.len());
+    }
+}
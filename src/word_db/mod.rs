@@ -1,68 +1,142 @@
 use super::latin_utilities::NormalizedLatinString;
-use bimap::BiMap;
-use std::cell::{Ref, RefCell};
+use std::fmt;
 use std::sync::{Arc, Mutex};
 
+pub mod arena;
+pub mod search;
 pub use super::query_system::ids::WordId;
+pub use search::WordPattern;
+
+use arena::Arena;
+use search::TrigramIndex;
 
 pub trait WordDatabase {
     fn intern_word(&self, s: NormalizedLatinString) -> WordId;
     fn lookup_word(&self, id: WordId) -> NormalizedLatinString;
     fn lookup_interned_word(&self, s: NormalizedLatinString) -> Option<WordId>;
+    /// Get the id of a (normalized) word without copying it, if it was interned
+    fn lookup_interned_str(&self, s: &str) -> Option<WordId>;
+    /// Get an interned word without copying it
+    fn lookup_word_str(&self, id: WordId) -> &str;
+    /// Get the interned words that match a pattern
+    fn search_words(&self, pattern: &WordPattern) -> Vec<WordId>;
 }
 
-type Mapping = BiMap<WordId, NormalizedLatinString>;
-
-#[derive(Debug, Default, Clone)]
+/// The interned words, shared among the clones
+/// Note, the lookups never lock, only the new words (and the searches) do
+#[derive(Default, Clone)]
 pub struct WordDb {
-    words: Arc<Mutex<RefCell<Mapping>>>,
+    words: Arc<Arena>,
+    index: Arc<Mutex<TrigramIndex>>,
 }
 
 impl WordDb {
-    fn next_id(words: Ref<Mapping>) -> WordId {
-        // TODO: Check
-        let mut candidate = words.len() as u32;
-        while words.contains_left(&WordId::from_integer(candidate)) {
-            candidate += 1;
+    /// Instantiate a database with the words previously interned in another one
+    pub fn from_words(words: impl IntoIterator<Item = (WordId, NormalizedLatinString)>) -> Self {
+        let mut words: Vec<_> = words.into_iter().collect();
+        words.sort_by_key(|(id, _)| *id);
+
+        let db = WordDb::default();
+        {
+            let mut index = db.index.lock().unwrap();
+            for (id, s) in words {
+                db.words.insert(id.as_integer(), s.inner());
+                index.insert(id, s.inner());
+            }
         }
+        db
+    }
 
-        WordId::from_integer(candidate)
+    /// Get all the interned words, with their ids
+    pub fn words(&self) -> Vec<(WordId, NormalizedLatinString)> {
+        self.words
+            .iter()
+            .map(|(id, s)| {
+                let s = NormalizedLatinString::instantiate(s);
+                (WordId::from_integer(id), s)
+            })
+            .collect()
     }
 
     pub fn len(&self) -> usize {
-        self.words.lock().unwrap().borrow().len()
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Get an interned word, if there is one with the id
+    pub fn get(&self, id: WordId) -> Option<&str> {
+        self.words.get(id.as_integer())
+    }
+
+    /// Get the id of a (normalized) word, if it was interned
+    pub fn find(&self, s: &str) -> Option<WordId> {
+        self.words.find(s).map(WordId::from_integer)
+    }
+}
+
+impl fmt::Debug for WordDb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WordDb").field("len", &self.len()).finish()
     }
 }
 
 impl WordDatabase for WordDb {
     fn intern_word(&self, s: NormalizedLatinString) -> WordId {
-        let lock = self.words.lock().unwrap();
-
-        if let Some(id) = lock.borrow().get_by_right(&s).cloned() {
-            return id;
+        let (id, new) = self.words.intern(s.inner());
+        let id = WordId::from_integer(id);
+        if new {
+            self.index.lock().unwrap().insert(id, s.inner());
         }
-
-        let id = WordDb::next_id(lock.borrow());
-        lock.borrow_mut().insert(id, s);
         id
     }
 
     fn lookup_word(&self, id: WordId) -> NormalizedLatinString {
-        self.words
-            .lock()
-            .unwrap()
-            .borrow()
-            .get_by_left(&id)
-            .expect("Shouldn't be possible to get a spurious id")
-            .clone()
+        NormalizedLatinString::instantiate(self.lookup_word_str(id))
     }
 
     fn lookup_interned_word(&self, s: NormalizedLatinString) -> Option<WordId> {
-        self.words
-            .lock()
-            .unwrap()
-            .borrow()
-            .get_by_right(&s)
-            .cloned()
+        self.lookup_interned_str(s.inner())
+    }
+
+    fn lookup_interned_str(&self, s: &str) -> Option<WordId> {
+        self.find(s)
+    }
+
+    fn lookup_word_str(&self, id: WordId) -> &str {
+        self.get(id)
+            .expect("Shouldn't be possible to get a spurious id")
+    }
+
+    fn search_words(&self, pattern: &WordPattern) -> Vec<WordId> {
+        let index = self.index.lock().unwrap();
+        index.search(pattern, |id| self.get(id).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        let db = WordDb::default();
+        let arma = db.intern_word("arma".into());
+        let cano = db.intern_word("cano".into());
+        assert_eq!(db.intern_word("arma".into()), arma);
+        assert_eq!(db.lookup_word_str(cano), "cano");
+        assert_eq!(db.lookup_interned_word("troiae".into()), None);
+        assert_eq!(db.lookup_interned_str("arma"), Some(arma));
+
+        let again = WordDb::from_words(db.words());
+        assert_eq!(again.len(), 2);
+        assert_eq!(again.find("arma"), Some(arma));
+        assert_eq!(
+            again.search_words(&WordPattern::regex("^ca").unwrap()),
+            vec![cano]
+        );
+        assert!(again.intern_word("troiae".into()) > cano);
     }
 }
@@ -0,0 +1,331 @@
+//! Search over the interned words, by wildcard (`amaui*`, `*bilis`), by regex or by
+//! edit distance. The words are indexed by their trigrams (with `^` and `$` marking their
+//! start and end), so that only the words that share the trigrams a pattern requires are checked
+
+use super::WordId;
+use crate::latin_utilities::StandardLatinConverter;
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+type Trigram = [char; 3];
+
+// The trigrams of a piece of text, as it is
+fn trigrams_of(s: &str) -> HashSet<Trigram> {
+    let chars: Vec<char> = s.chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+// The trigrams of a word, with its start and end marked
+fn trigrams(word: &str) -> HashSet<Trigram> {
+    trigrams_of(&format!("^{}$", word))
+}
+
+// The runs of letters a regex cannot match without (i.e. `amau` and `it$` in `^amau.*it$`)
+// Note, this is conservative: anything in groups, classes or before a quantifier is skipped,
+// and a regex with alternatives requires nothing
+fn required_literals(pattern: &str) -> Vec<String> {
+    if pattern.contains('|') {
+        return Vec::new();
+    }
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut res = Vec::new();
+    let mut run = String::new();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '^' if i == 0 => run.push('^'),
+            '$' if i == chars.len() - 1 && depth == 0 => run.push('$'),
+            c if c.is_alphabetic() && depth == 0 => run.extend(c.to_lowercase()),
+            '?' | '*' | '{' => {
+                run.pop();
+                res.push(std::mem::take(&mut run));
+            }
+            '+' => res.push(std::mem::take(&mut run)),
+            _ => {
+                res.push(std::mem::take(&mut run));
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    '[' => {
+                        while i < chars.len() && chars[i] != ']' {
+                            i += 1;
+                        }
+                    }
+                    '\\' => i += 1,
+                    _ => {}
+                }
+            }
+        }
+        i += 1;
+    }
+    res.push(run);
+
+    // A quantifier after a group or a class applies to something already skipped
+    res.retain(|run| !run.is_empty());
+    res
+}
+
+/// The edit (Levenshtein) distance between two words
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = prev + if ca == cb { 0 } else { 1 };
+            prev = row[j + 1];
+            row[j + 1] = substitution.min(prev + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// What to look for among the words
+#[derive(Debug, Clone)]
+pub enum WordPattern {
+    /// `*` stands for any letters (none included), `?` for a single one
+    Wildcard { pattern: String, regex: Regex },
+    /// A regex, matched against the normalized words (`^` and `$` anchor it)
+    Regex(Regex),
+    /// The words within some edits (insertions, deletions or substitutions) of another one
+    Fuzzy { word: String, distance: usize },
+}
+
+impl WordPattern {
+    /// A wildcard pattern, i.e. `amaui*` or `*bilis`, whose letters are normalized as the words are
+    pub fn wildcard(pattern: &str, converter: &StandardLatinConverter) -> Self {
+        let mut normalized = String::new();
+        let mut regex = String::from("^");
+        let mut segment = String::new();
+        for c in pattern.chars().chain(std::iter::once('*')) {
+            if c != '*' && c != '?' {
+                segment.push(c);
+                continue;
+            }
+
+            let letters = converter.convert(&segment);
+            normalized.push_str(letters.inner());
+            regex.push_str(&regex::escape(letters.inner()));
+            segment.clear();
+            match c {
+                '?' => {
+                    normalized.push('?');
+                    regex.push('.');
+                }
+                _ => {
+                    normalized.push('*');
+                    regex.push_str(".*");
+                }
+            }
+        }
+
+        // Note, the last `*` was only there to close the last segment
+        normalized.pop();
+        regex.truncate(regex.len() - 2);
+        regex.push('$');
+
+        WordPattern::Wildcard {
+            pattern: normalized,
+            regex: Regex::new(&regex).expect("An escaped pattern is always a valid regex"),
+        }
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(WordPattern::Regex(Regex::new(pattern)?))
+    }
+
+    pub fn fuzzy(word: &str, distance: usize, converter: &StandardLatinConverter) -> Self {
+        WordPattern::Fuzzy {
+            word: converter.convert(word).inner().to_string(),
+            distance,
+        }
+    }
+
+    /// Whether a word matches the pattern
+    pub fn matches(&self, word: &str) -> bool {
+        match self {
+            WordPattern::Wildcard { regex, .. } | WordPattern::Regex(regex) => regex.is_match(word),
+            WordPattern::Fuzzy {
+                word: other,
+                distance,
+            } => {
+                let (a, b) = (word.chars().count(), other.chars().count());
+                (a.max(b) - a.min(b)) <= *distance && edit_distance(word, other) <= *distance
+            }
+        }
+    }
+
+    // The trigrams any matching word has
+    fn required(&self) -> HashSet<Trigram> {
+        match self {
+            WordPattern::Wildcard { pattern, .. } => format!("^{}$", pattern)
+                .split(['*', '?'])
+                .flat_map(trigrams_of)
+                .collect(),
+            WordPattern::Regex(regex) => required_literals(regex.as_str())
+                .iter()
+                .flat_map(|run| trigrams_of(run))
+                .collect(),
+            WordPattern::Fuzzy { .. } => HashSet::new(),
+        }
+    }
+}
+
+/// An index of the words by their trigrams
+#[derive(Debug, Default, Clone)]
+pub struct TrigramIndex {
+    postings: HashMap<Trigram, Vec<WordId>>,
+    all: Vec<WordId>,
+}
+
+impl TrigramIndex {
+    pub fn insert(&mut self, id: WordId, word: &str) {
+        for trigram in trigrams(word) {
+            self.postings.entry(trigram).or_default().push(id);
+        }
+        self.all.push(id);
+    }
+
+    // The words that might match, a superset of the ones that do
+    fn candidates(&self, pattern: &WordPattern) -> Vec<WordId> {
+        if let WordPattern::Fuzzy { word, distance } = pattern {
+            // Note, each edit breaks at most three of the trigrams of the word
+            let wanted = trigrams(word);
+            let threshold = wanted.len().saturating_sub(3 * distance);
+            if threshold == 0 {
+                return self.all.clone();
+            }
+
+            let mut shared: HashMap<WordId, usize> = HashMap::new();
+            for trigram in &wanted {
+                for &id in self.postings.get(trigram).into_iter().flatten() {
+                    *shared.entry(id).or_insert(0) += 1;
+                }
+            }
+            return shared
+                .into_iter()
+                .filter(|&(_, count)| count >= threshold)
+                .map(|(id, _)| id)
+                .collect();
+        }
+
+        let required = pattern.required();
+        if required.is_empty() {
+            return self.all.clone();
+        }
+
+        // Start from the rarest trigram, so that the intersection stays small
+        let mut postings = Vec::new();
+        for trigram in &required {
+            match self.postings.get(trigram) {
+                Some(ids) => postings.push(ids),
+                None => return Vec::new(),
+            }
+        }
+        postings.sort_by_key(|ids| ids.len());
+
+        let mut res: HashSet<WordId> = postings[0].iter().cloned().collect();
+        for ids in &postings[1..] {
+            let ids: HashSet<_> = ids.iter().collect();
+            res.retain(|id| ids.contains(id));
+        }
+        res.into_iter().collect()
+    }
+
+    /// The words that match a pattern, sorted (by distance first, for a fuzzy search)
+    pub fn search<'a>(
+        &self,
+        pattern: &WordPattern,
+        word: impl Fn(WordId) -> &'a str,
+    ) -> Vec<WordId> {
+        let mut res: Vec<_> = self
+            .candidates(pattern)
+            .into_iter()
+            .map(|id| (id, word(id)))
+            .filter(|(_, s)| pattern.matches(s))
+            .collect();
+
+        match pattern {
+            WordPattern::Fuzzy { word, .. } => {
+                res.sort_by_key(|&(_, s)| (edit_distance(s, word), s))
+            }
+            _ => res.sort_by_key(|&(_, s)| s),
+        }
+        res.into_iter().map(|(id, _)| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDS: [&str; 8] = [
+        "amaui",
+        "amauit",
+        "amauerunt",
+        "amabilis",
+        "nobilis",
+        "rosa",
+        "rosam",
+        "a",
+    ];
+
+    fn search(pattern: &WordPattern) -> Vec<&'static str> {
+        let mut index = TrigramIndex::default();
+        for (i, word) in WORDS.iter().enumerate() {
+            index.insert(WordId::from_integer(i as u32), word);
+        }
+
+        index
+            .search(pattern, |id| WORDS[id.as_integer() as usize])
+            .into_iter()
+            .map(|id| WORDS[id.as_integer() as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let converter = StandardLatinConverter::default();
+        let wildcard = |s| search(&WordPattern::wildcard(s, &converter));
+        assert_eq!(wildcard("amaui*"), vec!["amaui", "amauit"]);
+        assert_eq!(wildcard("*bilis"), vec!["amabilis", "nobilis"]);
+        assert_eq!(wildcard("Amav?t"), vec!["amauit"]);
+        assert_eq!(wildcard("ros*"), vec!["rosa", "rosam"]);
+        assert_eq!(wildcard("a"), vec!["a"]);
+        assert_eq!(wildcard("*").len(), WORDS.len());
+    }
+
+    #[test]
+    fn test_regex() {
+        let regex = |s| search(&WordPattern::regex(s).unwrap());
+        assert_eq!(regex("^ama(ui|bi)"), vec!["amabilis", "amaui", "amauit"]);
+        assert_eq!(regex("it$"), vec!["amauit"]);
+        assert_eq!(regex("^rosam?$"), vec!["rosa", "rosam"]);
+        assert_eq!(regex("^(nob|rosa)"), vec!["nobilis", "rosa", "rosam"]);
+        assert!(WordPattern::regex("(").is_err());
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let converter = StandardLatinConverter::default();
+        let fuzzy = |s, d| search(&WordPattern::fuzzy(s, d, &converter));
+        assert_eq!(fuzzy("amauit", 0), vec!["amauit"]);
+        assert_eq!(fuzzy("amauit", 1), vec!["amauit", "amaui"]);
+        assert_eq!(fuzzy("rosas", 1), vec!["rosa", "rosam"]);
+        assert_eq!(fuzzy("nobiles", 2), vec!["nobilis"]);
+        assert_eq!(edit_distance("amabilis", "nobilis"), 3);
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(required_literals("^amau.*it$"), vec!["^amau", "it$"]);
+        assert_eq!(required_literals("^rosam?$"), vec!["^rosa", "$"]);
+        assert_eq!(required_literals("[abc]+bilis"), vec!["bilis"]);
+        assert!(required_literals("amo|amas").is_empty());
+    }
+}